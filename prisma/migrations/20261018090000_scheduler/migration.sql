@@ -0,0 +1,21 @@
+-- AlterTable
+ALTER TABLE "User" ADD COLUMN "scheduler" TEXT NOT NULL DEFAULT 'sm2';
+
+-- AlterTable
+ALTER TABLE "Word" ADD COLUMN "easeFactor" REAL NOT NULL DEFAULT 2.5;
+ALTER TABLE "Word" ADD COLUMN "interval" REAL NOT NULL DEFAULT 0;
+ALTER TABLE "Word" ADD COLUMN "stability" REAL NOT NULL DEFAULT 0;
+ALTER TABLE "Word" ADD COLUMN "difficulty" REAL NOT NULL DEFAULT 0;
+
+-- Convert levels of the old fixed ladder (1h, 12h, 24h, 32h, 48h, 72h) into days
+UPDATE "Word" SET
+    "interval" = CASE "rememberLevel"
+        WHEN 0 THEN 1.0 / 24
+        WHEN 1 THEN 0.5
+        WHEN 2 THEN 1.0
+        WHEN 3 THEN 32.0 / 24
+        WHEN 4 THEN 2.0
+        ELSE 3.0
+    END,
+    "difficulty" = 5.0;
+UPDATE "Word" SET "stability" = "interval";
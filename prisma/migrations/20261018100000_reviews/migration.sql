@@ -0,0 +1,19 @@
+-- CreateTable
+CREATE TABLE "Review" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "chatId" BIGINT NOT NULL,
+    "outcome" TEXT NOT NULL,
+    "levelBefore" INTEGER NOT NULL,
+    "levelAfter" INTEGER NOT NULL,
+    "answer" TEXT,
+    "latency" INTEGER,
+    "wordId" TEXT NOT NULL,
+    CONSTRAINT "Review_wordId_fkey" FOREIGN KEY ("wordId") REFERENCES "Word" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "Review_wordId_idx" ON "Review"("wordId");
+
+-- CreateIndex
+CREATE INDEX "Review_chatId_idx" ON "Review"("chatId");
@@ -0,0 +1,46 @@
+-- AlterTable
+ALTER TABLE "User" ADD COLUMN "activeDeckId" TEXT;
+
+-- CreateTable
+CREATE TABLE "Deck" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "name" TEXT NOT NULL,
+    "fromLang" TEXT,
+    "toLang" TEXT,
+    "remindersEnabled" BOOLEAN NOT NULL DEFAULT true,
+    "chatId" BIGINT NOT NULL,
+    CONSTRAINT "Deck_chatId_fkey" FOREIGN KEY ("chatId") REFERENCES "User" ("chatId") ON DELETE RESTRICT ON UPDATE CASCADE
+);
+
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_Word" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "chatId" BIGINT NOT NULL,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updatedAt" DATETIME NOT NULL,
+    "word" TEXT NOT NULL,
+    "translate" TEXT NOT NULL,
+    "rememberLevel" INTEGER NOT NULL DEFAULT 0,
+    "easeFactor" REAL NOT NULL DEFAULT 2.5,
+    "interval" REAL NOT NULL DEFAULT 0,
+    "stability" REAL NOT NULL DEFAULT 0,
+    "difficulty" REAL NOT NULL DEFAULT 0,
+    "nextRemindAt" DATETIME NOT NULL,
+    "remindedAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "status" TEXT NOT NULL DEFAULT 'active',
+    "userChatId" BIGINT NOT NULL,
+    "deckId" TEXT,
+    CONSTRAINT "Word_userChatId_fkey" FOREIGN KEY ("userChatId") REFERENCES "User" ("chatId") ON DELETE RESTRICT ON UPDATE CASCADE,
+    CONSTRAINT "Word_deckId_fkey" FOREIGN KEY ("deckId") REFERENCES "Deck" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+INSERT INTO "new_Word" ("chatId", "createdAt", "difficulty", "easeFactor", "id", "interval", "nextRemindAt", "rememberLevel", "remindedAt", "stability", "status", "translate", "updatedAt", "userChatId", "word") SELECT "chatId", "createdAt", "difficulty", "easeFactor", "id", "interval", "nextRemindAt", "rememberLevel", "remindedAt", "stability", "status", "translate", "updatedAt", "userChatId", "word" FROM "Word";
+DROP TABLE "Word";
+ALTER TABLE "new_Word" RENAME TO "Word";
+CREATE INDEX "Word_deckId_idx" ON "Word"("deckId");
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
+
+-- CreateIndex
+CREATE UNIQUE INDEX "Deck_chatId_name_key" ON "Deck"("chatId", "name");
@@ -0,0 +1,57 @@
+-- CreateTable
+CREATE TABLE "Translation" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "text" TEXT NOT NULL,
+    "position" INTEGER NOT NULL,
+    "note" TEXT,
+    "wordId" TEXT NOT NULL,
+    CONSTRAINT "Translation_wordId_fkey" FOREIGN KEY ("wordId") REFERENCES "Word" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "Translation_wordId_idx" ON "Translation"("wordId");
+
+-- Split comma joined translations into rows
+WITH RECURSIVE "split" ("wordId", "position", "text", "rest") AS (
+    SELECT "id", -1, '', "translate" || ', ' FROM "Word"
+    UNION ALL
+    SELECT
+        "wordId",
+        "position" + 1,
+        trim(substr("rest", 1, instr("rest", ', ') - 1)),
+        substr("rest", instr("rest", ', ') + 2)
+    FROM "split"
+    WHERE "rest" <> ''
+)
+INSERT INTO "Translation" ("id", "wordId", "position", "text")
+SELECT 'c' || lower(hex(randomblob(12))), "wordId", "position", "text"
+FROM "split"
+WHERE "position" >= 0 AND "text" <> '';
+
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_Word" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "chatId" BIGINT NOT NULL,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updatedAt" DATETIME NOT NULL,
+    "word" TEXT NOT NULL,
+    "rememberLevel" INTEGER NOT NULL DEFAULT 0,
+    "easeFactor" REAL NOT NULL DEFAULT 2.5,
+    "interval" REAL NOT NULL DEFAULT 0,
+    "stability" REAL NOT NULL DEFAULT 0,
+    "difficulty" REAL NOT NULL DEFAULT 0,
+    "nextRemindAt" DATETIME NOT NULL,
+    "remindedAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "status" TEXT NOT NULL DEFAULT 'active',
+    "userChatId" BIGINT NOT NULL,
+    "deckId" TEXT,
+    CONSTRAINT "Word_userChatId_fkey" FOREIGN KEY ("userChatId") REFERENCES "User" ("chatId") ON DELETE RESTRICT ON UPDATE CASCADE,
+    CONSTRAINT "Word_deckId_fkey" FOREIGN KEY ("deckId") REFERENCES "Deck" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+INSERT INTO "new_Word" ("chatId", "createdAt", "deckId", "difficulty", "easeFactor", "id", "interval", "nextRemindAt", "rememberLevel", "remindedAt", "stability", "status", "updatedAt", "userChatId", "word") SELECT "chatId", "createdAt", "deckId", "difficulty", "easeFactor", "id", "interval", "nextRemindAt", "rememberLevel", "remindedAt", "stability", "status", "updatedAt", "userChatId", "word" FROM "Word";
+DROP TABLE "Word";
+ALTER TABLE "new_Word" RENAME TO "Word";
+CREATE INDEX "Word_deckId_idx" ON "Word"("deckId");
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
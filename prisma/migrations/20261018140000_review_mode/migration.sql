@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "User" ADD COLUMN "reviewMode" TEXT NOT NULL DEFAULT 'typing';
@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "User" ADD COLUMN "strictness" TEXT NOT NULL DEFAULT 'lenient';
@@ -0,0 +1,31 @@
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_Word" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "chatId" BIGINT NOT NULL,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updatedAt" DATETIME NOT NULL,
+    "word" TEXT NOT NULL,
+    "normalized" TEXT NOT NULL,
+    "rememberLevel" INTEGER NOT NULL DEFAULT 0,
+    "easeFactor" REAL NOT NULL DEFAULT 2.5,
+    "interval" REAL NOT NULL DEFAULT 0,
+    "stability" REAL NOT NULL DEFAULT 0,
+    "difficulty" REAL NOT NULL DEFAULT 0,
+    "nextRemindAt" DATETIME NOT NULL,
+    "remindedAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "status" TEXT NOT NULL DEFAULT 'active',
+    "userChatId" BIGINT NOT NULL,
+    "deckId" TEXT,
+    CONSTRAINT "Word_userChatId_fkey" FOREIGN KEY ("userChatId") REFERENCES "User" ("chatId") ON DELETE RESTRICT ON UPDATE CASCADE,
+    CONSTRAINT "Word_deckId_fkey" FOREIGN KEY ("deckId") REFERENCES "Deck" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+-- SQLite can't fold the case of non-ASCII letters, existing words are normalized
+-- on start by `PrismaStorage::normalize_words`
+INSERT INTO "new_Word" ("chatId", "createdAt", "deckId", "difficulty", "easeFactor", "id", "interval", "nextRemindAt", "normalized", "rememberLevel", "remindedAt", "stability", "status", "updatedAt", "userChatId", "word") SELECT "chatId", "createdAt", "deckId", "difficulty", "easeFactor", "id", "interval", "nextRemindAt", '', "rememberLevel", "remindedAt", "stability", "status", "updatedAt", "userChatId", "word" FROM "Word";
+DROP TABLE "Word";
+ALTER TABLE "new_Word" RENAME TO "Word";
+CREATE INDEX "Word_deckId_idx" ON "Word"("deckId");
+CREATE INDEX "Word_chatId_normalized_idx" ON "Word"("chatId", "normalized");
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
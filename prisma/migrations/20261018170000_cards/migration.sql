@@ -0,0 +1,63 @@
+-- AlterTable
+ALTER TABLE "User" ADD COLUMN "directions" TEXT NOT NULL DEFAULT 'forward';
+
+-- AlterTable
+ALTER TABLE "Deck" ADD COLUMN "directions" TEXT;
+
+-- AlterTable
+ALTER TABLE "Review" ADD COLUMN "direction" TEXT NOT NULL DEFAULT 'forward';
+
+-- CreateTable
+CREATE TABLE "Card" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "direction" TEXT NOT NULL,
+    "rememberLevel" INTEGER NOT NULL DEFAULT 0,
+    "easeFactor" REAL NOT NULL DEFAULT 2.5,
+    "interval" REAL NOT NULL DEFAULT 0,
+    "stability" REAL NOT NULL DEFAULT 0,
+    "difficulty" REAL NOT NULL DEFAULT 0,
+    "nextRemindAt" DATETIME NOT NULL,
+    "remindedAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "wordId" TEXT NOT NULL,
+    CONSTRAINT "Card_wordId_fkey" FOREIGN KEY ("wordId") REFERENCES "Word" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "Card_wordId_direction_key" ON "Card"("wordId", "direction");
+
+-- CreateIndex
+CREATE INDEX "Card_nextRemindAt_idx" ON "Card"("nextRemindAt");
+
+-- Move scheduling of existing words to forward cards
+INSERT INTO "Card" ("id", "direction", "rememberLevel", "easeFactor", "interval", "stability", "difficulty", "nextRemindAt", "remindedAt", "wordId")
+SELECT 'c' || lower(hex(randomblob(12))), 'forward', "rememberLevel", "easeFactor", "interval", "stability", "difficulty", "nextRemindAt", "remindedAt", "id"
+FROM "Word";
+
+-- Reverse cards of existing words follow the schedule of forward ones,
+-- so a large vocabulary doesn't come due all at once
+INSERT INTO "Card" ("id", "direction", "nextRemindAt", "remindedAt", "wordId")
+SELECT 'c' || lower(hex(randomblob(12))), 'reverse', "nextRemindAt", "createdAt", "id"
+FROM "Word";
+
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_Word" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "chatId" BIGINT NOT NULL,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updatedAt" DATETIME NOT NULL,
+    "word" TEXT NOT NULL,
+    "normalized" TEXT NOT NULL,
+    "status" TEXT NOT NULL DEFAULT 'active',
+    "userChatId" BIGINT NOT NULL,
+    "deckId" TEXT,
+    CONSTRAINT "Word_userChatId_fkey" FOREIGN KEY ("userChatId") REFERENCES "User" ("chatId") ON DELETE RESTRICT ON UPDATE CASCADE,
+    CONSTRAINT "Word_deckId_fkey" FOREIGN KEY ("deckId") REFERENCES "Deck" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+INSERT INTO "new_Word" ("chatId", "createdAt", "deckId", "id", "normalized", "status", "updatedAt", "userChatId", "word") SELECT "chatId", "createdAt", "deckId", "id", "normalized", "status", "updatedAt", "userChatId", "word" FROM "Word";
+DROP TABLE "Word";
+ALTER TABLE "new_Word" RENAME TO "Word";
+CREATE INDEX "Word_deckId_idx" ON "Word"("deckId");
+CREATE INDEX "Word_chatId_normalized_idx" ON "Word"("chatId", "normalized");
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
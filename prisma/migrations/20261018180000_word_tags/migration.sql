@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "Word" ADD COLUMN "tags" TEXT NOT NULL DEFAULT '';
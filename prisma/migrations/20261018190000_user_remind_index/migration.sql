@@ -0,0 +1,2 @@
+-- CreateIndex
+CREATE INDEX "User_nextRemindAt_chatId_idx" ON "User"("nextRemindAt", "chatId");
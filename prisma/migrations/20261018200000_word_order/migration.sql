@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "User" ADD COLUMN "wordOrder" TEXT NOT NULL DEFAULT 'most_overdue';
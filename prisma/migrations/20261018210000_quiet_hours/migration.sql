@@ -0,0 +1,3 @@
+-- AlterTable
+ALTER TABLE "User" ADD COLUMN "timezone" TEXT NOT NULL DEFAULT 'UTC';
+ALTER TABLE "User" ADD COLUMN "quietHours" TEXT;
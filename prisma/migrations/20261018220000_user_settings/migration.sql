@@ -0,0 +1,3 @@
+-- AlterTable
+ALTER TABLE "User" ADD COLUMN "wordsPerPage" INTEGER NOT NULL DEFAULT 5;
+ALTER TABLE "User" ADD COLUMN "silent" BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,23 @@
+-- CreateTable
+CREATE TABLE "Challenge" (
+    "inlineMessageId" TEXT NOT NULL PRIMARY KEY,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "chatId" BIGINT NOT NULL,
+    "word" TEXT NOT NULL,
+    "options" TEXT NOT NULL,
+    "correct" INTEGER NOT NULL
+);
+
+-- CreateTable
+CREATE TABLE "ChallengeAnswer" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "userId" BIGINT NOT NULL,
+    "name" TEXT NOT NULL,
+    "correct" BOOLEAN NOT NULL,
+    "inlineMessageId" TEXT NOT NULL,
+    CONSTRAINT "ChallengeAnswer_inlineMessageId_fkey" FOREIGN KEY ("inlineMessageId") REFERENCES "Challenge" ("inlineMessageId") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "ChallengeAnswer_inlineMessageId_userId_key" ON "ChallengeAnswer"("inlineMessageId", "userId");
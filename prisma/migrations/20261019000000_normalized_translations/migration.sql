@@ -0,0 +1,19 @@
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_Translation" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "text" TEXT NOT NULL,
+    "normalized" TEXT NOT NULL,
+    "position" INTEGER NOT NULL,
+    "note" TEXT,
+    "wordId" TEXT NOT NULL,
+    CONSTRAINT "Translation_wordId_fkey" FOREIGN KEY ("wordId") REFERENCES "Word" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+-- SQLite can't fold the case of non-ASCII letters, existing translations are normalized
+-- on start by `PrismaStorage::normalize_words`
+INSERT INTO "new_Translation" ("id", "normalized", "note", "position", "text", "wordId") SELECT "id", '', "note", "position", "text", "wordId" FROM "Translation";
+DROP TABLE "Translation";
+ALTER TABLE "new_Translation" RENAME TO "Translation";
+CREATE INDEX "Translation_wordId_idx" ON "Translation"("wordId");
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
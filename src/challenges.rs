@@ -0,0 +1,205 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, Duration, FixedOffset};
+use rand::{distributions::Alphanumeric, Rng};
+use teloxide::{
+    payloads::EditMessageTextInlineSetters,
+    requests::{Requester, ResponseResult},
+    types::{CallbackQuery, ChosenInlineResult, InlineKeyboardMarkup, ParseMode, User},
+    utils::markdown,
+};
+
+use crate::{
+    common::AsyncMutex,
+    keyboard,
+    state::quiz::QuizOptions,
+    storage::{error::StorageError, now, Challenge, ChallengeAnswer, Storage},
+};
+
+// Offered challenges which aren't chosen in this time are forgotten, seconds
+static OFFER_LIFETIME: i64 = 600;
+static OFFER_ID_LENGTH: usize = 8;
+
+// A challenge shown in the results of an inline query, it's kept
+// in the storage once the user sends it
+#[derive(Debug, Clone)]
+struct Offer {
+    id: String,
+    created_at: DateTime<FixedOffset>,
+    word: String,
+    quiz: QuizOptions,
+}
+
+/// Question followed by the people who have answered, in MarkdownV2
+pub fn text(word: &str, answers: &[ChallengeAnswer]) -> String {
+    let mut text = format!(
+        "🧠 Which translation is right for *{}*?",
+        markdown::escape(word)
+    );
+    if !answers.is_empty() {
+        text.push('\n');
+    }
+    for answer in answers {
+        let mark = if answer.correct { "✅" } else { "❌" };
+        text.push_str(&format!("\n{} {}", mark, markdown::escape(&answer.name)));
+    }
+    text
+}
+
+// the payload is the option index, the challenge is found by its message
+pub fn keyboard(options: &[String]) -> InlineKeyboardMarkup {
+    let buttons = options
+        .iter()
+        .enumerate()
+        .map(|(index, option)| {
+            vec![keyboard::Button::ChallengeOption
+                .to_inline_button_with_payload(option, &index.to_string())]
+        })
+        .collect::<Vec<_>>();
+    InlineKeyboardMarkup::new(buttons)
+}
+
+/// Challenges offered in inline queries, answers go to the storage.
+/// Telegram tells which result is sent only if inline feedback
+/// is enabled for the bot with @BotFather.
+#[derive(Debug, Clone)]
+pub struct Challenges {
+    db: Arc<dyn Storage>,
+    /// Offers of every user who is typing an inline query
+    offers: Arc<AsyncMutex<HashMap<i64, Vec<Offer>>>>,
+}
+
+impl Challenges {
+    pub fn new(db: Arc<dyn Storage>) -> Challenges {
+        Challenges {
+            db,
+            offers: Arc::new(AsyncMutex::new(HashMap::new())),
+        }
+    }
+
+    /// Keeps the challenge until the user sends or forgets it,
+    /// returns the id of the inline query result
+    pub async fn offer(&self, chat_id: i64, word: &str, quiz: QuizOptions) -> String {
+        let id: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(OFFER_ID_LENGTH)
+            .map(char::from)
+            .collect();
+        let now = now();
+        let expired_at = now - Duration::seconds(OFFER_LIFETIME);
+        let mut offers = self.offers.lock().await;
+        offers.retain(|_, user_offers| {
+            user_offers.retain(|offer| offer.created_at > expired_at);
+            !user_offers.is_empty()
+        });
+        offers.entry(chat_id).or_default().push(Offer {
+            id: id.clone(),
+            created_at: now,
+            word: word.to_owned(),
+            quiz,
+        });
+        id
+    }
+
+    /// Saves the offered challenge which the user has sent to a chat,
+    /// the other offers of the user are forgotten
+    pub async fn choose(&self, result: &ChosenInlineResult) {
+        let Some(inline_message_id) = &result.inline_message_id else {
+            return;
+        };
+        let chat_id = result.from.id.0 as i64;
+        let offer = {
+            let mut offers = self.offers.lock().await;
+            offers
+                .remove(&chat_id)
+                .and_then(|user_offers| user_offers.into_iter().find(|o| o.id == result.result_id))
+        };
+        let Some(offer) = offer else {
+            return;
+        };
+
+        let challenge = Challenge {
+            inline_message_id: inline_message_id.clone(),
+            chat_id,
+            word: offer.word,
+            options: offer.quiz.options,
+            correct: offer.quiz.correct,
+            answers: vec![],
+        };
+        if let Err(err) = self.db.new_challenge(&challenge).await {
+            log::error!("Error saving challenge: {}", err);
+        }
+    }
+
+    /// Grades the pressed option and edits the challenge to show who has
+    /// answered. Returns the text shown to the user who pressed it.
+    pub async fn answer(
+        &self,
+        bot: &teloxide::Bot,
+        query: &CallbackQuery,
+    ) -> ResponseResult<Option<String>> {
+        let Some(inline_message_id) = &query.inline_message_id else {
+            return Ok(None);
+        };
+        let Some(chosen) = keyboard::payload(query).and_then(|index| index.parse::<usize>().ok())
+        else {
+            return Ok(None);
+        };
+
+        let mut challenge = match self.db.get_challenge(inline_message_id).await {
+            Ok(Some(challenge)) => challenge,
+            Ok(None) => return Ok(Some("This quiz has expired".to_owned())),
+            Err(err) => {
+                log::error!("Error getting challenge: {}", err);
+                return Ok(Some("Something went wrong, try again later".to_owned()));
+            }
+        };
+        let user_id = query.from.id.0 as i64;
+        if challenge.answers.iter().any(|a| a.user_id == user_id) {
+            return Ok(Some("You have already answered".to_owned()));
+        }
+
+        let answer = ChallengeAnswer {
+            user_id,
+            name: user_name(&query.from),
+            correct: chosen == challenge.correct,
+        };
+        match self
+            .db
+            .add_challenge_answer(inline_message_id, &answer)
+            .await
+        {
+            Ok(()) => {}
+            // the other answer of the user has been saved in the meantime
+            Err(StorageError::ChallengeAlreadyAnswered) => {
+                return Ok(Some("You have already answered".to_owned()))
+            }
+            Err(StorageError::ChallengeNotFound) => {
+                return Ok(Some("This quiz has expired".to_owned()))
+            }
+            Err(err) => {
+                log::error!("Error saving challenge answer: {}", err);
+                return Ok(Some("Something went wrong, try again later".to_owned()));
+            }
+        }
+        let result = if answer.correct {
+            "🎉 Correct!".to_owned()
+        } else {
+            format!("😔 Wrong! It's {}", challenge.options[challenge.correct])
+        };
+        challenge.answers.push(answer);
+
+        bot.edit_message_text_inline(inline_message_id, text(&challenge.word, &challenge.answers))
+            .parse_mode(ParseMode::MarkdownV2)
+            .reply_markup(keyboard(&challenge.options))
+            .await?;
+        Ok(Some(result))
+    }
+}
+
+fn user_name(user: &User) -> String {
+    match &user.username {
+        Some(username) => format!("@{}", username),
+        None => user.full_name(),
+    }
+}
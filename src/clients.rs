@@ -1,17 +1,25 @@
 use std::{collections::HashMap, sync::Arc};
 
-use rand::seq::SliceRandom;
+use chrono::{DateTime, FixedOffset};
 use teloxide::{
     requests::ResponseResult,
-    types::{CallbackQuery, ChatId, Message},
+    types::{CallbackQuery, ChatId, ChosenInlineResult, InlineQuery, Message, PollAnswer},
 };
 
 use crate::{
+    challenges::Challenges,
     common::AsyncMutex,
+    reminder::{self, queue::RemindQueue},
+    scheduler::order,
     state::{self, events::Event},
-    storage::{users_with_words, Storage},
+    storage::{error::StorageResult, now, Storage, UserWithCards},
 };
 
+// Users who couldn't be reminded because of a storage error
+// are checked again after this time
+static RETRY_AFTER: i64 = 1800;
+static REMIND_TIMES_PAGE_SIZE: i64 = 1000;
+
 #[derive(Debug, Clone)]
 pub struct Client {
     pub fsm: Arc<state::FSM>,
@@ -22,18 +30,26 @@ pub struct Client {
 pub struct Clients {
     clients: Arc<AsyncMutex<HashMap<ChatId, Client>>>,
     bot: Arc<teloxide::Bot>,
-    db: Arc<Storage>,
+    db: Arc<dyn Storage>,
+    challenges: Challenges,
+    reminds: RemindQueue,
 }
 
 impl Clients {
-    pub fn new(bot: teloxide::Bot, db: Storage) -> Clients {
+    pub fn new(bot: teloxide::Bot, db: Arc<dyn Storage>) -> Clients {
         Clients {
             clients: Arc::new(AsyncMutex::new(HashMap::new())),
             bot: Arc::new(bot),
-            db: Arc::new(db),
+            challenges: Challenges::new(db.clone()),
+            db,
+            reminds: RemindQueue::default(),
         }
     }
 
+    pub fn reminds(&self) -> RemindQueue {
+        self.reminds.clone()
+    }
+
     pub async fn get_or_insert(&self, chat_id: ChatId) -> Client {
         let bot = (*self.bot).clone();
         log::debug!("Lock clients");
@@ -47,7 +63,13 @@ impl Clients {
 
         // // Client doesn't exist, create a new one and insert it into the HashMap
         let client = {
-            let context = state::Context::new(bot.clone(), chat_id, self.db.clone());
+            let context = state::Context::new(
+                bot.clone(),
+                chat_id,
+                self.db.clone(),
+                self.challenges.clone(),
+                self.reminds.clone(),
+            );
             let fsm = state::FSM::new(Box::new(state::idle::Idle::new()), context);
             let new_client = Client {
                 fsm: Arc::from(fsm),
@@ -59,6 +81,8 @@ impl Clients {
         if let Err(err) = client.fsm.init().await {
             log::error!("Error initializing FSM: {}", err);
         }
+        // users who have just started are checked soon like new users in the storage
+        self.reminds.schedule_if_absent(chat_id.0, now()).await;
 
         client
     }
@@ -72,7 +96,17 @@ impl Clients {
         Ok(())
     }
 
-    pub async fn handle_callback_query(&self, query: CallbackQuery) -> ResponseResult<()> {
+    /// Returns the text shown to the user who pressed the button
+    pub async fn handle_callback_query(
+        &self,
+        query: CallbackQuery,
+    ) -> ResponseResult<Option<String>> {
+        // buttons of messages sent with inline queries belong to no chat
+        if query.inline_message_id.is_some() {
+            log::info!("Handling inline callback query from {}", query.from.id);
+            return self.challenges.answer(&self.bot, &query).await;
+        }
+
         let chat_id = query.message.as_ref().map(|msg| msg.chat.id);
         if let Some(chat_id) = chat_id {
             log::info!("Handling callback query for chat_id {}", chat_id);
@@ -80,55 +114,188 @@ impl Clients {
             client.fsm.handle_callback_query(query).await;
         }
         log::debug!("Callback query handled");
+        Ok(None)
+    }
+
+    // Polls are sent to private chats only, their ids are the ids of users
+    pub async fn handle_poll_answer(&self, answer: PollAnswer) -> ResponseResult<()> {
+        let chat_id = ChatId(answer.user.id.0 as i64);
+        log::info!("Handling poll answer for chat_id {}", chat_id);
+        let client = self.get_or_insert(chat_id).await;
+        client.fsm.handle_event(Event::PollAnswer(answer)).await;
+        log::debug!("Poll answer handled");
         Ok(())
     }
 
-    pub async fn handle_event(&self, event: Event) {
-        match event {
-            Event::Remind => self.remind().await,
-            _ => log::warn!("Unknown event: {:?}", event),
-        }
+    // Words of a user are searched in the private chat with them
+    pub async fn handle_inline_query(&self, query: InlineQuery) -> ResponseResult<()> {
+        let chat_id = ChatId(query.from.id.0 as i64);
+        log::info!("Handling inline query for chat_id {}", chat_id);
+        let client = self.get_or_insert(chat_id).await;
+        client.fsm.handle_inline_query(query).await;
+        log::debug!("Inline query handled");
+        Ok(())
     }
 
-    async fn remind(&self) {
-        log::debug!("Reminding");
-        let users = self.db.find_to_remind().await;
-        if let Err(err) = users {
-            log::error!("Error getting users to remind: {}", err);
-            return;
-        }
-        let users = users.unwrap();
-
-        let (no_words_users, users_with_words): (
-            Vec<users_with_words::Data>,
-            Vec<users_with_words::Data>,
-        ) = users.iter().cloned().partition(|u| u.words.is_empty());
-
-        log::debug!("Got users to remind: {:?}", users_with_words.len());
-        for user in users_with_words {
-            let word_opt = user
-                .words
-                .choose(&mut rand::thread_rng())
-                .map(|w| w.clone());
-            if let Some(word) = word_opt {
-                let chat_id = word.chat_id;
-                let client = self.get_or_insert(ChatId(chat_id)).await;
-                client
-                    .fsm
-                    .handle_event(Event::RemindWordToUser(word, user.into()))
-                    .await;
+    // Only challenges are kept, the words sent from the results need nothing
+    pub async fn handle_chosen_inline_result(
+        &self,
+        result: ChosenInlineResult,
+    ) -> ResponseResult<()> {
+        log::info!("Handling chosen inline result from {}", result.from.id);
+        self.challenges.choose(&result).await;
+        Ok(())
+    }
+
+    /// Fills the reminder queue with all users in the storage who have
+    /// something to remind, page by page from the earliest time
+    pub async fn load_reminds(&self) -> StorageResult<()> {
+        let mut cursor = None;
+        let mut loaded = 0;
+        loop {
+            let page = self
+                .db
+                .get_remind_times(cursor, REMIND_TIMES_PAGE_SIZE)
+                .await?;
+            loaded += page.len();
+            cursor = page.last().copied();
+            for time in &page {
+                reminder::reschedule(&*self.db, &self.reminds, time.chat_id).await?;
+            }
+            if (page.len() as i64) < REMIND_TIMES_PAGE_SIZE {
+                break;
             }
         }
+        log::info!("Loaded remind times of {} users", loaded);
+        Ok(())
+    }
 
-        log::debug!("Got users without words: {}", no_words_users.len());
-        for user in no_words_users {
-            let result = self
-                .db
-                .update_next_remind_user(user.chat_id, user.remind_every.into())
-                .await;
-            if let Err(err) = result {
-                log::error!("Error updating next remind: {}", err);
+    /// Reminds the users taken from the queue and schedules them again.
+    /// Users who are not due anymore or have no due cards get the time
+    /// of their next due card, the ones not in the storage are dropped.
+    pub async fn remind(&self, chat_ids: Vec<i64>) {
+        log::debug!("Reminding {} users", chat_ids.len());
+        let now = now();
+        let retry_at = now + chrono::Duration::seconds(RETRY_AFTER);
+        let users = match self.db.find_to_remind(&chat_ids).await {
+            Ok(users) => users,
+            Err(err) => {
+                log::error!("Error getting users to remind: {}", err);
+                for chat_id in chat_ids {
+                    self.reminds.schedule(chat_id, retry_at).await;
+                }
+                return;
+            }
+        };
+
+        for UserWithCards { user, cards } in users {
+            if user.next_remind_at > now {
+                self.reschedule(user.chat_id, retry_at).await;
+                continue;
             }
+            let allowed_at = user.allowed_at(now);
+            if allowed_at > now {
+                log::debug!("Quiet hours of {} until {}", user.chat_id, allowed_at);
+                self.reminds.schedule(user.chat_id, allowed_at).await;
+                continue;
+            }
+
+            let card_opt = match order::sort_for_user(&*self.db, &user, cards).await {
+                Ok(cards) => cards.into_iter().next(),
+                Err(err) => {
+                    log::error!("Error ordering words to remind: {}", err);
+                    self.reminds.schedule(user.chat_id, retry_at).await;
+                    continue;
+                }
+            };
+            let Some(card) = card_opt else {
+                self.reschedule(user.chat_id, retry_at).await;
+                continue;
+            };
+
+            // the answer schedules the user again, this time is used if
+            // the user is busy and the word isn't asked
+            let busy_retry_at = now + chrono::Duration::seconds(user.remind_every.into());
+            self.reminds.schedule(user.chat_id, busy_retry_at).await;
+            let client = self.get_or_insert(ChatId(card.word.chat_id)).await;
+            client
+                .fsm
+                .handle_event(Event::RemindWordToUser(card, user))
+                .await;
         }
     }
+
+    // schedules the user at the next due card, `retry_at` if it can't be read
+    async fn reschedule(&self, chat_id: i64, retry_at: DateTime<FixedOffset>) {
+        if let Err(err) = reminder::reschedule(&*self.db, &self.reminds, chat_id).await {
+            log::error!("Error scheduling the next remind: {}", err);
+            self.reminds.schedule(chat_id, retry_at).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        scheduler::Progress,
+        state::State,
+        storage::{memory::MemoryStorage, Direction},
+        testing::{FakeTelegram, CHAT_ID},
+    };
+
+    use super::*;
+
+    async fn is_due(reminds: &RemindQueue) -> bool {
+        tokio::time::timeout(Duration::from_millis(100), reminds.wait_due(10))
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn due_word_is_asked() {
+        let telegram = FakeTelegram::start().await;
+        let db = Arc::new(MemoryStorage::new());
+        let hund = db
+            .new_word(CHAT_ID, "Hund", &"dog".into(), None)
+            .await
+            .unwrap();
+        db.update_word_remind(
+            &hund.id,
+            Direction::Forward,
+            &Progress::default(),
+            hund.status,
+        )
+        .await
+        .unwrap();
+        let clients = Clients::new(telegram.bot(), db);
+
+        clients.remind(vec![CHAT_ID]).await;
+
+        assert!(telegram
+            .texts()
+            .iter()
+            .any(|text| text.starts_with("Write translation for the word")));
+        let client = clients.get_or_insert(ChatId(CHAT_ID)).await;
+        assert_ne!(
+            client.fsm.state.lock().await.name(),
+            state::idle::Idle::new().name()
+        );
+    }
+
+    #[tokio::test]
+    async fn users_without_due_words_wait_for_them() {
+        let telegram = FakeTelegram::start().await;
+        let db = Arc::new(MemoryStorage::new());
+        db.new_word(CHAT_ID, "Hund", &"dog".into(), None)
+            .await
+            .unwrap();
+        let clients = Clients::new(telegram.bot(), db);
+
+        clients.remind(vec![CHAT_ID]).await;
+
+        assert!(telegram.texts().is_empty());
+        assert!(!is_due(&clients.reminds()).await);
+    }
 }
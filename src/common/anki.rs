@@ -0,0 +1,581 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Cursor, Read, Write},
+    path::PathBuf,
+};
+
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use rand::Rng;
+use rusqlite::{params, Connection, OpenFlags};
+use serde_json::{json, Value};
+use thiserror::Error;
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+use super::{config::MAX_ANKI_COLLECTION_SIZE, normalize};
+
+// Note type of exported words, the same id makes Anki update it on a repeated import
+static MODEL_ID: i64 = 1_655_000_000_000;
+static DEFAULT_DECK_ID: i64 = 1;
+static DEFAULT_DECK_NAME: &str = "Default";
+static FIELD_SEPARATOR: char = '\x1f';
+static DAY_SECONDS: i64 = 24 * 60 * 60;
+
+// Collections in .apkg files from the oldest to the newest format
+static COLLECTION_FILES: [&str; 2] = ["collection.anki21", "collection.anki2"];
+// Compressed collection of recent Anki versions which can't be read
+static COMPRESSED_COLLECTION_FILE: &str = "collection.anki21b";
+static HTML_ENTITIES: [(&str, &str); 6] = [
+    ("&nbsp;", " "),
+    ("&lt;", "<"),
+    ("&gt;", ">"),
+    ("&quot;", "\""),
+    ("&#39;", "'"),
+    ("&amp;", "&"),
+];
+
+// Collection schema of Anki 2.1 (version 11), the one .apkg files are read with
+static SCHEMA: &str = "
+CREATE TABLE col (
+    id integer PRIMARY KEY, crt integer NOT NULL, mod integer NOT NULL,
+    scm integer NOT NULL, ver integer NOT NULL, dty integer NOT NULL,
+    usn integer NOT NULL, ls integer NOT NULL, conf text NOT NULL,
+    models text NOT NULL, decks text NOT NULL, dconf text NOT NULL, tags text NOT NULL
+);
+CREATE TABLE notes (
+    id integer PRIMARY KEY, guid text NOT NULL, mid integer NOT NULL,
+    mod integer NOT NULL, usn integer NOT NULL, tags text NOT NULL,
+    flds text NOT NULL, sfld integer NOT NULL, csum integer NOT NULL,
+    flags integer NOT NULL, data text NOT NULL
+);
+CREATE TABLE cards (
+    id integer PRIMARY KEY, nid integer NOT NULL, did integer NOT NULL,
+    ord integer NOT NULL, mod integer NOT NULL, usn integer NOT NULL,
+    type integer NOT NULL, queue integer NOT NULL, due integer NOT NULL,
+    ivl integer NOT NULL, factor integer NOT NULL, reps integer NOT NULL,
+    lapses integer NOT NULL, left integer NOT NULL, odue integer NOT NULL,
+    odid integer NOT NULL, flags integer NOT NULL, data text NOT NULL
+);
+CREATE TABLE revlog (
+    id integer PRIMARY KEY, cid integer NOT NULL, usn integer NOT NULL,
+    ease integer NOT NULL, ivl integer NOT NULL, lastIvl integer NOT NULL,
+    factor integer NOT NULL, time integer NOT NULL, type integer NOT NULL
+);
+CREATE TABLE graves (usn integer NOT NULL, oid integer NOT NULL, type integer NOT NULL);
+CREATE INDEX ix_notes_usn ON notes (usn);
+CREATE INDEX ix_cards_usn ON cards (usn);
+CREATE INDEX ix_revlog_usn ON revlog (usn);
+CREATE INDEX ix_cards_nid ON cards (nid);
+CREATE INDEX ix_cards_sched ON cards (did, queue, due);
+CREATE INDEX ix_revlog_cid ON revlog (cid);
+CREATE INDEX ix_notes_csum ON notes (csum);
+";
+
+#[derive(Error, Debug)]
+pub enum AnkiError {
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("The file has no Anki collection")]
+    NoCollection,
+    #[error("The Anki collection is too large")]
+    TooLarge,
+    #[error("The deck is exported by a new version of Anki, export it with \"Support older Anki versions\" checked")]
+    UnsupportedVersion,
+}
+
+/// A note with two fields, front and back, and a card for each of them
+#[derive(Debug, Clone)]
+pub struct AnkiNote {
+    pub guid: String,
+    pub front: String,
+    pub back: String,
+    pub tags: Vec<String>,
+    pub deck: Option<String>,
+    pub cards: Vec<AnkiCard>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnkiCard {
+    /// 0 asks the front, 1 asks the back
+    pub ord: i64,
+    /// `None` for cards which have never been reviewed
+    pub due: Option<DateTime<FixedOffset>>,
+    /// In days
+    pub interval: f64,
+    pub ease_factor: f64,
+    pub reps: i64,
+    pub lapses: i64,
+    pub suspended: bool,
+    pub reviews: Vec<AnkiReview>,
+}
+
+/// A note read from a collection, fields are stripped of HTML
+#[derive(Debug, Clone)]
+pub struct CollectionNote {
+    pub fields: Vec<String>,
+    pub tags: Vec<String>,
+    /// Deck of the first card, `None` for the default deck
+    pub deck: Option<String>,
+    /// Reviews aren't read
+    pub cards: Vec<AnkiCard>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Collection {
+    /// Field names of every note type, e.g. Front and Back of the basic one
+    pub note_types: Vec<Vec<String>>,
+    pub notes: Vec<CollectionNote>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnkiReview {
+    pub created_at: DateTime<FixedOffset>,
+    /// 1 - again, 2 - hard, 3 - good, 4 - easy
+    pub ease: i64,
+    /// Time of the answer in milliseconds
+    pub time: i64,
+}
+
+/// Builds an .apkg file: a zip with the SQLite collection and an empty media list
+pub fn write_package(notes: &[AnkiNote]) -> Result<Vec<u8>, AnkiError> {
+    let collection = write_collection(notes)?;
+
+    let mut package = ZipWriter::new(Cursor::new(vec![]));
+    package.start_file("collection.anki2", FileOptions::default())?;
+    package.write_all(&collection)?;
+    package.start_file("media", FileOptions::default())?;
+    package.write_all(b"{}")?;
+    Ok(package.finish()?.into_inner())
+}
+
+fn write_collection(notes: &[AnkiNote]) -> Result<Vec<u8>, AnkiError> {
+    // SQLite writes databases to files only
+    let path = temp_collection_path("export");
+    let result = Connection::open(&path)
+        .map_err(AnkiError::from)
+        .and_then(|mut db| {
+            let transaction = db.transaction()?;
+            insert_notes(&transaction, notes)?;
+            Ok(transaction.commit()?)
+        })
+        .and_then(|_| Ok(fs::read(&path)?));
+    // the file is missing if the database couldn't be created
+    fs::remove_file(&path).ok();
+    result
+}
+
+fn insert_notes(db: &Connection, notes: &[AnkiNote]) -> Result<(), AnkiError> {
+    let now = Utc::now();
+    let now_ms = now.timestamp_millis();
+    let now_s = now.timestamp();
+    // Anki counts due days of review cards from the day the collection is created
+    let created = now_s - now_s % DAY_SECONDS;
+
+    let mut deck_ids: HashMap<String, i64> = HashMap::new();
+    for name in notes.iter().filter_map(|note| note.deck.as_ref()) {
+        let next_id = now_ms + deck_ids.len() as i64;
+        deck_ids.entry(name.clone()).or_insert(next_id);
+    }
+
+    db.execute_batch(SCHEMA)?;
+    db.execute(
+        "INSERT INTO col VALUES (1, ?1, ?2, ?3, 11, 0, 0, 0, ?4, ?5, ?6, ?7, '{}')",
+        params![
+            created,
+            now_ms,
+            now_ms,
+            collection_conf(notes.len()).to_string(),
+            models(now_s).to_string(),
+            decks(&deck_ids, now_s).to_string(),
+            deck_conf().to_string(),
+        ],
+    )?;
+
+    // ids of notes, cards and reviews are timestamps in milliseconds in Anki
+    let mut next_id = now_ms;
+    let mut review_ids: Vec<i64> = vec![];
+    for (position, note) in notes.iter().enumerate() {
+        next_id += 1;
+        let note_id = next_id;
+        let front = escape_html(&note.front);
+        let tags = if note.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" {} ", note.tags.join(" "))
+        };
+        db.execute(
+            "INSERT INTO notes VALUES (?1, ?2, ?3, ?4, -1, ?5, ?6, ?7, ?8, 0, '')",
+            params![
+                note_id,
+                note.guid,
+                MODEL_ID,
+                now_s,
+                tags,
+                format!("{}{}{}", front, FIELD_SEPARATOR, escape_html(&note.back)),
+                front,
+                checksum(&front),
+            ],
+        )?;
+
+        let deck_id = note
+            .deck
+            .as_ref()
+            .and_then(|name| deck_ids.get(name))
+            .copied()
+            .unwrap_or(DEFAULT_DECK_ID);
+        for card in &note.cards {
+            next_id += 1;
+            let card_id = next_id;
+            // (type, queue, due): new cards are due by position, review ones by day
+            let (card_type, queue, due) = match card.due {
+                Some(due) => (2, 2, (due.timestamp() - created).div_euclid(DAY_SECONDS)),
+                None => (0, 0, position as i64 + 1),
+            };
+            let queue = if card.suspended { -1 } else { queue };
+            db.execute(
+                "INSERT INTO cards VALUES (?1, ?2, ?3, ?4, ?5, -1, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 0, 0, 0, 0, '')",
+                params![
+                    card_id,
+                    note_id,
+                    deck_id,
+                    card.ord,
+                    now_s,
+                    card_type,
+                    queue,
+                    due,
+                    card.interval.round().max(1.0) as i64,
+                    (card.ease_factor * 1000.0).round() as i64,
+                    card.reps,
+                    card.lapses,
+                ],
+            )?;
+
+            for review in &card.reviews {
+                let mut review_id = review.created_at.timestamp_millis();
+                while review_ids.contains(&review_id) {
+                    review_id += 1;
+                }
+                review_ids.push(review_id);
+                db.execute(
+                    "INSERT INTO revlog VALUES (?1, ?2, -1, ?3, 0, 0, 0, ?4, 1)",
+                    params![review_id, card_id, review.ease, review.time],
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads notes and their cards from an .apkg file
+pub fn read_package(data: &[u8]) -> Result<Collection, AnkiError> {
+    let mut package = ZipArchive::new(Cursor::new(data))?;
+    // the old collection next to a compressed one only asks to update Anki
+    if package.by_name(COMPRESSED_COLLECTION_FILE).is_ok() {
+        return Err(AnkiError::UnsupportedVersion);
+    }
+    let name = COLLECTION_FILES
+        .into_iter()
+        .find(|name| package.file_names().any(|file| file == *name))
+        .ok_or(AnkiError::NoCollection)?;
+    let file = package.by_name(name)?;
+    if file.size() > MAX_ANKI_COLLECTION_SIZE {
+        return Err(AnkiError::TooLarge);
+    }
+    // the size in the header can be wrong, reading stops right after the limit
+    let mut collection = vec![];
+    file.take(MAX_ANKI_COLLECTION_SIZE + 1)
+        .read_to_end(&mut collection)?;
+    if collection.len() as u64 > MAX_ANKI_COLLECTION_SIZE {
+        return Err(AnkiError::TooLarge);
+    }
+
+    // SQLite reads databases from files only
+    let path = temp_collection_path("import");
+    fs::write(&path, collection)?;
+    let result = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(AnkiError::from)
+        .and_then(|db| read_collection(&db));
+    fs::remove_file(&path)?;
+    result
+}
+
+fn temp_collection_path(prefix: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "{}-{}.anki2",
+        prefix,
+        rand::thread_rng().gen::<u64>()
+    ))
+}
+
+fn read_collection(db: &Connection) -> Result<Collection, AnkiError> {
+    let (created, models, decks): (i64, String, String) =
+        db.query_row("SELECT crt, models, decks FROM col", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+    let models: HashMap<String, Value> = serde_json::from_str(&models)?;
+    let decks: HashMap<String, Value> = serde_json::from_str(&decks)?;
+
+    let note_types = models
+        .values()
+        .map(|model| {
+            model["flds"]
+                .as_array()
+                .map(|fields| {
+                    fields
+                        .iter()
+                        .map(|field| field["name"].as_str().unwrap_or("").to_owned())
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+        .collect();
+    let deck_name = |id: i64| {
+        decks
+            .get(&id.to_string())
+            .and_then(|deck| deck["name"].as_str())
+            .filter(|name| *name != DEFAULT_DECK_NAME)
+            .map(str::to_owned)
+    };
+
+    // (deck id, card) by note id
+    let mut cards: HashMap<i64, Vec<(i64, AnkiCard)>> = HashMap::new();
+    let mut statement = db.prepare(
+        "SELECT nid, did, ord, type, queue, due, ivl, factor, reps, lapses FROM cards ORDER BY ord",
+    )?;
+    let rows = statement.query_map([], |row| {
+        let card_type: i64 = row.get(3)?;
+        let due: i64 = row.get(5)?;
+        // only review cards have the due day, learning ones are started again
+        let due = (card_type == 2)
+            .then(|| Utc.timestamp_opt(created + due * DAY_SECONDS, 0).single())
+            .flatten()
+            .map(|due| due.fixed_offset());
+        let card = AnkiCard {
+            ord: row.get(2)?,
+            due,
+            interval: row.get::<_, i64>(6)?.max(0) as f64,
+            ease_factor: row.get::<_, i64>(7)? as f64 / 1000.0,
+            reps: row.get(8)?,
+            lapses: row.get(9)?,
+            suspended: row.get::<_, i64>(4)? == -1,
+            reviews: vec![],
+        };
+        Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, card))
+    })?;
+    for row in rows {
+        let (note_id, deck_id, card) = row?;
+        cards.entry(note_id).or_default().push((deck_id, card));
+    }
+
+    let mut statement = db.prepare("SELECT id, flds, tags FROM notes ORDER BY id")?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+    let mut notes = vec![];
+    for row in rows {
+        let (id, fields, tags) = row?;
+        let cards = cards.remove(&id).unwrap_or_default();
+        notes.push(CollectionNote {
+            fields: fields.split(FIELD_SEPARATOR).map(strip_html).collect(),
+            tags: tags.split_whitespace().map(str::to_owned).collect(),
+            deck: cards.first().and_then(|(deck_id, _)| deck_name(*deck_id)),
+            cards: cards.into_iter().map(|(_, card)| card).collect(),
+        });
+    }
+
+    Ok(Collection { note_types, notes })
+}
+
+/// Text of a field without tags, entities and sounds, line breaks are kept
+pub fn strip_html(field: &str) -> String {
+    let mut text = String::new();
+    let mut tag: Option<String> = None;
+    for c in field.chars() {
+        match (c, &mut tag) {
+            ('<', _) => tag = Some(String::new()),
+            ('>', Some(name)) => {
+                let name = name.trim_start_matches('/').to_lowercase();
+                if ["br", "div", "p", "li"]
+                    .iter()
+                    .any(|block| name.split([' ', '/']).next() == Some(block))
+                {
+                    text.push('\n');
+                }
+                tag = None;
+            }
+            (c, Some(name)) => name.push(c),
+            (c, None) => text.push(c),
+        }
+    }
+    for (entity, replacement) in HTML_ENTITIES {
+        text = text.replace(entity, replacement);
+    }
+    // [sound:file.mp3] refers to a media file which isn't imported
+    while let Some((before, after)) = text.split_once("[sound:") {
+        let after = after.split_once(']').map_or("", |(_, after)| after);
+        text = format!("{}{}", before, after);
+    }
+    text.lines()
+        .map(normalize::clean)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn collection_conf(notes_count: usize) -> Value {
+    json!({
+        "nextPos": notes_count + 1,
+        "estTimes": true,
+        "activeDecks": [DEFAULT_DECK_ID],
+        "sortType": "noteFld",
+        "timeLim": 0,
+        "sortBackwards": false,
+        "addToCur": true,
+        "curDeck": DEFAULT_DECK_ID,
+        "newSpread": 0,
+        "dueCounts": true,
+        "curModel": MODEL_ID.to_string(),
+        "collapseTime": 1200,
+    })
+}
+
+fn models(modified: i64) -> Value {
+    let template = |name: &str, ord: i64, question: &str, answer: &str| {
+        json!({
+            "name": name,
+            "ord": ord,
+            "qfmt": format!("{{{{{}}}}}", question),
+            "afmt": format!("{{{{FrontSide}}}}<hr id=answer>{{{{{}}}}}", answer),
+            "did": null,
+            "bqfmt": "",
+            "bafmt": "",
+        })
+    };
+    let field = |name: &str, ord: i64| {
+        json!({
+            "name": name,
+            "ord": ord,
+            "sticky": false,
+            "rtl": false,
+            "font": "Arial",
+            "size": 20,
+            "media": [],
+        })
+    };
+    json!({
+        MODEL_ID.to_string(): {
+            "id": MODEL_ID,
+            "name": "Word and translation",
+            "type": 0,
+            "mod": modified,
+            "usn": -1,
+            "sortf": 0,
+            "did": DEFAULT_DECK_ID,
+            "tmpls": [
+                template("Word → translation", 0, "Word", "Translation"),
+                template("Translation → word", 1, "Translation", "Word"),
+            ],
+            "flds": [field("Word", 0), field("Translation", 1)],
+            "css": ".card { font-family: arial; font-size: 20px; text-align: center; }",
+            "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\begin{document}\n",
+            "latexPost": "\\end{document}",
+            "tags": [],
+            "vers": [],
+            "req": [[0, "any", [0]], [1, "any", [1]]],
+        }
+    })
+}
+
+fn decks(deck_ids: &HashMap<String, i64>, modified: i64) -> Value {
+    let deck = |id: i64, name: &str| {
+        json!({
+            "id": id,
+            "name": name,
+            "mod": modified,
+            "usn": -1,
+            "lrnToday": [0, 0],
+            "revToday": [0, 0],
+            "newToday": [0, 0],
+            "timeToday": [0, 0],
+            "collapsed": false,
+            "desc": "",
+            "dyn": 0,
+            "conf": 1,
+            "extendNew": 10,
+            "extendRev": 50,
+        })
+    };
+    let mut decks = serde_json::Map::new();
+    decks.insert(
+        DEFAULT_DECK_ID.to_string(),
+        deck(DEFAULT_DECK_ID, DEFAULT_DECK_NAME),
+    );
+    for (name, id) in deck_ids {
+        decks.insert(id.to_string(), deck(*id, name));
+    }
+    Value::Object(decks)
+}
+
+fn deck_conf() -> Value {
+    json!({
+        "1": {
+            "id": 1,
+            "name": "Default",
+            "mod": 0,
+            "usn": 0,
+            "maxTaken": 60,
+            "autoplay": true,
+            "timer": 0,
+            "replayq": true,
+            "dyn": false,
+            "new": {
+                "delays": [1, 10],
+                "ints": [1, 4, 7],
+                "initialFactor": 2500,
+                "order": 1,
+                "perDay": 20,
+                "bury": true,
+                "separate": true,
+            },
+            "rev": {
+                "perDay": 200,
+                "ease4": 1.3,
+                "fuzz": 0.05,
+                "minSpace": 1,
+                "ivlFct": 1,
+                "maxIvl": 36500,
+                "bury": true,
+            },
+            "lapse": {
+                "delays": [10],
+                "mult": 0,
+                "minInt": 1,
+                "leechFails": 8,
+                "leechAction": 0,
+            },
+        }
+    })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// first 8 hex digits of SHA1 of the sort field, Anki finds duplicates with it
+fn checksum(field: &str) -> i64 {
+    let digest = sha1_smol::Sha1::from(field).digest().to_string();
+    i64::from_str_radix(&digest[..8], 16).unwrap_or(0)
+}
@@ -0,0 +1,88 @@
+use super::{config::BULK_SEPARATORS, normalize, translation::Translation};
+
+/// A line of a bulk add message
+#[derive(Debug, Clone)]
+pub enum BulkLine {
+    Word {
+        word: String,
+        translation: Translation,
+    },
+    /// Text of a line without a separator, a word or translations
+    Malformed(String),
+}
+
+/// Splits the line by the separator found first, e.g. "perro - dog, hound"
+pub fn parse_line(line: &str) -> BulkLine {
+    let split = BULK_SEPARATORS
+        .iter()
+        .filter_map(|separator| {
+            line.find(separator)
+                .map(|index| (index, &line[index + separator.len()..]))
+        })
+        .min_by_key(|(index, _)| *index);
+    let Some((index, rest)) = split else {
+        return BulkLine::Malformed(line.trim().to_owned());
+    };
+
+    let word = normalize::clean(&line[..index]);
+    let translation = Translation::parse(rest);
+    if word.is_empty() || translation.is_empty() {
+        return BulkLine::Malformed(line.trim().to_owned());
+    }
+    BulkLine::Word { word, translation }
+}
+
+/// Lines of the message, blank ones are skipped
+pub fn parse(text: &str) -> Vec<BulkLine> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_line)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(line: &BulkLine) -> (&str, Vec<&str>) {
+        match line {
+            BulkLine::Word { word, translation } => (word.as_str(), translation.texts().collect()),
+            BulkLine::Malformed(text) => panic!("malformed line `{}`", text),
+        }
+    }
+
+    #[test]
+    fn parse_line_splits_by_first_separator() {
+        let line = parse_line("perro - dog, hound");
+        assert_eq!(word(&line), ("perro", vec!["dog", "hound"]));
+        // the dash of the translation isn't a separator
+        let line = parse_line("e-mail = correo - electrónico");
+        assert_eq!(word(&line), ("e-mail", vec!["correo - electrónico"]));
+        let line = parse_line("  der   Hund\tdog");
+        assert_eq!(word(&line), ("der Hund", vec!["dog"]));
+    }
+
+    #[test]
+    fn parse_line_without_word_or_translation_is_malformed() {
+        for line in ["perro", " - dog", "perro - ", "perro -  "] {
+            assert!(
+                matches!(parse_line(line), BulkLine::Malformed(_)),
+                "`{}` is not malformed",
+                line
+            );
+        }
+        assert!(matches!(
+            parse_line("  perro  "),
+            BulkLine::Malformed(text) if text == "perro"
+        ));
+    }
+
+    #[test]
+    fn parse_skips_blank_lines() {
+        let lines = parse("perro - dog\n\n   \ngato — cat\nbroken\n");
+        assert_eq!(lines.len(), 3);
+        assert_eq!(word(&lines[0]), ("perro", vec!["dog"]));
+        assert_eq!(word(&lines[1]), ("gato", vec!["cat"]));
+        assert!(matches!(lines[2], BulkLine::Malformed(_)));
+    }
+}
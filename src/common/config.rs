@@ -1,24 +1,44 @@
-use phf::phf_map;
-
-#[cfg(not(debug_assertions))]
-static ONE_HOUR: i64 = 1 * 60 * 60;
-
+// Length of one scheduler "day" in seconds. Intervals produced by
+// `crate::scheduler` are measured in days and converted with this unit.
 #[cfg(not(debug_assertions))]
-pub static TIMINGS: phf::Map<i32, i64> = phf_map! {
-  0i32 => ONE_HOUR,
-  1i32 => ONE_HOUR * 12,
-  2i32 => ONE_HOUR * 24,
-  3i32 => ONE_HOUR * 32,
-  4i32 => ONE_HOUR * 48,
-  5i32 => ONE_HOUR * 72,
-};
+pub static DAY: i64 = 24 * 60 * 60;
 
 #[cfg(debug_assertions)]
-pub static TIMINGS: phf::Map<i32, i64> = phf_map! {
-  0i32 => 1 * 30,
-  1i32 => 2 * 30,
-  2i32 => 3 * 30,
-  3i32 => 4 * 30,
-  4i32 => 5 * 30,
-  5i32 => 6 * 30,
-};
+pub static DAY: i64 = 12 * 60;
+
+// Interval (in days) used for a word that has just been added or failed.
+pub static RELEARN_INTERVAL: f64 = 1.0 / 24.0;
+
+// Upper bound for any interval (in days), roughly 50 years.
+pub static MAX_INTERVAL: f64 = 18250.0;
+
+pub static DEFAULT_SCHEDULER: &str = "sm2";
+
+// A word whose interval (in days) reaches this value is considered mastered
+pub static MASTERED_INTERVAL: f64 = 90.0;
+
+// Interval (in days) between refresher reviews of mastered words
+pub static REFRESH_INTERVAL: f64 = 120.0;
+
+// Time to answer a reminded word in seconds, the word is failed after it.
+// A review session ends when a word is not answered in time.
+pub static REMIND_TIMEOUT: u64 = 2 * 60;
+
+// Time to answer a reminder sent as a quiz poll, in seconds. Polls come as
+// notifications which are often opened a while later.
+pub static POLL_REMIND_TIMEOUT: u64 = 30 * 60;
+
+// Size of the largest CSV/TSV file that can be imported, in bytes
+pub static MAX_IMPORT_FILE_SIZE: u32 = 1024 * 1024;
+
+// Size of the largest Anki deck that can be imported, in bytes. Decks include
+// media files, 20 MB is the most a bot can download from Telegram.
+pub static MAX_ANKI_FILE_SIZE: u32 = 20 * 1024 * 1024;
+
+// Size of the collection unpacked from an Anki deck, in bytes. Deflate packs
+// text well, the limit stops files which unpack to gigabytes.
+pub static MAX_ANKI_COLLECTION_SIZE: u64 = 200 * 1024 * 1024;
+
+// Separators between a word and its translations in lines of bulk added words,
+// the first one found in a line is used
+pub static BULK_SEPARATORS: [&str; 4] = [" - ", " — ", "\t", "="];
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    key_value_enum,
+    storage::{
+        error::StorageResult, now, Deck, Direction, Review, ReviewOutcome, Storage, Word,
+        WordFilter, WordStatus,
+    },
+};
+
+use super::anki::{self, AnkiCard, AnkiNote, AnkiReview};
+
+// Increased on changes of the backup which older versions can't read
+pub static BACKUP_VERSION: u32 = 1;
+static REVIEWS_PAGE_SIZE: i64 = 100;
+static CSV_HEADER: [&str; 10] = [
+    "word",
+    "translation",
+    "tags",
+    "deck",
+    "status",
+    "forward_level",
+    "reverse_level",
+    "next_review",
+    "reviews",
+    "last_review",
+];
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Anki error: {0}")]
+    Anki(#[from] anki::AnkiError),
+}
+
+key_value_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ExportFormat {
+        // a table with words and progress, can be imported back as a file
+        Csv { text: "CSV table", key: "csv" },
+        // everything about the words including the history of reviews
+        Json { text: "JSON backup", key: "json" },
+        // a deck with scheduled cards for Anki
+        Anki { text: "Anki deck", key: "apkg" },
+    }
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Csv, ExportFormat::Json, ExportFormat::Anki];
+
+    pub fn file_name(&self) -> String {
+        format!("words.{}", self.key())
+    }
+}
+
+/// JSON backup of all words of a user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    pub exported_at: DateTime<FixedOffset>,
+    pub decks: Vec<BackupDeck>,
+    pub words: Vec<BackupWord>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupDeck {
+    pub name: String,
+    pub from_lang: Option<String>,
+    pub to_lang: Option<String>,
+    pub reminders_enabled: bool,
+    pub directions: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupWord {
+    pub word: String,
+    pub translations: Vec<BackupTranslation>,
+    pub status: String,
+    /// Name of the deck
+    pub deck: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<FixedOffset>,
+    pub cards: Vec<BackupCard>,
+    /// From the oldest to the newest
+    pub reviews: Vec<BackupReview>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupTranslation {
+    pub text: String,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupCard {
+    pub direction: String,
+    pub level: i32,
+    pub ease_factor: f64,
+    pub interval: f64,
+    pub stability: f64,
+    pub difficulty: f64,
+    pub next_remind_at: DateTime<FixedOffset>,
+    pub reminded_at: DateTime<FixedOffset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupReview {
+    pub created_at: DateTime<FixedOffset>,
+    pub direction: String,
+    pub outcome: String,
+    pub level_before: i32,
+    pub level_after: i32,
+    pub answer: Option<String>,
+    pub latency: Option<i32>,
+}
+
+/// All words of a user with their decks and reviews
+#[derive(Debug, Clone)]
+pub struct Export {
+    words: Vec<Word>,
+    decks: Vec<Deck>,
+    /// Reviews by word id, from the oldest to the newest
+    reviews: HashMap<String, Vec<Review>>,
+}
+
+impl Export {
+    pub async fn load(db: &dyn Storage, chat_id: i64) -> StorageResult<Export> {
+        let words = db.get_all_words(chat_id, &WordFilter::default()).await?;
+
+        let mut reviews: HashMap<String, Vec<Review>> = HashMap::new();
+        let mut skip = 0;
+        loop {
+            let page = db
+                .get_user_reviews(chat_id, skip, REVIEWS_PAGE_SIZE)
+                .await?;
+            skip += page.len() as i64;
+            let is_last = (page.len() as i64) < REVIEWS_PAGE_SIZE;
+            // pages go from the newest reviews
+            for review in page.into_iter().rev() {
+                reviews
+                    .entry(review.word_id.clone())
+                    .or_default()
+                    .insert(0, review);
+            }
+            if is_last {
+                break;
+            }
+        }
+
+        Ok(Export {
+            words,
+            decks: db.get_decks(chat_id).await?,
+            reviews,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    pub fn build(&self, format: ExportFormat) -> Result<Vec<u8>, ExportError> {
+        match format {
+            ExportFormat::Csv => self.to_csv(),
+            ExportFormat::Json => Ok(serde_json::to_vec_pretty(&self.to_backup())?),
+            ExportFormat::Anki => Ok(anki::write_package(&self.to_anki())?),
+        }
+    }
+
+    fn deck_name(&self, word: &Word) -> Option<String> {
+        self.decks
+            .iter()
+            .find(|deck| Some(&deck.id) == word.deck_id.as_ref())
+            .map(|deck| deck.name.clone())
+    }
+
+    fn word_reviews(&self, word: &Word) -> &[Review] {
+        self.reviews.get(&word.id).map_or(&[], Vec::as_slice)
+    }
+
+    fn to_csv(&self) -> Result<Vec<u8>, ExportError> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer.write_record(CSV_HEADER)?;
+        for word in &self.words {
+            let reviews = self.word_reviews(word);
+            let next_review = word
+                .cards
+                .iter()
+                .map(|card| card.next_remind_at)
+                .min()
+                .map(|date| date.to_rfc3339())
+                .unwrap_or_default();
+            let last_review = reviews
+                .last()
+                .map(|review| review.created_at.to_rfc3339())
+                .unwrap_or_default();
+            writer.write_record([
+                word.word.clone(),
+                word.translations.to_plain_string(),
+                word.tags.join(" "),
+                self.deck_name(word).unwrap_or_default(),
+                word.status.key().to_owned(),
+                word.level(Direction::Forward).to_string(),
+                word.level(Direction::Reverse).to_string(),
+                next_review,
+                reviews.len().to_string(),
+                last_review,
+            ])?;
+        }
+        writer
+            .into_inner()
+            .map_err(|err| ExportError::Csv(err.into_error().into()))
+    }
+
+    fn to_backup(&self) -> Backup {
+        let decks = self
+            .decks
+            .iter()
+            .map(|deck| BackupDeck {
+                name: deck.name.clone(),
+                from_lang: deck.from_lang.clone(),
+                to_lang: deck.to_lang.clone(),
+                reminders_enabled: deck.reminders_enabled,
+                directions: deck.directions.map(|d| d.key().to_owned()),
+            })
+            .collect();
+        let words = self
+            .words
+            .iter()
+            .map(|word| BackupWord {
+                word: word.word.clone(),
+                translations: word
+                    .translations
+                    .iter()
+                    .map(|item| BackupTranslation {
+                        text: item.text.clone(),
+                        note: item.note.clone(),
+                    })
+                    .collect(),
+                status: word.status.key().to_owned(),
+                deck: self.deck_name(word),
+                tags: word.tags.clone(),
+                created_at: word.created_at,
+                cards: word
+                    .cards
+                    .iter()
+                    .map(|card| BackupCard {
+                        direction: card.direction.key().to_owned(),
+                        level: card.progress.level,
+                        ease_factor: card.progress.ease_factor,
+                        interval: card.progress.interval,
+                        stability: card.progress.stability,
+                        difficulty: card.progress.difficulty,
+                        next_remind_at: card.next_remind_at,
+                        reminded_at: card.reminded_at,
+                    })
+                    .collect(),
+                reviews: self
+                    .word_reviews(word)
+                    .iter()
+                    .map(|review| BackupReview {
+                        created_at: review.created_at,
+                        direction: review.direction.key().to_owned(),
+                        outcome: review.outcome.key().to_owned(),
+                        level_before: review.level_before,
+                        level_after: review.level_after,
+                        answer: review.answer.clone(),
+                        latency: review.latency,
+                    })
+                    .collect(),
+            })
+            .collect();
+        Backup {
+            version: BACKUP_VERSION,
+            exported_at: now(),
+            decks,
+            words,
+        }
+    }
+
+    // the forward card asks the word (ord 0), the reverse one the translation (ord 1)
+    fn to_anki(&self) -> Vec<AnkiNote> {
+        self.words
+            .iter()
+            .map(|word| {
+                let reviews = self.word_reviews(word);
+                let cards = word
+                    .cards
+                    .iter()
+                    .map(|card| {
+                        let reviews: Vec<&Review> = reviews
+                            .iter()
+                            .filter(|review| review.direction == card.direction)
+                            .collect();
+                        let reviewed = !reviews.is_empty() || card.progress.level > 0;
+                        AnkiCard {
+                            ord: match card.direction {
+                                Direction::Forward => 0,
+                                Direction::Reverse => 1,
+                            },
+                            due: reviewed.then_some(card.next_remind_at),
+                            interval: card.progress.interval,
+                            ease_factor: card.progress.ease_factor,
+                            reps: reviews.len() as i64,
+                            lapses: reviews
+                                .iter()
+                                .filter(|review| anki_ease(review.outcome) == 1)
+                                .count() as i64,
+                            suspended: word.status == WordStatus::Suspended,
+                            reviews: reviews
+                                .iter()
+                                .map(|review| AnkiReview {
+                                    created_at: review.created_at,
+                                    ease: anki_ease(review.outcome),
+                                    time: review.latency.unwrap_or(0) as i64,
+                                })
+                                .collect(),
+                        }
+                    })
+                    .collect();
+                AnkiNote {
+                    guid: word.id.clone(),
+                    front: word.word.clone(),
+                    back: word.translations.to_plain_string(),
+                    tags: word.tags.clone(),
+                    deck: self.deck_name(word),
+                    cards,
+                }
+            })
+            .collect()
+    }
+}
+
+// answer button in Anki: 1 - again, 2 - hard, 3 - good, 4 - easy
+fn anki_ease(outcome: ReviewOutcome) -> i64 {
+    match outcome {
+        ReviewOutcome::Wrong
+        | ReviewOutcome::Forgot
+        | ReviewOutcome::Timeout
+        | ReviewOutcome::Again => 1,
+        ReviewOutcome::AlmostCorrect | ReviewOutcome::Hard => 2,
+        ReviewOutcome::Correct | ReviewOutcome::Good => 3,
+        ReviewOutcome::Easy => 4,
+    }
+}
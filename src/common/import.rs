@@ -0,0 +1,461 @@
+use thiserror::Error;
+
+use crate::{
+    scheduler::Progress,
+    storage::{now, Card, Direction},
+};
+
+use super::{
+    anki::{self, AnkiCard, AnkiError},
+    normalize,
+    translation::{Translation, TranslationItem},
+};
+
+// Delimiters a file is checked for if it's not a .tsv
+static DELIMITERS: [u8; 3] = [b'\t', b';', b','];
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("The file is not a UTF-8 text")]
+    NotUtf8,
+    #[error("The file can't be read: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("The file has no rows")]
+    Empty,
+    #[error("{0}")]
+    Anki(#[from] AnkiError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Word,
+    Translation,
+    Note,
+    Tags,
+    Deck,
+    Skip,
+}
+
+impl Column {
+    pub const ALL: [Column; 6] = [
+        Column::Word,
+        Column::Translation,
+        Column::Note,
+        Column::Tags,
+        Column::Deck,
+        Column::Skip,
+    ];
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            Column::Word => "word",
+            Column::Translation => "translation",
+            Column::Note => "note",
+            Column::Tags => "tags",
+            Column::Deck => "deck",
+            Column::Skip => "skip",
+        }
+    }
+
+    // column of a header cell, e.g. "Translations" or "Back"
+    fn from_header(cell: &str) -> Option<Column> {
+        match normalize::key(cell).as_str() {
+            "word" | "words" | "term" | "front" => Some(Column::Word),
+            "translation" | "translations" | "meaning" | "definition" | "back" => {
+                Some(Column::Translation)
+            }
+            "note" | "notes" | "comment" | "example" => Some(Column::Note),
+            "tag" | "tags" => Some(Column::Tags),
+            "deck" => Some(Column::Deck),
+            // other columns of exported files
+            "status" | "forward_level" | "reverse_level" | "next_review" | "reviews"
+            | "last_review" => Some(Column::Skip),
+            _ => None,
+        }
+    }
+
+    /// The next column in `ALL`, used to change the mapping with a button
+    pub fn next(&self) -> Column {
+        Column::ALL
+            .into_iter()
+            .cycle()
+            .skip_while(|column| column != self)
+            .nth(1)
+            .unwrap_or(Column::Skip)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Table {
+    /// `None` for Anki decks
+    pub delimiter: Option<u8>,
+    pub header: Option<Vec<String>>,
+    pub rows: Vec<Vec<String>>,
+    /// Cards of the rows carried over from Anki, empty for CSV files
+    pub progress: Vec<Vec<Card>>,
+}
+
+/// A row of the table read according to the column mapping
+#[derive(Debug, Clone)]
+pub struct ImportRow {
+    pub word: String,
+    pub translation: Translation,
+    pub tags: Vec<String>,
+    pub deck: Option<String>,
+    /// New cards are created if empty
+    pub cards: Vec<Card>,
+}
+
+impl Table {
+    /// Reads a CSV or TSV file, the delimiter and the header are detected
+    pub fn parse(data: &[u8], file_name: &str) -> Result<Table, ImportError> {
+        let text = std::str::from_utf8(data).map_err(|_| ImportError::NotUtf8)?;
+        let text = text.trim_start_matches('\u{feff}');
+        let delimiter = detect_delimiter(text, file_name);
+
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(text.as_bytes());
+        let mut rows = vec![];
+        for record in reader.records() {
+            let row: Vec<String> = record?.iter().map(|cell| cell.trim().to_owned()).collect();
+            if row.iter().any(|cell| !cell.is_empty()) {
+                rows.push(row);
+            }
+        }
+        if rows.is_empty() {
+            return Err(ImportError::Empty);
+        }
+
+        let first = &rows[0];
+        let is_header = first
+            .iter()
+            .any(|c| Column::from_header(c) == Some(Column::Word))
+            && first
+                .iter()
+                .all(|c| c.is_empty() || Column::from_header(c).is_some());
+        let header = is_header.then(|| rows.remove(0));
+        Ok(Table {
+            delimiter: Some(delimiter),
+            header,
+            rows,
+            progress: vec![],
+        })
+    }
+
+    /// Reads notes of an .apkg file, a column for every field position followed
+    /// by tags and deck. Fields of different note types at the same position
+    /// share a column.
+    pub fn from_anki(data: &[u8]) -> Result<Table, ImportError> {
+        let collection = anki::read_package(data)?;
+        if collection.notes.is_empty() {
+            return Err(ImportError::Empty);
+        }
+
+        let fields_count = collection
+            .note_types
+            .iter()
+            .map(|fields| fields.len())
+            .max()
+            .unwrap_or(0);
+        let mut header: Vec<String> = (0..fields_count)
+            .map(|index| {
+                let mut names: Vec<&str> = vec![];
+                for fields in &collection.note_types {
+                    match fields.get(index) {
+                        Some(name) if !names.contains(&name.as_str()) => names.push(name),
+                        _ => {}
+                    }
+                }
+                names.join("/")
+            })
+            .collect();
+        header.push("tags".to_owned());
+        header.push("deck".to_owned());
+
+        let mut rows = vec![];
+        let mut progress = vec![];
+        for note in collection.notes {
+            let mut row = note.fields;
+            row.resize(fields_count, String::new());
+            row.push(note.tags.join(" "));
+            row.push(note.deck.unwrap_or_default());
+            rows.push(row);
+            progress.push(anki_cards(&note.cards));
+        }
+        Ok(Table {
+            delimiter: None,
+            header: Some(header),
+            rows,
+            progress,
+        })
+    }
+
+    pub fn columns_count(&self) -> usize {
+        self.header
+            .iter()
+            .chain(self.rows.iter())
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Columns named in the header, otherwise word, translation, note, tags and deck.
+    /// The first unknown columns are words and translations if the header has none.
+    pub fn default_mapping(&self) -> Vec<Column> {
+        let mut mapping: Vec<Column> = (0..self.columns_count())
+            .map(|index| match &self.header {
+                Some(header) => header
+                    .get(index)
+                    .and_then(|cell| Column::from_header(cell))
+                    .unwrap_or(Column::Skip),
+                None => Column::ALL.get(index).copied().unwrap_or(Column::Skip),
+            })
+            .collect();
+        for column in [Column::Word, Column::Translation] {
+            if mapping.contains(&column) {
+                continue;
+            }
+            if let Some(skipped) = mapping.iter_mut().find(|c| **c == Column::Skip) {
+                *skipped = column;
+            }
+        }
+        mapping
+    }
+
+    /// Rows with a word and a translation, the number of other rows is returned too
+    pub fn read(&self, mapping: &[Column]) -> (Vec<ImportRow>, usize) {
+        let mut rows = vec![];
+        let mut malformed = 0;
+        for (index, row) in self.rows.iter().enumerate() {
+            match read_row(row, mapping) {
+                Some(row) => rows.push(ImportRow {
+                    cards: self.progress.get(index).cloned().unwrap_or_default(),
+                    ..row
+                }),
+                None => malformed += 1,
+            }
+        }
+        (rows, malformed)
+    }
+}
+
+fn read_row(row: &[String], mapping: &[Column]) -> Option<ImportRow> {
+    let cell = |column: Column| {
+        mapping
+            .iter()
+            .zip(row)
+            .filter(|(c, cell)| **c == column && !cell.is_empty())
+            .map(|(_, cell)| cell.as_str())
+            .collect::<Vec<&str>>()
+    };
+
+    let word = normalize::clean(&cell(Column::Word).join(" "));
+    let mut items: Vec<TranslationItem> = cell(Column::Translation)
+        .into_iter()
+        .flat_map(|text| Translation::parse(text).to_vec())
+        .collect();
+    if word.is_empty() || items.is_empty() {
+        return None;
+    }
+    let note = normalize::clean(&cell(Column::Note).join("; "));
+    if !note.is_empty() && items[0].note.is_none() {
+        items[0].note = Some(note);
+    }
+    let tags = cell(Column::Tags)
+        .into_iter()
+        .flat_map(|tags| tags.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_owned)
+        .collect();
+    let deck = cell(Column::Deck)
+        .first()
+        .map(|deck| normalize::clean(deck));
+    Some(ImportRow {
+        word,
+        translation: Translation::new(items),
+        tags,
+        deck,
+        cards: vec![],
+    })
+}
+
+// Reviewed Anki cards continue with their intervals, the first card asks
+// the word and the second one the translation like in exported decks.
+// FSRS estimates stability and difficulty from the next answer.
+fn anki_cards(cards: &[AnkiCard]) -> Vec<Card> {
+    if cards.iter().all(|card| card.due.is_none()) {
+        return vec![];
+    }
+    Direction::ALL
+        .into_iter()
+        .enumerate()
+        .map(|(ord, direction)| {
+            let card = cards
+                .iter()
+                .find(|card| card.ord == ord as i64)
+                .and_then(|card| card.due.map(|due| (card, due)));
+            match card {
+                Some((card, due)) => Card {
+                    direction,
+                    progress: Progress {
+                        level: (card.reps - card.lapses).max(1) as i32,
+                        ease_factor: card.ease_factor,
+                        interval: card.interval,
+                        ..Progress::default()
+                    },
+                    next_remind_at: due,
+                    reminded_at: now(),
+                },
+                None => Card::new(direction),
+            }
+        })
+        .collect()
+}
+
+// .tsv files use tabs, otherwise the delimiter most used in the first line
+fn detect_delimiter(text: &str, file_name: &str) -> u8 {
+    if file_name.to_lowercase().ends_with(".tsv") {
+        return b'\t';
+    }
+    let first_line = text
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("");
+    DELIMITERS
+        .into_iter()
+        .map(|delimiter| {
+            let count = first_line.bytes().filter(|b| *b == delimiter).count();
+            (delimiter, count)
+        })
+        .filter(|(_, count)| *count > 0)
+        .max_by_key(|(_, count)| *count)
+        .map_or(b',', |(delimiter, _)| delimiter)
+}
+
+/// Name of the delimiter shown to the user
+pub fn delimiter_name(delimiter: u8) -> &'static str {
+    match delimiter {
+        b'\t' => "tab",
+        b';' => "semicolon",
+        _ => "comma",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(table: &Table, mapping: &[Column]) -> Vec<(String, String)> {
+        table
+            .read(mapping)
+            .0
+            .into_iter()
+            .map(|row| (row.word, row.translation.to_plain_string()))
+            .collect()
+    }
+
+    #[test]
+    fn delimiter_is_detected() {
+        assert_eq!(detect_delimiter("a,b;c;d", "words.csv"), b';');
+        assert_eq!(detect_delimiter("\n\na\tb,c\td", "words.txt"), b'\t');
+        assert_eq!(detect_delimiter("a,b;c", "words.tsv"), b'\t');
+        assert_eq!(detect_delimiter("word", "words.csv"), b',');
+    }
+
+    #[test]
+    fn parse_detects_header() {
+        let table = Table::parse(
+            "\u{feff}Front;Back;Tags\nperro;dog;animals\n".as_bytes(),
+            "a.csv",
+        )
+        .unwrap();
+        assert_eq!(table.delimiter, Some(b';'));
+        assert_eq!(table.rows, vec![vec!["perro", "dog", "animals"]]);
+        assert_eq!(
+            table.default_mapping(),
+            [Column::Word, Column::Translation, Column::Tags]
+        );
+
+        // "dog" isn't a known column, so the first row is a word
+        let table = Table::parse(b"word,dog\n", "a.csv").unwrap();
+        assert_eq!(table.header, None);
+        assert_eq!(table.rows.len(), 1);
+    }
+
+    #[test]
+    fn parse_fails_on_empty_or_binary_files() {
+        assert!(matches!(
+            Table::parse(b" \n,,\n", "a.csv"),
+            Err(ImportError::Empty)
+        ));
+        assert!(matches!(
+            Table::parse(&[0xff, 0xfe, 0x00], "a.csv"),
+            Err(ImportError::NotUtf8)
+        ));
+    }
+
+    #[test]
+    fn parse_reads_quoted_cells() {
+        let table = Table::parse(b"perro,\"dog, hound\"\n\"gato\",cat\n", "a.csv").unwrap();
+        assert_eq!(
+            words(&table, &table.default_mapping()),
+            [
+                ("perro".to_owned(), "dog, hound".to_owned()),
+                ("gato".to_owned(), "cat".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn default_mapping_fills_word_and_translation() {
+        let table = Table::parse(b"tags;notes;x;y\na;b;c;d\n", "a.csv").unwrap();
+        assert!(table.header.is_none());
+        assert_eq!(
+            table.default_mapping(),
+            [
+                Column::Word,
+                Column::Translation,
+                Column::Note,
+                Column::Tags
+            ]
+        );
+
+        let table = Table {
+            delimiter: Some(b','),
+            header: Some(vec!["tags".to_owned(), "x".to_owned(), "y".to_owned()]),
+            rows: vec![],
+            progress: vec![],
+        };
+        assert_eq!(
+            table.default_mapping(),
+            [Column::Tags, Column::Word, Column::Translation]
+        );
+    }
+
+    #[test]
+    fn read_counts_malformed_rows() {
+        let table = Table::parse(
+            b"perro,dog,paw,pets animals,Spanish\ngato,,,,\n,cat,,,\n",
+            "a.csv",
+        )
+        .unwrap();
+        let (rows, malformed) = table.read(&table.default_mapping());
+        assert_eq!(malformed, 2);
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!(row.word, "perro");
+        assert_eq!(row.translation.to_plain_string(), "dog (paw)");
+        assert_eq!(row.tags, ["pets", "animals"]);
+        assert_eq!(row.deck.as_deref(), Some("Spanish"));
+        assert!(row.cards.is_empty());
+    }
+
+    #[test]
+    fn column_next_cycles() {
+        assert_eq!(Column::Word.next(), Column::Translation);
+        assert_eq!(Column::Skip.next(), Column::Word);
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+
+/// Time of the day when the user doesn't want to be reminded,
+/// the end is earlier than the start if the window goes over midnight
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuietHours {
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+}
+
+impl QuietHours {
+    /// "22:00-08:00" or "22-8", the start and the end can't be the same
+    pub fn parse(text: &str) -> Option<QuietHours> {
+        let (from, to) = text.trim().split_once(['-', '–'])?;
+        let (from, to) = (parse_time(from)?, parse_time(to)?);
+        (from != to).then_some(QuietHours { from, to })
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.from < self.to {
+            self.from <= time && time < self.to
+        } else {
+            self.from <= time || time < self.to
+        }
+    }
+}
+
+impl Display for QuietHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.from.format("%H:%M"),
+            self.to.format("%H:%M")
+        )
+    }
+}
+
+// "8", "08:30"
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    let text = text.trim();
+    let (hours, minutes) = text.split_once(':').unwrap_or((text, "0"));
+    NaiveTime::from_hms_opt(hours.parse().ok()?, minutes.parse().ok()?, 0)
+}
+
+/// Timezone by its IANA name, e.g. Europe/Berlin
+pub fn parse_timezone(text: &str) -> Option<Tz> {
+    text.trim().parse().ok()
+}
+
+/// Timezone of the longitude without daylight saving time, e.g. Etc/GMT-3
+pub fn timezone_from_location(longitude: f64) -> Tz {
+    let offset = (longitude / 15.0).round().clamp(-12.0, 12.0) as i32;
+    // the sign of Etc zones is inverted
+    let name = match offset {
+        0 => "Etc/GMT".to_owned(),
+        offset => format!("Etc/GMT{:+}", -offset),
+    };
+    parse_timezone(&name).unwrap_or(Tz::UTC)
+}
+
+/// The first moment since `at` which is out of the quiet hours
+pub fn next_allowed(
+    at: DateTime<FixedOffset>,
+    timezone: Tz,
+    quiet_hours: Option<QuietHours>,
+) -> DateTime<FixedOffset> {
+    let Some(quiet_hours) = quiet_hours else {
+        return at;
+    };
+    let local = at.with_timezone(&timezone);
+    if !quiet_hours.contains(local.time()) {
+        return at;
+    }
+
+    let mut end = local.date_naive().and_time(quiet_hours.to);
+    if end <= local.naive_local() {
+        end += Duration::days(1);
+    }
+    // the end could be skipped by daylight saving time, then it's an hour later
+    let end = timezone
+        .from_local_datetime(&end)
+        .earliest()
+        .or_else(|| {
+            timezone
+                .from_local_datetime(&(end + Duration::hours(1)))
+                .earliest()
+        })
+        .unwrap_or(local + Duration::hours(1));
+    end.with_timezone(at.offset())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hours: u32, minutes: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hours, minutes, 0).unwrap()
+    }
+
+    fn utc(text: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(text).unwrap()
+    }
+
+    fn night() -> QuietHours {
+        QuietHours::parse("22:00-08:00").unwrap()
+    }
+
+    #[test]
+    fn parse_quiet_hours() {
+        assert_eq!(night(), QuietHours::parse(" 22-8 ").unwrap());
+        assert_eq!(
+            QuietHours::parse("13:30–14").unwrap().to_string(),
+            "13:30-14:00"
+        );
+        assert_eq!(QuietHours::parse("8-8"), None);
+        assert_eq!(QuietHours::parse("25-8"), None);
+        assert_eq!(QuietHours::parse("22"), None);
+    }
+
+    #[test]
+    fn contains_within_day() {
+        let lunch = QuietHours::parse("12-14").unwrap();
+        assert!(!lunch.contains(time(11, 59)));
+        assert!(lunch.contains(time(12, 0)));
+        assert!(!lunch.contains(time(14, 0)));
+    }
+
+    #[test]
+    fn contains_across_midnight() {
+        assert!(night().contains(time(22, 0)));
+        assert!(night().contains(time(0, 0)));
+        assert!(night().contains(time(7, 59)));
+        assert!(!night().contains(time(8, 0)));
+        assert!(!night().contains(time(21, 59)));
+    }
+
+    #[test]
+    fn next_allowed_across_midnight() {
+        let at = utc("2026-01-10T23:00:00Z");
+        assert_eq!(next_allowed(at, Tz::UTC, None), at);
+        assert_eq!(
+            next_allowed(at, Tz::UTC, Some(night())),
+            utc("2026-01-11T08:00:00Z")
+        );
+        assert_eq!(
+            next_allowed(utc("2026-01-11T07:00:00Z"), Tz::UTC, Some(night())),
+            utc("2026-01-11T08:00:00Z")
+        );
+        let day = utc("2026-01-11T12:00:00Z");
+        assert_eq!(next_allowed(day, Tz::UTC, Some(night())), day);
+    }
+
+    #[test]
+    fn next_allowed_in_timezone() {
+        // midnight in Berlin (UTC+1) is 08:00 in Tokyo (UTC+9)
+        let at = utc("2026-01-10T23:00:00+00:00");
+        let allowed = next_allowed(at, Tz::Europe__Berlin, Some(night()));
+        assert_eq!(allowed, utc("2026-01-11T07:00:00Z"));
+        assert_eq!(allowed.offset(), at.offset());
+        assert_eq!(next_allowed(at, Tz::Asia__Tokyo, Some(night())), at);
+    }
+
+    #[test]
+    fn next_allowed_across_dst() {
+        let quiet_hours = QuietHours::parse("22:00-02:30");
+        // 02:30 is skipped on 29 March in Berlin, the clocks go from 02:00 to 03:00
+        assert_eq!(
+            next_allowed(utc("2026-03-28T22:00:00Z"), Tz::Europe__Berlin, quiet_hours),
+            utc("2026-03-29T03:30:00+02:00")
+        );
+        // 02:30 happens twice on 25 October, the earlier one is used
+        assert_eq!(
+            next_allowed(utc("2026-10-24T21:00:00Z"), Tz::Europe__Berlin, quiet_hours),
+            utc("2026-10-25T02:30:00+02:00")
+        );
+        // the end of the night is 08:00 in summer time
+        assert_eq!(
+            next_allowed(
+                utc("2026-03-29T00:00:00Z"),
+                Tz::Europe__Berlin,
+                Some(night())
+            ),
+            utc("2026-03-29T08:00:00+02:00")
+        );
+    }
+
+    #[test]
+    fn timezone_of_location() {
+        assert_eq!(timezone_from_location(13.4), Tz::Etc__GMTMinus1);
+        assert_eq!(timezone_from_location(-74.0), Tz::Etc__GMTPlus5);
+        assert_eq!(timezone_from_location(2.0), Tz::Etc__GMT);
+        assert_eq!(parse_timezone(" Europe/Berlin "), Some(Tz::Europe__Berlin));
+        assert_eq!(parse_timezone("Mars/Olympus"), None);
+    }
+}
@@ -1,4 +1,11 @@
+pub mod anki;
+pub mod bulk;
 pub mod config;
+pub mod export;
+pub mod import;
+pub mod local_time;
+pub mod normalize;
+pub mod search;
 pub mod translation;
 use teloxide::macros::BotCommands;
 
@@ -14,4 +21,46 @@ pub enum Command {
     Help,
     #[command(description = "Start")]
     Start,
+    #[command(description = "return a word from the archive to learning.")]
+    Activate(String),
+    #[command(description = "stop reminding a word.")]
+    Suspend(String),
+    #[command(description = "change a word, its translations or start learning it again.")]
+    Edit(String),
+    #[command(description = "turn on/off rare reminders of mastered words.")]
+    Refresher,
+    #[command(
+        description = "review all due words now, write a number after the command to review only some of them."
+    )]
+    Review(String),
+    #[command(description = "switch between typing answers, grading yourself and quizzes.")]
+    Mode,
+    #[command(
+        description = "set how strictly answers are checked: exact, lenient or very_lenient."
+    )]
+    Strictness(String),
+    #[command(
+        description = "set review directions: forward, reverse or both. Add a deck name to set them only for the deck."
+    )]
+    Directions(String),
+    #[command(
+        description = "set which due word is reminded first: most_overdue, lowest_level, weighted_random or interleaved."
+    )]
+    Order(String),
+    #[command(
+        description = "set your timezone by a name like Europe/Berlin, or share a location without a name."
+    )]
+    Timezone(String),
+    #[command(
+        description = "set hours without reminders in your timezone, e.g. 22:00-08:00, or turn them off."
+    )]
+    Quiet(String),
+    #[command(
+        description = "change reminder interval, answer checking, review directions, words per page and sound."
+    )]
+    Settings,
+    #[command(
+        description = "get all words with their progress as a file: csv, json or apkg (Anki)."
+    )]
+    Export(String),
 }
@@ -0,0 +1,75 @@
+use caseless::default_case_fold_str;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+// Letters which don't decompose into a base letter and a diacritic
+static LOOSE_EQUIVALENTS: [(char, &str); 6] = [
+    ('ø', "o"),
+    ('æ', "ae"),
+    ('œ', "oe"),
+    ('ł', "l"),
+    ('đ', "d"),
+    ('ı', "i"),
+];
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Canonical (NFC) text with collapsed whitespace, the way words are stored
+pub fn clean(text: &str) -> String {
+    collapse_whitespace(&text.nfc().collect::<String>())
+}
+
+/// Compatibility (NFKC) case folded text, used to compare words and answers
+/// "Haus " and "haus", "Straße" and "STRASSE" have the same key
+pub fn key(text: &str) -> String {
+    let folded = default_case_fold_str(&text.nfkc().collect::<String>());
+    collapse_whitespace(&folded.nfkc().collect::<String>())
+}
+
+/// `key` which also ignores diacritics, e.g. "café" and "cafe", "ёж" and "еж"
+pub fn loose_key(text: &str) -> String {
+    key(text)
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .fold(String::new(), |mut result, c| {
+            match LOOSE_EQUIVALENTS.iter().find(|(from, _)| *from == c) {
+                Some((_, to)) => result.push_str(to),
+                None => result.push(c),
+            }
+            result
+        })
+        .nfc()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_ignores_case_and_whitespace() {
+        assert_eq!(key(" Haus  "), "haus");
+        assert_eq!(key("New   York"), key("new york"));
+        assert_eq!(key("Straße"), key("STRASSE"));
+    }
+
+    #[test]
+    fn key_unifies_unicode_forms() {
+        // precomposed and combining "é", fullwidth letters
+        assert_eq!(key("caf\u{e9}"), key("cafe\u{301}"));
+        assert_eq!(key("ＡＢＣ"), "abc");
+    }
+
+    #[test]
+    fn key_keeps_diacritics() {
+        assert_ne!(key("café"), key("cafe"));
+        assert_eq!(loose_key("café"), loose_key("cafe"));
+        assert_eq!(loose_key("Ærø"), "aero");
+    }
+
+    #[test]
+    fn clean_keeps_case() {
+        assert_eq!(clean("  Der   Hund "), "Der Hund");
+    }
+}
@@ -0,0 +1,59 @@
+use crate::storage::Word;
+
+use super::{
+    normalize,
+    translation::{edit_distance, Strictness},
+};
+
+// Queries shorter than this (in chars) aren't matched with typos
+static MIN_FUZZY_QUERY: usize = 3;
+
+/// How well a text matches a query, the lower the better
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Match {
+    Exact,
+    Prefix,
+    Contains,
+    /// The beginning of the text is the query with a few typos
+    Fuzzy,
+}
+
+fn match_text(text: &str, query: &str, loose_query: &str) -> Option<Match> {
+    let key = normalize::key(text);
+    if key == query {
+        return Some(Match::Exact);
+    }
+    if key.starts_with(query) {
+        return Some(Match::Prefix);
+    }
+    if key.contains(query) {
+        return Some(Match::Contains);
+    }
+
+    let query_len = loose_query.chars().count();
+    if query_len < MIN_FUZZY_QUERY {
+        return None;
+    }
+    let start: String = normalize::loose_key(text).chars().take(query_len).collect();
+    let max_typos = Strictness::Lenient.max_typos(query_len).max(1);
+    (edit_distance(&start, loose_query) <= max_typos).then_some(Match::Fuzzy)
+}
+
+/// Words whose text or translations match the query, the best matches first.
+/// Matches of the same quality keep the order of `words`.
+pub fn search<'a>(words: &'a [Word], query: &str) -> Vec<&'a Word> {
+    let loose_query = normalize::loose_key(query);
+    let query = normalize::key(query);
+    let mut found: Vec<(Match, &Word)> = words
+        .iter()
+        .filter_map(|word| {
+            std::iter::once(word.word.as_str())
+                .chain(word.translations.texts())
+                .filter_map(|text| match_text(text, &query, &loose_query))
+                .min()
+                .map(|quality| (quality, word))
+        })
+        .collect();
+    found.sort_by_key(|(quality, _)| *quality);
+    found.into_iter().map(|(_, word)| word).collect()
+}
@@ -3,13 +3,101 @@ use std::{
     vec,
 };
 
+use teloxide::utils::markdown;
+
+use crate::key_value_enum;
+
+use super::normalize;
+
 static SEPARATOR: &str = ", ";
 
+key_value_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Strictness {
+        Exact { text: "exact", key: "exact" },
+        Lenient { text: "lenient", key: "lenient" },
+        VeryLenient { text: "very lenient", key: "very_lenient" },
+    }
+}
+
+impl Strictness {
+    pub const ALL: [Strictness; 3] = [
+        Strictness::Exact,
+        Strictness::Lenient,
+        Strictness::VeryLenient,
+    ];
+
+    /// Amount of typos allowed in an answer of the given length (in chars)
+    pub fn max_typos(&self, len: usize) -> usize {
+        match self {
+            Strictness::Exact => 0,
+            // 1 typo from 5 letters, 2 typos from 10 letters...
+            Strictness::Lenient => len / 5,
+            // 1 typo from 3 letters, 2 typos from 6 letters...
+            Strictness::VeryLenient => len / 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnswerCheck {
+    Correct,
+    /// Contains the expected spelling of the closest translation
+    AlmostCorrect(String),
+    Wrong,
+}
+
 #[derive(Debug, Clone, PartialEq, Hash)]
-pub struct Translation(vec::Vec<String>);
+pub struct TranslationItem {
+    pub text: String,
+    /// Clarification shown next to the translation, not required in answers
+    pub note: Option<String>,
+}
+
+impl TranslationItem {
+    pub fn new(text: impl Into<String>, note: Option<String>) -> Self {
+        Self {
+            text: text.into(),
+            note,
+        }
+    }
+
+    /// "bank (of a river)" -> text `bank` with note `of a river`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = normalize::clean(text);
+        let (text, note) = match text.strip_suffix(')').and_then(|t| t.rsplit_once('(')) {
+            Some((text, note)) if !text.trim().is_empty() => (text.trim(), Some(note.trim())),
+            _ => (text.as_str(), None),
+        };
+        if text.is_empty() {
+            return None;
+        }
+        let note = note.filter(|n| !n.is_empty()).map(str::to_owned);
+        Some(Self::new(text, note))
+    }
+
+    /// "bank (of a river)", the way it's parsed
+    pub fn to_plain_string(&self) -> String {
+        match &self.note {
+            Some(note) => format!("{} ({})", self.text, note),
+            None => self.text.clone(),
+        }
+    }
+
+    pub fn to_formatted_string(&self) -> String {
+        let text = format!("`{}`", markdown::escape_code(&self.text));
+        match &self.note {
+            Some(note) => format!("{} _\\({}\\)_", text, markdown::escape(note)),
+            None => text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Hash, Default)]
+pub struct Translation(vec::Vec<TranslationItem>);
 
 impl Deref for Translation {
-    type Target = vec::Vec<String>;
+    type Target = vec::Vec<TranslationItem>;
 
     fn deref(&self) -> &Self::Target {
         &self.0
@@ -23,38 +111,102 @@ impl DerefMut for Translation {
 }
 
 impl Translation {
-    pub fn new(text: impl Into<String>) -> Self {
-        Self(convert_to_vec(text))
+    pub fn new(items: vec::Vec<TranslationItem>) -> Self {
+        Self(items)
     }
 
-    pub fn to_string(&self) -> String {
-        self.0.join(SEPARATOR)
+    /// Translations followed by the new ones which are not among them yet
+    pub fn merge(&self, other: &Translation) -> Self {
+        let mut items = self.0.clone();
+        for item in other.iter() {
+            let key = normalize::key(&item.text);
+            if !items.iter().any(|i| normalize::key(&i.text) == key) {
+                items.push(item.clone());
+            }
+        }
+        Self(items)
+    }
+
+    /// Every line is a separate translation, a single line is split by `SEPARATOR`
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        let parts: vec::Vec<&str> = if text.contains('\n') {
+            text.lines().collect()
+        } else {
+            text.split(SEPARATOR).collect()
+        };
+        Self(
+            parts
+                .into_iter()
+                .filter_map(TranslationItem::parse)
+                .collect(),
+        )
     }
 
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
 
+    pub fn texts(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|item| item.text.as_str())
+    }
+
+    /// Translations joined with `SEPARATOR`, can be parsed back
+    pub fn to_plain_string(&self) -> String {
+        self.0
+            .iter()
+            .map(|item| item.to_plain_string())
+            .collect::<vec::Vec<String>>()
+            .join(SEPARATOR)
+    }
+
     pub fn to_formatted_string(&self) -> String {
         self.0
             .iter()
-            .map(|s| format!("`{}`", s))
+            .map(|item| item.to_formatted_string())
             .collect::<Vec<String>>()
             .join(", ")
     }
 
-    pub fn check(&self, translation: &str) -> bool {
-        self.0.iter().any(|s| s.eq_ignore_ascii_case(translation))
+    pub fn check(&self, translation: &str, strictness: Strictness) -> AnswerCheck {
+        let answer = normalize::key(translation);
+        if self.texts().any(|s| normalize::key(s) == answer) {
+            return AnswerCheck::Correct;
+        }
+
+        // lenient modes ignore diacritics, the expected spelling is shown anyway
+        let fold: fn(&str) -> String = match strictness {
+            Strictness::Exact => normalize::key,
+            _ => normalize::loose_key,
+        };
+        let answer = fold(translation);
+        self.texts()
+            .map(|s| (s, edit_distance(&fold(s), &answer)))
+            .filter(|(s, distance)| *distance <= strictness.max_typos(s.chars().count()))
+            .min_by_key(|(_, distance)| *distance)
+            .map_or(AnswerCheck::Wrong, |(s, _)| {
+                AnswerCheck::AlmostCorrect(s.to_owned())
+            })
     }
 }
 
-impl From<&Translation> for String {
-    fn from(translation: &Translation) -> Self {
-        translation.0.join(SEPARATOR)
+/// Levenshtein distance counted in chars
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: vec::Vec<char> = b.chars().collect();
+    let mut prev: vec::Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(prev[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut current);
     }
+    prev[b.len()]
 }
 
-impl From<Translation> for vec::Vec<String> {
+impl From<Translation> for vec::Vec<TranslationItem> {
     fn from(translation: Translation) -> Self {
         translation.0
     }
@@ -65,7 +217,8 @@ impl From<vec::Vec<String>> for Translation {
         Self(
             vec.iter()
                 .map(|s| s.trim().to_string())
-                .filter(|s| s.is_empty() == false)
+                .filter(|s| !s.is_empty())
+                .map(|s| TranslationItem::new(s, None))
                 .collect(),
         )
     }
@@ -73,29 +226,123 @@ impl From<vec::Vec<String>> for Translation {
 
 impl From<&str> for Translation {
     fn from(text: &str) -> Self {
-        Self(convert_to_vec(text))
+        Self::parse(text)
     }
 }
 
 impl From<String> for Translation {
     fn from(text: String) -> Self {
-        Self(convert_to_vec(text))
+        Self::parse(&text)
     }
 }
 
-impl From<Translation> for String {
-    fn from(translation: Translation) -> Self {
-        translation.0.join(SEPARATOR)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_typos_grow_with_length() {
+        assert_eq!(Strictness::Exact.max_typos(20), 0);
+        assert_eq!(Strictness::Lenient.max_typos(4), 0);
+        assert_eq!(Strictness::Lenient.max_typos(5), 1);
+        assert_eq!(Strictness::Lenient.max_typos(10), 2);
+        assert_eq!(Strictness::VeryLenient.max_typos(2), 0);
+        assert_eq!(Strictness::VeryLenient.max_typos(3), 1);
+        assert_eq!(Strictness::VeryLenient.max_typos(6), 2);
+    }
+
+    #[test]
+    fn edit_distance_counts_chars() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("ёж", "еж"), 1);
+    }
+
+    #[test]
+    fn check_accepts_any_translation() {
+        let translation = Translation::parse("dog, hound");
+        assert_eq!(
+            translation.check(" Hound ", Strictness::Exact),
+            AnswerCheck::Correct
+        );
+        assert_eq!(
+            translation.check("cat", Strictness::VeryLenient),
+            AnswerCheck::Wrong
+        );
+    }
+
+    #[test]
+    fn check_allows_typos_by_strictness() {
+        let translation = Translation::parse("elephant");
+        assert_eq!(
+            translation.check("elepant", Strictness::Exact),
+            AnswerCheck::Wrong
+        );
+        assert_eq!(
+            translation.check("elepant", Strictness::Lenient),
+            AnswerCheck::AlmostCorrect("elephant".to_owned())
+        );
+        assert_eq!(
+            translation.check("elefant", Strictness::Lenient),
+            AnswerCheck::Wrong
+        );
+        assert_eq!(
+            translation.check("elefant", Strictness::VeryLenient),
+            AnswerCheck::AlmostCorrect("elephant".to_owned())
+        );
+    }
+
+    #[test]
+    fn check_ignores_diacritics_when_lenient() {
+        let translation = Translation::parse("café");
+        assert_eq!(
+            translation.check("cafe", Strictness::Exact),
+            AnswerCheck::Wrong
+        );
+        assert_eq!(
+            translation.check("cafe", Strictness::Lenient),
+            AnswerCheck::AlmostCorrect("café".to_owned())
+        );
     }
-}
 
-fn convert_to_vec<T>(text: T) -> vec::Vec<String>
-where
-    T: Into<String>,
-{
-    text.into()
-        .split(SEPARATOR)
-        .map(|s| s.trim().to_string())
-        .filter(|s| s.is_empty() == false)
-        .collect()
+    #[test]
+    fn item_parse_splits_note() {
+        assert_eq!(
+            TranslationItem::parse(" bank (of a river) "),
+            Some(TranslationItem::new("bank", Some("of a river".to_owned())))
+        );
+        assert_eq!(
+            TranslationItem::parse("bank ()"),
+            Some(TranslationItem::new("bank", None))
+        );
+        // a text in parentheses only is not a note
+        assert_eq!(
+            TranslationItem::parse("(of a river)"),
+            Some(TranslationItem::new("(of a river)", None))
+        );
+        assert_eq!(TranslationItem::parse("   "), None);
+    }
+
+    #[test]
+    fn parse_splits_by_separator_or_lines() {
+        let translation = Translation::parse(" dog, hound (hunting) ");
+        assert_eq!(translation.texts().collect::<Vec<_>>(), ["dog", "hound"]);
+        assert_eq!(translation.to_plain_string(), "dog, hound (hunting)");
+        assert!(Translation::parse("  ").is_empty());
+
+        let translation = Translation::parse("a dog, a hound\na cat");
+        assert_eq!(
+            translation.texts().collect::<Vec<_>>(),
+            ["a dog, a hound", "a cat"]
+        );
+    }
+
+    #[test]
+    fn plain_string_is_parsed_back() {
+        let translation = Translation::parse("bank (of a river), shore");
+        assert_eq!(
+            Translation::parse(&translation.to_plain_string()),
+            translation
+        );
+    }
 }
@@ -1,17 +1,51 @@
-use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use teloxide::types::{CallbackQuery, InlineKeyboardButton, InlineKeyboardMarkup};
 
 use crate::key_value_enum;
 
+// Separates the button key from its payload in callback data
+static PAYLOAD_SEPARATOR: char = ':';
+
 key_value_enum! {
     #[derive(Debug, Clone, Copy, Hash, PartialEq)]
     pub enum Button {
         AddWord { text: "Add word", key: "add_word" },
+        BulkAdd { text: "Add many", key: "bulk_add" },
         RemoveWord { text: "Remove word", key: "remove_word" },
         ListWords { text: "List words", key: "list_words" },
+        Archive { text: "Archive", key: "archive" },
+        Decks { text: "Decks", key: "decks" },
+        NewDeck { text: "New deck", key: "new_deck" },
+        RenameDeck { text: "Rename", key: "rename_deck" },
+        DeleteDeck { text: "Delete", key: "delete_deck" },
+        ChooseDeck { text: "Choose active", key: "choose_deck" },
+        ToggleDeckReminders { text: "Reminders on/off", key: "toggle_deck_reminders" },
+        NextDeck { text: "Switch deck", key: "next_deck" },
+        EditWord { text: "Edit word", key: "edit_word" },
+        ChangeWord { text: "Change word", key: "change_word" },
+        ReplaceTranslations { text: "Replace translations", key: "replace_translations" },
+        AppendTranslations { text: "Add translations", key: "append_translations" },
+        ResetProgress { text: "Reset progress", key: "reset_progress" },
         Cancel { text: "Cancel", key: "cancel" },
         PrevPage { text: "←", key: "prev_page" },
         NextPage { text: "→", key: "next_page" },
         Forgot { text: "Forgot", key: "forgot" },
+        ShowAnswer { text: "Show answer", key: "show_answer" },
+        Again { text: "Again", key: "grade_again" },
+        Hard { text: "Hard", key: "grade_hard" },
+        Good { text: "Good", key: "grade_good" },
+        Easy { text: "Easy", key: "grade_easy" },
+        QuizOption { text: "Option", key: "quiz_option" },
+        ChallengeOption { text: "Challenge option", key: "challenge" },
+        ImportColumn { text: "Column", key: "import_column" },
+        ImportPolicy { text: "Existing words: skip/merge/overwrite", key: "import_policy" },
+        ImportProgress { text: "Anki progress: keep/reset", key: "import_progress" },
+        StartImport { text: "Import", key: "start_import" },
+        RemindInterval { text: "Reminder interval", key: "settings_interval" },
+        SettingsStrictness { text: "Answer checking", key: "settings_strictness" },
+        SettingsDirections { text: "Review directions", key: "settings_directions" },
+        WordsPerPage { text: "Words per page", key: "settings_page_size" },
+        Sound { text: "Notification sound", key: "settings_sound" },
+        SettingsScheduler { text: "Scheduler", key: "settings_scheduler" },
     }
 }
 
@@ -23,6 +57,36 @@ impl Button {
     pub fn to_keyboard(&self) -> InlineKeyboardMarkup {
         InlineKeyboardMarkup::new(vec![vec![self.to_inline_button()]])
     }
+
+    /// Button with its own text and data appended to the key, e.g. `quiz_option:2`.
+    /// Telegram limits the whole callback data to 64 bytes.
+    pub fn to_inline_button_with_payload(
+        &self,
+        text: impl Into<String>,
+        payload: &str,
+    ) -> InlineKeyboardButton {
+        InlineKeyboardButton::callback(
+            text,
+            format!("{}{}{}", self.key(), PAYLOAD_SEPARATOR, payload),
+        )
+    }
+
+    /// Parses callback data of a button with or without a payload
+    pub fn from_callback_data(data: &str) -> Result<Self, &'static str> {
+        match data.split_once(PAYLOAD_SEPARATOR) {
+            Some((key, _)) => Self::from_key(key),
+            None => Self::from_key(data),
+        }
+    }
+}
+
+/// Payload of the pressed button, see `Button::to_inline_button_with_payload`
+pub fn payload(query: &CallbackQuery) -> Option<&str> {
+    query
+        .data
+        .as_deref()
+        .and_then(|data| data.split_once(PAYLOAD_SEPARATOR))
+        .map(|(_, payload)| payload)
 }
 
 pub fn make(buttons: &[Vec<Option<Button>>]) -> InlineKeyboardMarkup {
@@ -43,13 +107,29 @@ pub fn make(buttons: &[Vec<Option<Button>>]) -> InlineKeyboardMarkup {
     InlineKeyboardMarkup::new(keyboard)
 }
 
+/// Self-grade buttons, the payload tells which card is graded
+pub fn grades(payload: &str) -> InlineKeyboardMarkup {
+    let buttons = [Button::Again, Button::Hard, Button::Good, Button::Easy]
+        .iter()
+        .map(|button| button.to_inline_button_with_payload(button.text(), payload))
+        .collect::<Vec<_>>();
+    InlineKeyboardMarkup::new(vec![buttons])
+}
+
 pub fn words_actions() -> InlineKeyboardMarkup {
     let keyboard: Vec<Vec<InlineKeyboardButton>> = vec![
         vec![
             Button::AddWord.to_inline_button(),
             Button::RemoveWord.to_inline_button(),
         ],
-        vec![Button::ListWords.to_inline_button()],
+        vec![
+            Button::ListWords.to_inline_button(),
+            Button::Archive.to_inline_button(),
+        ],
+        vec![
+            Button::BulkAdd.to_inline_button(),
+            Button::Decks.to_inline_button(),
+        ],
     ];
 
     InlineKeyboardMarkup::new(keyboard)
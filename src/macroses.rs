@@ -26,6 +26,7 @@ macro_rules! key_value_enum {
                 }
             }
 
+            #[allow(dead_code)]
             pub fn from_option_key(key: Option<String>) -> Result<Self, &'static str> {
                 match key {
                     Some(key) => Self::from_key(&key),
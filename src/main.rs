@@ -1,4 +1,5 @@
 extern crate dotenv;
+mod challenges;
 mod clients;
 mod common;
 mod keyboard;
@@ -6,18 +7,15 @@ mod macroses;
 #[allow(warnings)]
 mod prisma;
 mod reminder;
+mod scheduler;
 mod state;
 mod storage;
-use std::env;
-use teloxide::{
-    prelude::*,
-    types::{
-        InlineKeyboardButton, InlineKeyboardMarkup, InlineQueryResultArticle, InputMessageContent,
-        InputMessageContentText,
-    },
-};
+#[cfg(test)]
+mod testing;
+use std::{env, sync::Arc};
+use teloxide::prelude::*;
 
-use crate::storage::Storage;
+use crate::storage::{database::PrismaStorage, memory::MemoryStorage, Storage};
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
@@ -26,10 +24,7 @@ async fn main() -> Result<(), String> {
 
     pretty_env_logger::init();
 
-    let db = prisma::PrismaClient::_builder()
-        .build()
-        .await
-        .expect("Failed to connect to database");
+    let db = connect_storage().await;
     let teloxide_token = env::var("TELOXIDE_TOKEN").expect("TELOXIDE_TOKEN must be set.");
     let last5 = &teloxide_token[teloxide_token.len() - 5..];
     log::info!("Starting reminder bot with token {}...", last5);
@@ -39,9 +34,11 @@ async fn main() -> Result<(), String> {
     let handler = dptree::entry()
         .branch(Update::filter_message().endpoint(handle_message))
         .branch(Update::filter_callback_query().endpoint(callback_handler))
-        .branch(Update::filter_inline_query().endpoint(inline_query_handler));
+        .branch(Update::filter_poll_answer().endpoint(poll_answer_handler))
+        .branch(Update::filter_inline_query().endpoint(inline_query_handler))
+        .branch(Update::filter_chosen_inline_result().endpoint(chosen_inline_result_handler));
 
-    let users = clients::Clients::new(bot.clone(), Storage::new(db));
+    let users = clients::Clients::new(bot.clone(), db);
     let mut reminder = reminder::Reminder::new(users.clone());
     let reminder_task = tokio::spawn(async move {
         reminder.run().await;
@@ -58,16 +55,18 @@ async fn main() -> Result<(), String> {
     Ok(())
 }
 
-async fn inline_query_handler(bot: Bot, q: InlineQuery) -> ResponseResult<()> {
-    let choose_debian_version = InlineQueryResultArticle::new(
-        "0",
-        "Chose debian version",
-        InputMessageContent::Text(InputMessageContentText::new("Debian versions:")),
-    )
-    .reply_markup(make_keyboard());
+async fn inline_query_handler(clients: clients::Clients, query: InlineQuery) -> ResponseResult<()> {
+    log::info!("Got inline query: {:?}", query.id);
+    clients.handle_inline_query(query).await?;
+    Ok(())
+}
 
-    bot.answer_inline_query(q.id, vec![choose_debian_version.into()])
-        .await?;
+async fn chosen_inline_result_handler(
+    clients: clients::Clients,
+    result: ChosenInlineResult,
+) -> ResponseResult<()> {
+    log::info!("Got chosen inline result: {:?}", result.result_id);
+    clients.handle_chosen_inline_result(result).await?;
     Ok(())
 }
 
@@ -78,35 +77,47 @@ async fn callback_handler(
 ) -> ResponseResult<()> {
     log::info!("Got callback query: {:?}", query.id);
     let id = query.id.to_owned();
-    clients.handle_callback_query(query).await?;
-    bot.answer_callback_query(id).await?;
+    let text = clients.handle_callback_query(query).await?;
+    let mut answer = bot.answer_callback_query(id);
+    if let Some(text) = text {
+        answer = answer.text(text);
+    }
+    answer.await?;
+    Ok(())
+}
+
+async fn poll_answer_handler(clients: clients::Clients, answer: PollAnswer) -> ResponseResult<()> {
+    log::info!("Got poll answer: {:?}", answer.poll_id);
+    clients.handle_poll_answer(answer).await?;
     Ok(())
 }
 
 async fn handle_message(clients: clients::Clients, message: Message) -> ResponseResult<()> {
-    log::info!("Got text message: {}", message.text().unwrap());
+    log::info!("Got message: {:?}", message.text());
     clients.handle_message(message).await?;
     Ok(())
 }
 
-fn make_keyboard() -> InlineKeyboardMarkup {
-    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = vec![];
-
-    let debian_versions = [
-        "Buzz", "Rex", "Bo", "Hamm", "Slink", "Potato", "Woody", "Sarge", "Etch", "Lenny",
-        "Squeeze", "Wheezy", "Jessie", "Stretch", "Buster", "Bullseye",
-    ];
-
-    for versions in debian_versions.chunks(3) {
-        let row = versions
-            .iter()
-            .map(|&version| InlineKeyboardButton::callback(version.to_owned(), version.to_owned()))
-            .collect();
-
-        keyboard.push(row);
+// STORAGE=memory runs the bot without a database, everything is lost on restart
+async fn connect_storage() -> Arc<dyn Storage> {
+    if env::var("STORAGE").is_ok_and(|s| s == "memory") {
+        log::warn!("Using in-memory storage");
+        return Arc::new(MemoryStorage::new());
     }
 
-    InlineKeyboardMarkup::new(keyboard)
+    let db = prisma::PrismaClient::_builder()
+        .build()
+        .await
+        .expect("Failed to connect to database");
+    let db = PrismaStorage::new(db);
+    let normalized = db
+        .normalize_words()
+        .await
+        .expect("Failed to normalize words");
+    if normalized > 0 {
+        log::info!("Normalized {} words and translations", normalized);
+    }
+    Arc::new(db)
 }
 
 fn load_env() {
@@ -1,13 +1,40 @@
+pub mod queue;
+
 use tokio::{signal, sync::mpsc};
 
-use crate::{clients, state::events::Event};
+use crate::{
+    clients,
+    storage::{error::StorageResult, Storage, MAX_USERS_TO_REMIND},
+};
+
+use queue::RemindQueue;
 
-static EVERY: i64 = 10;
+// Waited before loading the queue again if the storage fails at startup
+static LOAD_RETRY: tokio::time::Duration = tokio::time::Duration::from_secs(10);
 
 pub struct Reminder {
     clients: clients::Clients,
 }
 
+/// Schedules the user when their earliest card is due and the interval since
+/// the last remind has passed. Users with nothing to remind leave the queue
+/// until their words or settings change.
+pub async fn reschedule(
+    db: &dyn Storage,
+    reminds: &RemindQueue,
+    chat_id: i64,
+) -> StorageResult<()> {
+    let Some(user) = db.get_user(chat_id).await? else {
+        reminds.unschedule(chat_id).await;
+        return Ok(());
+    };
+    match db.next_due_at(&user).await? {
+        Some(due_at) => reminds.schedule(chat_id, user.remind_at(due_at)).await,
+        None => reminds.unschedule(chat_id).await,
+    }
+    Ok(())
+}
+
 impl Reminder {
     pub fn new(clients: clients::Clients) -> Self {
         Self { clients }
@@ -29,7 +56,7 @@ async fn wait_shot_down() {
 }
 
 impl Reminder {
-    // start reminder - reminder every EVERY seconds and not block the thread
+    // start reminder - remind users when they are due and not block the thread
     pub async fn run(&mut self) {
         tokio::select! {
             _ = self.start() => { },
@@ -38,16 +65,82 @@ impl Reminder {
     }
 
     async fn start(&self) {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(EVERY as u64));
+        while let Err(err) = self.clients.load_reminds().await {
+            log::error!("Error loading remind times: {}", err);
+            tokio::time::sleep(LOAD_RETRY).await;
+        }
+        let reminds = self.clients.reminds();
         loop {
-            log::debug!("Tick");
-            interval.tick().await;
-            self.remind().await;
+            let due = reminds.wait_due(MAX_USERS_TO_REMIND as usize).await;
+            if due.backlog > 0 {
+                log::warn!(
+                    "Reminding {} users, {} more are due, the oldest has waited {}s, {} scheduled",
+                    due.chat_ids.len(),
+                    due.backlog,
+                    due.lag.num_seconds(),
+                    due.scheduled
+                );
+            } else {
+                log::debug!(
+                    "Reminding {} users, the oldest has waited {}s, {} scheduled",
+                    due.chat_ids.len(),
+                    due.lag.num_seconds(),
+                    due.scheduled
+                );
+            }
+            self.clients.remind(due.chat_ids).await;
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        scheduler::Progress,
+        storage::{memory::MemoryStorage, Direction},
+    };
+
+    use super::*;
+
+    static CHAT_ID: i64 = 1;
+
+    async fn is_due(reminds: &RemindQueue) -> bool {
+        tokio::time::timeout(Duration::from_millis(100), reminds.wait_due(10))
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn users_wait_for_their_next_card() {
+        let db = MemoryStorage::new();
+        let reminds = RemindQueue::default();
+        db.ensure_user(CHAT_ID).await.unwrap();
+        reschedule(&db, &reminds, CHAT_ID).await.unwrap();
+        assert!(!is_due(&reminds).await);
+
+        let hund = db
+            .new_word(CHAT_ID, "Hund", &"dog".into(), None)
+            .await
+            .unwrap();
+        reschedule(&db, &reminds, CHAT_ID).await.unwrap();
+        assert!(!is_due(&reminds).await);
+
+        db.update_word_remind(
+            &hund.id,
+            Direction::Forward,
+            &Progress::default(),
+            hund.status,
+        )
+        .await
+        .unwrap();
+        reschedule(&db, &reminds, CHAT_ID).await.unwrap();
+        assert!(is_due(&reminds).await);
 
-    pub async fn remind(&self) {
-        log::debug!("Reminding");
-        self.clients.handle_event(Event::Remind).await;
+        // the interval since the last remind has to pass too
+        db.update_next_remind_user(CHAT_ID, 3600).await.unwrap();
+        reschedule(&db, &reminds, CHAT_ID).await.unwrap();
+        assert!(!is_due(&reminds).await);
     }
 }
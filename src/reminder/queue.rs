@@ -0,0 +1,269 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    sync::Arc,
+};
+
+use chrono::{DateTime, FixedOffset};
+use tokio::sync::Notify;
+
+use crate::{common::AsyncMutex, storage::now};
+
+// The heap is rebuilt when outdated entries outnumber the users this much
+static COMPACT_RATIO: usize = 2;
+
+#[derive(Debug, Default)]
+struct Data {
+    /// When each user is due
+    due: HashMap<i64, DateTime<FixedOffset>>,
+    /// Due times from the earliest, entries which don't match `due` are outdated
+    heap: BinaryHeap<Reverse<(DateTime<FixedOffset>, i64)>>,
+}
+
+impl Data {
+    fn is_current(&self, at: DateTime<FixedOffset>, chat_id: i64) -> bool {
+        self.due.get(&chat_id) == Some(&at)
+    }
+
+    fn next_at(&mut self) -> Option<DateTime<FixedOffset>> {
+        while let Some(Reverse((at, chat_id))) = self.heap.peek().copied() {
+            if self.is_current(at, chat_id) {
+                return Some(at);
+            }
+            self.heap.pop();
+        }
+        None
+    }
+
+    fn pop_due(&mut self, now: DateTime<FixedOffset>, limit: usize) -> Option<DueUsers> {
+        let mut chat_ids = vec![];
+        let mut earliest = None;
+        while chat_ids.len() < limit {
+            match self.next_at() {
+                Some(at) if at <= now => {}
+                _ => break,
+            }
+            if let Some(Reverse((at, chat_id))) = self.heap.pop() {
+                self.due.remove(&chat_id);
+                chat_ids.push(chat_id);
+                earliest.get_or_insert(at);
+            }
+        }
+        let earliest = earliest?;
+
+        // counted only when the reminder falls behind
+        let backlog = match self.next_at() {
+            Some(at) if at <= now => self.due.values().filter(|at| **at <= now).count(),
+            _ => 0,
+        };
+        Some(DueUsers {
+            chat_ids,
+            backlog,
+            scheduled: self.due.len(),
+            lag: now - earliest,
+        })
+    }
+
+    fn compact(&mut self) {
+        if self.heap.len() > COMPACT_RATIO * self.due.len() {
+            self.heap = self
+                .due
+                .iter()
+                .map(|(chat_id, at)| Reverse((*at, *chat_id)))
+                .collect();
+        }
+    }
+}
+
+/// Users taken from the queue at once with the state of the queue
+#[derive(Debug, Clone)]
+pub struct DueUsers {
+    /// From the longest waiting
+    pub chat_ids: Vec<i64>,
+    /// Users who are due too but left for the next rounds
+    pub backlog: usize,
+    /// Users left in the queue
+    pub scheduled: usize,
+    /// How long the longest waiting user has been due
+    pub lag: chrono::Duration,
+}
+
+/// Users by the time they should be reminded at, see `reminder::reschedule`.
+/// Due cards are read from the storage only when a user is taken from the queue.
+#[derive(Debug, Clone, Default)]
+pub struct RemindQueue(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    data: AsyncMutex<Data>,
+    /// Wakes the waiting reminder up when an earlier time is scheduled
+    changed: Notify,
+}
+
+impl RemindQueue {
+    /// Sets the time of the user replacing the previous one
+    pub async fn schedule(&self, chat_id: i64, at: DateTime<FixedOffset>) {
+        let mut data = self.0.data.lock().await;
+        let earliest = data.next_at();
+        data.due.insert(chat_id, at);
+        data.heap.push(Reverse((at, chat_id)));
+        data.compact();
+        if earliest.is_none_or(|earliest| at < earliest) {
+            self.0.changed.notify_one();
+        }
+    }
+
+    /// Keeps the time of the user if it's already scheduled
+    pub async fn schedule_if_absent(&self, chat_id: i64, at: DateTime<FixedOffset>) {
+        if !self.0.data.lock().await.due.contains_key(&chat_id) {
+            self.schedule(chat_id, at).await;
+        }
+    }
+
+    /// Removes the user until they are scheduled again
+    pub async fn unschedule(&self, chat_id: i64) {
+        let mut data = self.0.data.lock().await;
+        data.due.remove(&chat_id);
+        data.compact();
+    }
+
+    /// Waits until some users are due and takes at most `limit` of them,
+    /// the ones waiting the longest first. Taken users are out of the queue
+    /// until they are scheduled again.
+    pub async fn wait_due(&self, limit: usize) -> DueUsers {
+        loop {
+            let changed = self.0.changed.notified();
+            let next_at = {
+                let mut data = self.0.data.lock().await;
+                if let Some(users) = data.pop_due(now(), limit) {
+                    return users;
+                }
+                data.next_at()
+            };
+            match next_at {
+                Some(at) => {
+                    let wait = (at - now()).to_std().unwrap_or_default();
+                    tokio::select! {
+                        _ = tokio::time::sleep(wait) => {},
+                        _ = changed => {},
+                    }
+                }
+                None => changed.await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    fn ago(seconds: i64) -> DateTime<FixedOffset> {
+        now() - Duration::seconds(seconds)
+    }
+
+    async fn take(queue: &RemindQueue, limit: usize) -> DueUsers {
+        let wait = queue.wait_due(limit);
+        tokio::time::timeout(std::time::Duration::from_secs(1), wait)
+            .await
+            .expect("no users are due")
+    }
+
+    #[tokio::test]
+    async fn longest_waiting_first() {
+        let queue = RemindQueue::default();
+        queue.schedule(1, ago(10)).await;
+        queue.schedule(2, ago(30)).await;
+        queue.schedule(3, ago(20)).await;
+        queue.schedule(4, ago(-3600)).await;
+
+        let due = take(&queue, 10).await;
+        assert_eq!(due.chat_ids, [2, 3, 1]);
+        assert_eq!(due.backlog, 0);
+        assert_eq!(due.scheduled, 1);
+        assert!(due.lag >= Duration::seconds(30));
+    }
+
+    #[tokio::test]
+    async fn limit_leaves_backlog() {
+        let queue = RemindQueue::default();
+        for chat_id in 1..=5 {
+            queue.schedule(chat_id, ago(100 - chat_id)).await;
+        }
+        let due = take(&queue, 2).await;
+        assert_eq!(due.chat_ids, [1, 2]);
+        assert_eq!(due.backlog, 3);
+        assert_eq!(due.scheduled, 3);
+        let due = take(&queue, 10).await;
+        assert_eq!(due.chat_ids, [3, 4, 5]);
+        assert_eq!(due.scheduled, 0);
+    }
+
+    #[tokio::test]
+    async fn schedule_replaces_time() {
+        let queue = RemindQueue::default();
+        queue.schedule(1, ago(10)).await;
+        queue.schedule(2, ago(5)).await;
+        queue.schedule(1, ago(-3600)).await;
+        queue.schedule(3, ago(20)).await;
+        queue.schedule(3, ago(1)).await;
+
+        let due = take(&queue, 10).await;
+        assert_eq!(due.chat_ids, [2, 3]);
+        assert_eq!(due.scheduled, 1);
+    }
+
+    #[tokio::test]
+    async fn schedule_if_absent_keeps_time() {
+        let queue = RemindQueue::default();
+        queue.schedule(1, ago(-3600)).await;
+        queue.schedule_if_absent(1, ago(10)).await;
+        queue.schedule_if_absent(2, ago(10)).await;
+
+        let due = take(&queue, 10).await;
+        assert_eq!(due.chat_ids, [2]);
+        // taken users are absent until they are scheduled again
+        queue.schedule_if_absent(2, ago(5)).await;
+        assert_eq!(take(&queue, 10).await.chat_ids, [2]);
+    }
+
+    #[tokio::test]
+    async fn unscheduled_users_are_skipped() {
+        let queue = RemindQueue::default();
+        queue.schedule(1, ago(10)).await;
+        queue.schedule(2, ago(5)).await;
+        queue.unschedule(1).await;
+        queue.unschedule(3).await;
+
+        let due = take(&queue, 10).await;
+        assert_eq!(due.chat_ids, [2]);
+        assert_eq!(due.scheduled, 0);
+    }
+
+    #[tokio::test]
+    async fn outdated_entries_are_compacted() {
+        let queue = RemindQueue::default();
+        for seconds in 0..100 {
+            queue.schedule(1, ago(seconds)).await;
+            queue.schedule(2, ago(seconds)).await;
+        }
+        let data = queue.0.data.lock().await;
+        assert_eq!(data.due.len(), 2);
+        assert!(data.heap.len() <= COMPACT_RATIO * data.due.len() + 1);
+    }
+
+    #[tokio::test]
+    async fn earlier_time_wakes_waiting_reminder() {
+        let queue = RemindQueue::default();
+        queue.schedule(1, ago(-3600)).await;
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { take(&queue, 10).await }
+        });
+        tokio::task::yield_now().await;
+        queue.schedule(2, now()).await;
+        assert_eq!(waiting.await.unwrap().chat_ids, [2]);
+    }
+}
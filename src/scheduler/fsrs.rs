@@ -0,0 +1,173 @@
+use crate::common::config::RELEARN_INTERVAL;
+
+use super::{clamp_interval, Grade, Progress, Scheduler};
+
+/// Free Spaced Repetition Scheduler (FSRS v4)
+#[derive(Debug, Clone, Copy)]
+pub struct Fsrs {
+    weights: [f64; 17],
+    // Probability to recall the word at the moment of the next review
+    desired_retention: f64,
+}
+
+impl Fsrs {
+    pub const DEFAULT: Fsrs = Fsrs {
+        weights: [
+            0.4, 0.6, 2.4, 5.8, 4.93, 0.94, 0.86, 0.01, 1.49, 0.14, 0.94, 2.18, 0.05, 0.34, 1.26,
+            0.29, 2.61,
+        ],
+        desired_retention: 0.9,
+    };
+
+    fn rating(grade: Grade) -> f64 {
+        match grade {
+            Grade::Again => 1.0,
+            Grade::Hard => 2.0,
+            Grade::Good => 3.0,
+            Grade::Easy => 4.0,
+        }
+    }
+
+    fn initial_stability(&self, grade: Grade) -> f64 {
+        self.weights[Fsrs::rating(grade) as usize - 1]
+    }
+
+    fn initial_difficulty(&self, grade: Grade) -> f64 {
+        let w = &self.weights;
+        num::clamp(w[4] - w[5] * (Fsrs::rating(grade) - 3.0), 1.0, 10.0)
+    }
+
+    fn retrievability(&self, elapsed: f64, stability: f64) -> f64 {
+        (1.0 + elapsed / (9.0 * stability)).powi(-1)
+    }
+
+    fn next_difficulty(&self, difficulty: f64, grade: Grade) -> f64 {
+        let w = &self.weights;
+        let difficulty = difficulty - w[6] * (Fsrs::rating(grade) - 3.0);
+        // mean reversion to the difficulty of a "good" first answer
+        let difficulty = w[7] * self.initial_difficulty(Grade::Good) + (1.0 - w[7]) * difficulty;
+        num::clamp(difficulty, 1.0, 10.0)
+    }
+
+    fn next_stability(&self, difficulty: f64, stability: f64, r: f64, grade: Grade) -> f64 {
+        let w = &self.weights;
+        if !grade.is_success() {
+            return w[11]
+                * difficulty.powf(-w[12])
+                * ((stability + 1.0).powf(w[13]) - 1.0)
+                * (w[14] * (1.0 - r)).exp();
+        }
+
+        let hard_penalty = if grade == Grade::Hard { w[15] } else { 1.0 };
+        let easy_bonus = if grade == Grade::Easy { w[16] } else { 1.0 };
+        stability
+            * (1.0
+                + w[8].exp()
+                    * (11.0 - difficulty)
+                    * stability.powf(-w[9])
+                    * ((w[10] * (1.0 - r)).exp() - 1.0)
+                    * hard_penalty
+                    * easy_bonus)
+    }
+
+    fn interval(&self, stability: f64) -> f64 {
+        clamp_interval(9.0 * stability * (1.0 / self.desired_retention - 1.0))
+    }
+}
+
+impl Scheduler for Fsrs {
+    fn name(&self) -> &'static str {
+        "fsrs"
+    }
+
+    fn text(&self) -> &'static str {
+        "FSRS"
+    }
+
+    fn next(&self, progress: &Progress, grade: Grade, elapsed: f64) -> Progress {
+        let is_new = progress.stability <= 0.0 || progress.difficulty <= 0.0;
+        let (stability, difficulty) = if is_new {
+            (
+                self.initial_stability(grade),
+                self.initial_difficulty(grade),
+            )
+        } else {
+            let r = self.retrievability(elapsed, progress.stability);
+            (
+                self.next_stability(progress.difficulty, progress.stability, r, grade),
+                self.next_difficulty(progress.difficulty, grade),
+            )
+        };
+
+        let (level, interval) = if grade.is_success() {
+            (progress.level + 1, self.interval(stability))
+        } else {
+            (0, RELEARN_INTERVAL)
+        };
+
+        Progress {
+            level,
+            interval,
+            stability,
+            difficulty,
+            ..*progress
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FSRS: Fsrs = Fsrs::DEFAULT;
+
+    #[test]
+    fn first_answer_sets_initial_state() {
+        let good = FSRS.next(&Progress::default(), Grade::Good, 0.0);
+        assert_eq!(good.level, 1);
+        assert_eq!(good.stability, 2.4);
+        assert_eq!(good.difficulty, 4.93);
+        // the interval equals the stability at 90% retention
+        assert!((good.interval - good.stability).abs() < 1e-9);
+
+        let again = FSRS.next(&Progress::default(), Grade::Again, 0.0);
+        assert_eq!(again.level, 0);
+        assert_eq!(again.interval, RELEARN_INTERVAL);
+        assert!(again.difficulty > good.difficulty);
+        assert!(again.stability < good.stability);
+    }
+
+    #[test]
+    fn better_grades_give_longer_intervals() {
+        let progress = FSRS.next(&Progress::default(), Grade::Good, 0.0);
+        let [hard, good, easy] = [Grade::Hard, Grade::Good, Grade::Easy]
+            .map(|grade| FSRS.next(&progress, grade, progress.interval));
+        assert!(progress.interval < hard.interval);
+        assert!(hard.interval < good.interval);
+        assert!(good.interval < easy.interval);
+        assert!(easy.difficulty < good.difficulty && good.difficulty < hard.difficulty);
+        assert_eq!(good.level, 2);
+    }
+
+    #[test]
+    fn forgetting_lowers_stability() {
+        let progress = [Grade::Good; 4]
+            .iter()
+            .fold(Progress::default(), |p, grade| {
+                FSRS.next(&p, *grade, p.interval)
+            });
+        let forgotten = FSRS.next(&progress, Grade::Again, progress.interval);
+        assert_eq!(forgotten.level, 0);
+        assert_eq!(forgotten.interval, RELEARN_INTERVAL);
+        assert!(forgotten.stability < progress.stability);
+        assert!(forgotten.difficulty > progress.difficulty);
+    }
+
+    #[test]
+    fn late_review_grows_stability_more() {
+        let progress = FSRS.next(&Progress::default(), Grade::Good, 0.0);
+        let on_time = FSRS.next(&progress, Grade::Good, progress.interval);
+        let late = FSRS.next(&progress, Grade::Good, progress.interval * 3.0);
+        assert!(late.stability > on_time.stability);
+    }
+}
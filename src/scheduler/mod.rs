@@ -0,0 +1,97 @@
+pub mod fsrs;
+pub mod order;
+pub mod sm2;
+
+use std::fmt::Debug;
+
+use chrono::{DateTime, FixedOffset, Utc};
+
+use crate::common::config::{DAY, DEFAULT_SCHEDULER, MAX_INTERVAL, RELEARN_INTERVAL};
+
+static SM2: sm2::Sm2 = sm2::Sm2;
+static FSRS: fsrs::Fsrs = fsrs::Fsrs::DEFAULT;
+
+/// Schedulers a user can choose from
+pub static ALL: [&dyn Scheduler; 2] = [&SM2, &FSRS];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    pub fn is_success(&self) -> bool {
+        *self != Grade::Again
+    }
+}
+
+/// Scheduling state of a single word. Intervals are measured in days,
+/// see `config::DAY`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    /// Amount of successful reviews in a row
+    pub level: i32,
+    pub ease_factor: f64,
+    pub interval: f64,
+    pub stability: f64,
+    pub difficulty: f64,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            level: 0,
+            ease_factor: sm2::INITIAL_EASE_FACTOR,
+            interval: 0.0,
+            stability: 0.0,
+            difficulty: 0.0,
+        }
+    }
+}
+
+impl Progress {
+    pub fn next_remind_at(&self) -> DateTime<FixedOffset> {
+        let seconds = (self.interval * DAY as f64).round() as i64;
+        let next_remind = Utc::now() + chrono::Duration::seconds(seconds);
+        next_remind.with_timezone(&FixedOffset::east_opt(0).unwrap())
+    }
+}
+
+pub trait Scheduler: Send + Sync + Debug {
+    fn name(&self) -> &'static str;
+
+    /// Name shown to the user
+    fn text(&self) -> &'static str;
+
+    /// Calculates the progress after answering with `grade`,
+    /// `elapsed` is amount of days since the previous review.
+    fn next(&self, progress: &Progress, grade: Grade, elapsed: f64) -> Progress;
+}
+
+pub fn get(name: &str) -> &'static dyn Scheduler {
+    match name {
+        "sm2" => &SM2,
+        "fsrs" => &FSRS,
+        _ => {
+            log::warn!(
+                "Unknown scheduler `{}`, fallback to `{}`",
+                name,
+                DEFAULT_SCHEDULER
+            );
+            get(DEFAULT_SCHEDULER)
+        }
+    }
+}
+
+/// Days passed since `reviewed_at`
+pub fn elapsed_days(reviewed_at: DateTime<FixedOffset>) -> f64 {
+    let seconds = (Utc::now() - reviewed_at.with_timezone(&Utc)).num_seconds();
+    f64::max(seconds as f64, 0.0) / DAY as f64
+}
+
+fn clamp_interval(interval: f64) -> f64 {
+    num::clamp(interval, RELEARN_INTERVAL, MAX_INTERVAL)
+}
@@ -0,0 +1,210 @@
+use chrono::{DateTime, FixedOffset};
+use rand::Rng;
+
+use crate::{
+    common::config::{DAY, RELEARN_INTERVAL},
+    key_value_enum,
+    storage::{error::StorageResult, now, DueCard, LiteUser, Storage},
+};
+
+key_value_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum WordOrder {
+        // the word which has waited the longest
+        MostOverdue { text: "most overdue first", key: "most_overdue" },
+        // the word which is remembered the worst
+        LowestLevel { text: "lowest level first", key: "lowest_level" },
+        // words overdue by more of their interval are picked more often
+        WeightedRandom { text: "random, overdue words more often", key: "weighted_random" },
+        // new (or forgotten) words and the ones being reviewed in turn
+        Interleaved { text: "new and review words in turn", key: "interleaved" },
+    }
+}
+
+impl WordOrder {
+    pub const ALL: [WordOrder; 4] = [
+        WordOrder::MostOverdue,
+        WordOrder::LowestLevel,
+        WordOrder::WeightedRandom,
+        WordOrder::Interleaved,
+    ];
+
+    /// Due cards in the order they should be reviewed,
+    /// `new_first` is used by `Interleaved` only
+    pub fn sort(
+        &self,
+        mut cards: Vec<DueCard>,
+        now: DateTime<FixedOffset>,
+        new_first: bool,
+    ) -> Vec<DueCard> {
+        cards.sort_by_key(|c| c.card.next_remind_at);
+        match self {
+            WordOrder::MostOverdue => cards,
+            WordOrder::LowestLevel => {
+                // stable, the most overdue first among the same level
+                cards.sort_by_key(|c| c.card.progress.level);
+                cards
+            }
+            WordOrder::WeightedRandom => {
+                // weighted shuffle, the biggest `u ^ (1 / weight)` goes first
+                let mut rng = rand::thread_rng();
+                let mut keyed: Vec<(f64, DueCard)> = cards
+                    .into_iter()
+                    .map(|c| {
+                        let key = rng.gen::<f64>().powf(1.0 / overdue_ratio(&c, now));
+                        (key, c)
+                    })
+                    .collect();
+                keyed.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+                keyed.into_iter().map(|(_, c)| c).collect()
+            }
+            WordOrder::Interleaved => {
+                let (new, review): (Vec<DueCard>, Vec<DueCard>) =
+                    cards.into_iter().partition(is_new);
+                let (mut first, mut second) = if new_first {
+                    (new.into_iter(), review.into_iter())
+                } else {
+                    (review.into_iter(), new.into_iter())
+                };
+                let mut sorted = vec![];
+                loop {
+                    match (first.next(), second.next()) {
+                        (None, None) => return sorted,
+                        (a, b) => sorted.extend(a.into_iter().chain(b)),
+                    }
+                }
+            }
+        }
+    }
+}
+
+// words which are not remembered yet: just added or forgotten
+fn is_new(card: &DueCard) -> bool {
+    card.card.progress.level == 0
+}
+
+// how many of its intervals the card is overdue by, at least 1
+fn overdue_ratio(card: &DueCard, now: DateTime<FixedOffset>) -> f64 {
+    let overdue = (now - card.card.next_remind_at).num_seconds().max(0) as f64 / DAY as f64;
+    let interval = card.card.progress.interval.max(RELEARN_INTERVAL);
+    1.0 + overdue / interval
+}
+
+// a new word goes next unless the previous review was of a new word
+async fn new_first(db: &dyn Storage, chat_id: i64) -> StorageResult<bool> {
+    let last = db.get_user_reviews(chat_id, 0, 1).await?;
+    Ok(last.first().is_none_or(|review| review.level_before > 0))
+}
+
+/// Due cards of the user in the order the user has chosen
+pub async fn sort_for_user(
+    db: &dyn Storage,
+    user: &LiteUser,
+    cards: Vec<DueCard>,
+) -> StorageResult<Vec<DueCard>> {
+    let new_first =
+        user.word_order == WordOrder::Interleaved && new_first(db, user.chat_id).await?;
+    Ok(user.word_order.sort(cards, now(), new_first))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use crate::{
+        scheduler::Progress,
+        storage::{Card, Direction, Word, WordStatus},
+    };
+
+    use super::*;
+
+    // a card due `overdue` days ago
+    fn card(word: &str, level: i32, overdue: f64) -> DueCard {
+        let now = now();
+        let next_remind_at = now - Duration::seconds((overdue * DAY as f64) as i64);
+        let card = Card {
+            direction: Direction::Forward,
+            progress: Progress {
+                level,
+                interval: 1.0,
+                ..Progress::default()
+            },
+            next_remind_at,
+            reminded_at: next_remind_at - Duration::seconds(DAY),
+        };
+        DueCard {
+            word: Word {
+                id: word.to_owned(),
+                chat_id: 1,
+                created_at: now,
+                word: word.to_owned(),
+                translations: "translation".into(),
+                cards: vec![card.clone()],
+                status: WordStatus::Active,
+                deck_id: None,
+                tags: vec![],
+            },
+            card,
+        }
+    }
+
+    fn words(cards: &[DueCard]) -> Vec<&str> {
+        cards.iter().map(|c| c.word.word.as_str()).collect()
+    }
+
+    fn cards() -> Vec<DueCard> {
+        vec![
+            card("a", 2, 1.0),
+            card("b", 0, 2.0),
+            card("c", 3, 5.0),
+            card("d", 0, 0.5),
+            card("e", 2, 3.0),
+        ]
+    }
+
+    #[test]
+    fn most_overdue_first() {
+        let sorted = WordOrder::MostOverdue.sort(cards(), now(), false);
+        assert_eq!(words(&sorted), ["c", "e", "b", "a", "d"]);
+    }
+
+    #[test]
+    fn lowest_level_first() {
+        let sorted = WordOrder::LowestLevel.sort(cards(), now(), false);
+        assert_eq!(words(&sorted), ["b", "d", "e", "a", "c"]);
+    }
+
+    #[test]
+    fn interleaved_alternates_new_and_review() {
+        let sorted = WordOrder::Interleaved.sort(cards(), now(), true);
+        assert_eq!(words(&sorted), ["b", "c", "d", "e", "a"]);
+        let sorted = WordOrder::Interleaved.sort(cards(), now(), false);
+        assert_eq!(words(&sorted), ["c", "b", "e", "d", "a"]);
+        assert!(WordOrder::Interleaved.sort(vec![], now(), true).is_empty());
+    }
+
+    #[test]
+    fn weighted_random_prefers_overdue() {
+        let cards = vec![card("due", 1, 0.0), card("overdue", 1, 100.0)];
+        let mut overdue_first = 0;
+        for _ in 0..200 {
+            let sorted = WordOrder::WeightedRandom.sort(cards.clone(), now(), false);
+            let mut sorted_words = words(&sorted);
+            if sorted_words[0] == "overdue" {
+                overdue_first += 1;
+            }
+            sorted_words.sort();
+            assert_eq!(sorted_words, ["due", "overdue"]);
+        }
+        // it goes first with the probability of 101 / 102
+        assert!(overdue_first > 150, "{} times first", overdue_first);
+    }
+
+    #[test]
+    fn overdue_ratio_is_at_least_one() {
+        let now = now();
+        assert_eq!(overdue_ratio(&card("a", 1, 0.0), now), 1.0);
+        assert!((overdue_ratio(&card("a", 1, 2.0), now) - 3.0).abs() < 0.01);
+        assert_eq!(overdue_ratio(&card("a", 1, -1.0), now), 1.0);
+    }
+}
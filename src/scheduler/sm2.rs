@@ -0,0 +1,121 @@
+use crate::common::config::RELEARN_INTERVAL;
+
+use super::{clamp_interval, Grade, Progress, Scheduler};
+
+pub static INITIAL_EASE_FACTOR: f64 = 2.5;
+static MIN_EASE_FACTOR: f64 = 1.3;
+
+/// Classic SuperMemo 2 algorithm
+#[derive(Debug, Clone, Copy)]
+pub struct Sm2;
+
+impl Sm2 {
+    // SM-2 grades answers from 0 to 5, everything below 3 is a failure
+    fn quality(grade: Grade) -> f64 {
+        match grade {
+            Grade::Again => 1.0,
+            Grade::Hard => 3.0,
+            Grade::Good => 4.0,
+            Grade::Easy => 5.0,
+        }
+    }
+}
+
+impl Scheduler for Sm2 {
+    fn name(&self) -> &'static str {
+        "sm2"
+    }
+
+    fn text(&self) -> &'static str {
+        "SM-2"
+    }
+
+    fn next(&self, progress: &Progress, grade: Grade, _: f64) -> Progress {
+        let q = Sm2::quality(grade);
+        let ease_factor = progress.ease_factor + (0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02));
+        let ease_factor = f64::max(ease_factor, MIN_EASE_FACTOR);
+
+        if !grade.is_success() {
+            return Progress {
+                level: 0,
+                ease_factor,
+                interval: RELEARN_INTERVAL,
+                stability: RELEARN_INTERVAL,
+                ..*progress
+            };
+        }
+
+        let interval = match progress.level {
+            0 => 1.0,
+            1 => 6.0,
+            _ => progress.interval * ease_factor,
+        };
+        let interval = clamp_interval(interval);
+
+        Progress {
+            level: progress.level + 1,
+            ease_factor,
+            interval,
+            stability: interval,
+            ..*progress
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::config::MAX_INTERVAL;
+
+    use super::*;
+
+    fn review(progress: &Progress, grades: &[Grade]) -> Progress {
+        grades.iter().fold(*progress, |progress, grade| {
+            Sm2.next(&progress, *grade, 0.0)
+        })
+    }
+
+    #[test]
+    fn intervals_grow_after_correct_answers() {
+        let first = review(&Progress::default(), &[Grade::Good]);
+        assert_eq!(first.level, 1);
+        assert_eq!(first.interval, 1.0);
+        let second = review(&first, &[Grade::Good]);
+        assert_eq!(second.level, 2);
+        assert_eq!(second.interval, 6.0);
+        let third = review(&second, &[Grade::Good]);
+        assert_eq!(third.level, 3);
+        assert_eq!(third.interval, 6.0 * INITIAL_EASE_FACTOR);
+        assert_eq!(third.stability, third.interval);
+    }
+
+    #[test]
+    fn ease_factor_follows_grades() {
+        let progress = Progress::default();
+        assert_eq!(review(&progress, &[Grade::Good]).ease_factor, 2.5);
+        assert!((review(&progress, &[Grade::Easy]).ease_factor - 2.6).abs() < 1e-9);
+        assert!((review(&progress, &[Grade::Hard]).ease_factor - 2.36).abs() < 1e-9);
+        let forgotten = review(&progress, &[Grade::Again; 5]);
+        assert_eq!(forgotten.ease_factor, MIN_EASE_FACTOR);
+    }
+
+    #[test]
+    fn wrong_answer_relearns() {
+        let progress = review(&Progress::default(), &[Grade::Good; 4]);
+        let forgotten = review(&progress, &[Grade::Again]);
+        assert_eq!(forgotten.level, 0);
+        assert_eq!(forgotten.interval, RELEARN_INTERVAL);
+        assert!(forgotten.ease_factor < progress.ease_factor);
+        // learning starts over from a day
+        assert_eq!(review(&forgotten, &[Grade::Good]).interval, 1.0);
+    }
+
+    #[test]
+    fn interval_is_limited() {
+        let progress = Progress {
+            level: 10,
+            interval: MAX_INTERVAL,
+            ..Progress::default()
+        };
+        assert_eq!(review(&progress, &[Grade::Easy]).interval, MAX_INTERVAL);
+    }
+}
@@ -1,10 +1,20 @@
 pub mod add_translation;
 pub mod add_word;
+pub mod bulk_add;
+pub mod deck_input;
+pub mod decks;
+pub mod edit_word;
 pub mod error;
 pub mod events;
 pub mod idle;
+pub mod import_file;
+pub mod inline_search;
+pub mod quiz;
 pub mod remind;
 pub mod remove_words;
+pub mod session;
+pub mod settings;
+pub mod timezone_input;
 pub mod word_list;
 
 use async_trait::async_trait;
@@ -13,14 +23,22 @@ use tokio::{sync::MutexGuard, task::JoinHandle};
 
 use teloxide::{
     requests::Requester,
-    types::{CallbackQuery, ChatId, InlineQuery, Message},
+    types::{CallbackQuery, ChatId, InlineQuery, InputFile, Message},
     utils::command::BotCommands,
 };
 
 use crate::{
-    common::{AsyncMutex, Command},
+    challenges::Challenges,
+    common::{
+        export::{Export, ExportFormat},
+        local_time::{self, QuietHours},
+        translation::Strictness,
+        AsyncMutex, Command,
+    },
     keyboard,
-    storage::{error::StorageError, Storage},
+    reminder::{self, queue::RemindQueue},
+    scheduler::order::WordOrder,
+    storage::{error::StorageError, Directions, ReviewMode, Storage, WordStatus},
 };
 
 use self::{
@@ -29,6 +47,8 @@ use self::{
 };
 
 const DEFAULT_STATE_TIMEOUT: u64 = 60; // seconds
+const QUIET_HOURS_OFF: &str = "off";
+const DEFAULT_DIRECTIONS: &str = "default";
 
 #[derive(Debug, Clone)]
 pub struct FSM {
@@ -73,6 +93,80 @@ impl FSM {
                 self.context.bot.send_message(msg.chat.id, response).await?;
             }
 
+            Command::Activate(word) => {
+                self.set_word_status(msg, &word, WordStatus::Active).await?;
+            }
+            Command::Suspend(word) => {
+                self.set_word_status(msg, &word, WordStatus::Suspended)
+                    .await?;
+            }
+            Command::Refresher => {
+                let user = self.context.db.ensure_user(msg.chat.id.0).await?;
+                let enabled = !user.refresh_mastered;
+                self.context
+                    .db
+                    .set_refresh_mastered(user.chat_id, enabled)
+                    .await?;
+                self.context.reschedule().await?;
+                let response = if enabled {
+                    "Mastered words will be reminded once in a few months"
+                } else {
+                    "Mastered words won't be reminded anymore"
+                };
+                self.context.bot.send_message(msg.chat.id, response).await?;
+            }
+            Command::Mode => {
+                let user = self.context.db.ensure_user(msg.chat.id.0).await?;
+                let mode = ReviewMode::ALL
+                    .into_iter()
+                    .cycle()
+                    .skip_while(|mode| *mode != user.review_mode)
+                    .nth(1)
+                    .unwrap_or(ReviewMode::Typing);
+                self.context.db.set_review_mode(user.chat_id, mode).await?;
+                let response = match mode {
+                    ReviewMode::Typing => "Now you type translations of reminded words",
+                    ReviewMode::SelfGraded => {
+                        "Now you reveal translations of reminded words and grade yourself"
+                    }
+                    ReviewMode::Quiz => "Now you choose translations of reminded words",
+                    ReviewMode::Poll => "Now you answer quiz polls about reminded words",
+                };
+                self.context.bot.send_message(msg.chat.id, response).await?;
+            }
+
+            Command::Edit(word) => {
+                let current_state = self.state.lock().await;
+                let new_state = edit_word::EditWord::start(&self.context, &word).await;
+                self.handle_new_state(new_state, current_state).await;
+            }
+            Command::Review(count) => {
+                self.start_session(msg, &count).await?;
+            }
+            Command::Strictness(strictness) => {
+                self.set_strictness(msg, &strictness).await?;
+            }
+            Command::Directions(args) => {
+                self.set_directions(msg, &args).await?;
+            }
+            Command::Order(order) => {
+                self.set_word_order(msg, &order).await?;
+            }
+            Command::Timezone(timezone) => {
+                self.set_timezone(msg, &timezone).await?;
+            }
+            Command::Quiet(hours) => {
+                self.set_quiet_hours(msg, &hours).await?;
+            }
+            Command::Settings => {
+                let current_state = self.state.lock().await;
+                let new_state = settings::Settings::start(&self.context).await;
+                self.handle_new_state(new_state, current_state).await;
+            }
+            Command::Export(format) => {
+                self.export(msg, &format).await?;
+            }
+
             // hard reset to idle state
             Command::Start => {
                 let current_state = self.state.lock().await;
@@ -87,6 +181,238 @@ impl FSM {
         Ok(())
     }
 
+    async fn set_strictness(&self, msg: &Message, strictness: &str) -> StateResult<()> {
+        let user = self.context.db.ensure_user(msg.chat.id.0).await?;
+        let response = match Strictness::from_key(strictness.trim()) {
+            Ok(strictness) => {
+                self.context
+                    .db
+                    .set_strictness(user.chat_id, strictness)
+                    .await?;
+                format!("Answers are checked {} now", strictness.text())
+            }
+            Err(_) => {
+                let options: Vec<&str> = Strictness::ALL.iter().map(|s| s.key()).collect();
+                format!(
+                    "Answers are checked {}. Write one of {} after the command",
+                    user.strictness.text(),
+                    options.join(", ")
+                )
+            }
+        };
+        self.context.bot.send_message(msg.chat.id, response).await?;
+        Ok(())
+    }
+
+    async fn set_word_order(&self, msg: &Message, order: &str) -> StateResult<()> {
+        let user = self.context.db.ensure_user(msg.chat.id.0).await?;
+        let response = match WordOrder::from_key(order.trim()) {
+            Ok(order) => {
+                self.context.db.set_word_order(user.chat_id, order).await?;
+                format!("Due words are reminded {} now", order.text())
+            }
+            Err(_) => {
+                let options: Vec<&str> = WordOrder::ALL.iter().map(|o| o.key()).collect();
+                format!(
+                    "Due words are reminded {}. Write one of {} after the command",
+                    user.word_order.text(),
+                    options.join(", ")
+                )
+            }
+        };
+        self.context.bot.send_message(msg.chat.id, response).await?;
+        Ok(())
+    }
+
+    // without a name the timezone is asked with a button to share a location
+    async fn set_timezone(&self, msg: &Message, timezone: &str) -> StateResult<()> {
+        if timezone.trim().is_empty() {
+            let current_state = self.state.lock().await;
+            let new_state = timezone_input::TimezoneInput::new();
+            self.handle_new_state(Ok(Box::new(new_state)), current_state)
+                .await;
+            return Ok(());
+        }
+        match local_time::parse_timezone(timezone) {
+            Some(timezone) => timezone_input::TimezoneInput::apply(&self.context, timezone).await?,
+            None => {
+                self.context
+                    .bot
+                    .send_message(
+                        msg.chat.id,
+                        format!(
+                            "Unknown timezone {}, write a name like Europe/Berlin",
+                            timezone.trim()
+                        ),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_quiet_hours(&self, msg: &Message, hours: &str) -> StateResult<()> {
+        let user = self.context.db.ensure_user(msg.chat.id.0).await?;
+        let hours = hours.trim();
+        let response = if hours == QUIET_HOURS_OFF {
+            self.context.db.set_quiet_hours(user.chat_id, None).await?;
+            self.context.reschedule().await?;
+            "Reminders can come at any time now".to_owned()
+        } else if let Some(quiet_hours) = QuietHours::parse(hours) {
+            self.context
+                .db
+                .set_quiet_hours(user.chat_id, Some(quiet_hours))
+                .await?;
+            self.context.reschedule().await?;
+            format!(
+                "No reminders from {} to {} ({})",
+                quiet_hours.from.format("%H:%M"),
+                quiet_hours.to.format("%H:%M"),
+                user.timezone.name()
+            )
+        } else {
+            let current = user
+                .quiet_hours
+                .map_or("none".to_owned(), |hours| hours.to_string());
+            format!(
+                "Quiet hours: {} ({}). Write them like 22:00-08:00 after the command or {} to turn them off",
+                current,
+                user.timezone.name(),
+                QUIET_HOURS_OFF
+            )
+        };
+        self.context.bot.send_message(msg.chat.id, response).await?;
+        Ok(())
+    }
+
+    // "both" sets directions of the user, "both Spanish" of the deck only,
+    // "default Spanish" makes the deck follow the user's directions again
+    async fn set_directions(&self, msg: &Message, args: &str) -> StateResult<()> {
+        let user = self.context.db.ensure_user(msg.chat.id.0).await?;
+        let (directions, deck_name) = match args.trim().split_once(' ') {
+            Some((directions, deck_name)) => (directions, Some(deck_name.trim())),
+            None => (args.trim(), None),
+        };
+        let directions = match directions {
+            DEFAULT_DIRECTIONS => Ok(None),
+            directions => Directions::from_key(directions).map(Some),
+        };
+        let options: Vec<&str> = Directions::ALL.iter().map(|d| d.key()).collect();
+        let response = match (directions, deck_name) {
+            (Ok(Some(directions)), None) => {
+                self.context
+                    .db
+                    .set_directions(user.chat_id, directions)
+                    .await?;
+                self.context.reschedule().await?;
+                format!("Review directions: {}", directions.text())
+            }
+            (Ok(directions), Some(deck_name)) => {
+                match self.context.db.find_deck(user.chat_id, deck_name).await? {
+                    Some(deck) => {
+                        self.context
+                            .db
+                            .set_deck_directions(&deck.id, directions)
+                            .await?;
+                        self.context.reschedule().await?;
+                        let directions = directions.unwrap_or(user.directions);
+                        format!("Review directions of {}: {}", deck.name, directions.text())
+                    }
+                    None => format!("Deck {} not found", deck_name),
+                }
+            }
+            _ => format!(
+                "Review directions: {}. Write one of {} after the command, \
+                 add a deck name to change only the deck or write {} before it to reset the deck",
+                user.directions.text(),
+                options.join(", "),
+                DEFAULT_DIRECTIONS
+            ),
+        };
+        self.context.bot.send_message(msg.chat.id, response).await?;
+        Ok(())
+    }
+
+    async fn export(&self, msg: &Message, format: &str) -> StateResult<()> {
+        let Ok(format) = ExportFormat::from_key(format.trim()) else {
+            let options: Vec<String> = ExportFormat::ALL
+                .iter()
+                .map(|f| format!("{} ({})", f.key(), f.text()))
+                .collect();
+            self.context
+                .bot
+                .send_message(
+                    msg.chat.id,
+                    format!("Write one of {} after the command", options.join(", ")),
+                )
+                .await?;
+            return Ok(());
+        };
+
+        let export = Export::load(self.context.db.as_ref(), msg.chat.id.0).await?;
+        if export.is_empty() {
+            self.context
+                .bot
+                .send_message(msg.chat.id, "No words to export yet")
+                .await?;
+            return Ok(());
+        }
+        // Anki packages are written to a temporary SQLite file
+        let data = tokio::task::spawn_blocking(move || export.build(format)).await??;
+        self.context
+            .bot
+            .send_document(
+                msg.chat.id,
+                InputFile::memory(data).file_name(format.file_name()),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn start_session(&self, msg: &Message, count: &str) -> StateResult<()> {
+        let count = count.trim();
+        let limit = if count.is_empty() {
+            None
+        } else if let Ok(limit) = count.parse::<usize>() {
+            Some(limit)
+        } else {
+            self.context
+                .bot
+                .send_message(msg.chat.id, "Write the number of words after the command")
+                .await?;
+            return Ok(());
+        };
+
+        let current_state = self.state.lock().await;
+        let new_state = session::Session::start(&self.context, limit).await;
+        self.handle_new_state(new_state, current_state).await;
+        Ok(())
+    }
+
+    async fn set_word_status(
+        &self,
+        msg: &Message,
+        word: &str,
+        status: WordStatus,
+    ) -> StateResult<()> {
+        let word = word.trim();
+        let response = if word.is_empty() {
+            "Write the word after the command".to_owned()
+        } else if self
+            .context
+            .db
+            .set_word_status(msg.chat.id.0, word, status)
+            .await?
+        {
+            self.context.reschedule().await?;
+            format!("Word {} is {} now", word, status.text())
+        } else {
+            format!("Word {} not found", word)
+        };
+        self.context.bot.send_message(msg.chat.id, response).await?;
+        Ok(())
+    }
+
     pub async fn handle_message(&self, msg: Message) {
         log::debug!("Handling message: {:?}", msg.text());
         if let Some(cmd) = self.parse_command(&msg).await {
@@ -105,11 +431,18 @@ impl FSM {
 
     async fn change_state(
         &self,
-        current_state: MutexGuard<'_, Box<dyn State>>,
+        mut current_state: MutexGuard<'_, Box<dyn State>>,
         new_state: Box<dyn State>,
     ) {
         let updated_state = current_state.name() != new_state.name();
         if updated_state == false {
+            // same state with updated data, e.g. another page of a list
+            let timeout_duration = new_state.timeout().filter(|_| new_state.resets_timeout());
+            *current_state = new_state;
+            if let Some(timeout_duration) = timeout_duration {
+                self.abort_timeout().await;
+                self.set_timeout(timeout_duration).await;
+            }
             return;
         }
 
@@ -175,7 +508,7 @@ impl FSM {
         if let Some(Ok(cmd)) = callback_query
             .data
             .to_owned()
-            .map(|text| keyboard::Button::from_key(&text))
+            .map(|text| keyboard::Button::from_callback_data(&text))
         {
             match cmd {
                 keyboard::Button::Cancel => {
@@ -200,6 +533,14 @@ impl FSM {
         }
     }
 
+    /// Searches the user's words in any state, the state isn't locked
+    /// or changed as queries come on every keystroke
+    pub async fn handle_inline_query(&self, query: InlineQuery) {
+        if let Err(err) = inline_search::answer(&self.context, &query).await {
+            log::error!("Error answering inline query: {}", err);
+        }
+    }
+
     pub async fn handle_event(&self, event: Event) {
         let current_state = self.state.lock().await;
         let new_state = current_state.handle_event(&self.context, event).await;
@@ -241,6 +582,13 @@ impl FSM {
                     .send_message(self.context.chat_id, "Word already exists")
                     .await;
             }
+            StorageError::DeckAlreadyExists => {
+                let _ = self
+                    .context
+                    .bot
+                    .send_message(self.context.chat_id, "Deck already exists")
+                    .await;
+            }
             _ => self.answer_smt_went_wrong().await,
         }
     }
@@ -258,12 +606,33 @@ impl FSM {
 pub struct Context {
     pub bot: teloxide::Bot,
     pub chat_id: ChatId,
-    pub db: Arc<Storage>,
+    pub db: Arc<dyn Storage>,
+    pub challenges: Challenges,
+    pub reminds: RemindQueue,
 }
 
 impl Context {
-    pub fn new(bot: teloxide::Bot, chat_id: ChatId, db: Arc<Storage>) -> Self {
-        Self { bot, chat_id, db }
+    pub fn new(
+        bot: teloxide::Bot,
+        chat_id: ChatId,
+        db: Arc<dyn Storage>,
+        challenges: Challenges,
+        reminds: RemindQueue,
+    ) -> Self {
+        Self {
+            bot,
+            chat_id,
+            db,
+            challenges,
+            reminds,
+        }
+    }
+
+    /// Schedules the user's next remind, needed whenever words or settings
+    /// can make a card due earlier
+    pub async fn reschedule(&self) -> StateResult<()> {
+        reminder::reschedule(self.db.as_ref(), &self.reminds, self.chat_id.0).await?;
+        Ok(())
     }
 }
 
@@ -277,6 +646,12 @@ pub trait State: Send + Sync + Debug {
         Some(time::Duration::from_secs(DEFAULT_STATE_TIMEOUT))
     }
 
+    /// Whether the timeout starts over when the state is replaced by the same
+    /// state with updated data
+    fn resets_timeout(&self) -> bool {
+        false
+    }
+
     async fn on_enter(&self, _: &Context, _: Option<Box<dyn State>>) -> StateResult<()> {
         Ok(())
     }
@@ -293,14 +668,6 @@ pub trait State: Send + Sync + Debug {
         Ok(self.clone_state())
     }
 
-    async fn handle_inline_query(
-        &self,
-        _: &Context,
-        _: InlineQuery,
-    ) -> StateResult<Box<dyn State>> {
-        Ok(self.clone_state())
-    }
-
     async fn handle_event(&self, _: &Context, _: Event) -> StateResult<Box<dyn State>> {
         Ok(self.clone_state())
     }
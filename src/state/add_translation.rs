@@ -26,8 +26,7 @@ impl State for AddTranslation {
         msg: Message,
     ) -> StateResult<Box<dyn State>> {
         if let Some(text) = msg.text() {
-            let translation = translation::Translation::from(text);
-            let translations = translation.to_string();
+            let translation = translation::Translation::parse(text);
             if translation.is_empty() {
                 ctx.bot
                     .send_message(
@@ -38,10 +37,17 @@ impl State for AddTranslation {
                 return Ok(self.clone_state());
             }
 
+            let user = ctx.db.ensure_user(ctx.chat_id.0).await?;
             let word = ctx
                 .db
-                .new_word(ctx.chat_id.0, &self.word, &translations)
+                .new_word(
+                    ctx.chat_id.0,
+                    &self.word,
+                    &translation,
+                    user.active_deck_id.as_deref(),
+                )
                 .await?;
+            ctx.reschedule().await?;
 
             ctx.bot
                 .send_message(
@@ -64,3 +70,86 @@ impl State for AddTranslation {
         Box::new(self.clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        state::FSM,
+        storage::{memory::MemoryStorage, Storage, WordFilter},
+        testing::{context_with_bot, message, FakeTelegram, CHAT_ID},
+    };
+
+    use super::*;
+
+    fn fsm(db: Arc<MemoryStorage>, telegram: &FakeTelegram) -> FSM {
+        let ctx = context_with_bot(db, telegram.bot());
+        FSM::new(Box::new(AddTranslation::new("Hund")), ctx)
+    }
+
+    async fn state_name(fsm: &FSM) -> &'static str {
+        fsm.state.lock().await.name()
+    }
+
+    #[tokio::test]
+    async fn translation_adds_word() {
+        let telegram = FakeTelegram::start().await;
+        let db = Arc::new(MemoryStorage::new());
+        let fsm = fsm(db.clone(), &telegram);
+
+        fsm.handle_message(message("dog, hound")).await;
+
+        let words = db
+            .get_all_words(CHAT_ID, &WordFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(words.len(), 1);
+        assert_eq!(words[0].word, "Hund");
+        assert_eq!(
+            words[0].translations.texts().collect::<Vec<_>>(),
+            ["dog", "hound"]
+        );
+        assert!(telegram.texts()[0].starts_with("Word Hund has been added"));
+        assert_eq!(state_name(&fsm).await, idle::Idle::new().name());
+    }
+
+    #[tokio::test]
+    async fn empty_translation_is_asked_again() {
+        let telegram = FakeTelegram::start().await;
+        let db = Arc::new(MemoryStorage::new());
+        let fsm = fsm(db.clone(), &telegram);
+
+        fsm.handle_message(message("\n \n")).await;
+
+        assert_eq!(
+            db.words_count(CHAT_ID, &WordFilter::default())
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(telegram.texts(), ["Translation for Hund is empty"]);
+        assert_eq!(state_name(&fsm).await, AddTranslation::new("Hund").name());
+    }
+
+    #[tokio::test]
+    async fn existing_word_is_not_added_twice() {
+        let telegram = FakeTelegram::start().await;
+        let db = Arc::new(MemoryStorage::new());
+        db.new_word(CHAT_ID, "hund", &"dog".into(), None)
+            .await
+            .unwrap();
+        let fsm = fsm(db.clone(), &telegram);
+
+        fsm.handle_message(message("hound")).await;
+
+        let words = db
+            .get_all_words(CHAT_ID, &WordFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(words.len(), 1);
+        assert_eq!(words[0].translations.to_plain_string(), "dog");
+        assert_eq!(telegram.texts()[0], "Word already exists");
+        assert_eq!(state_name(&fsm).await, idle::Idle::new().name());
+    }
+}
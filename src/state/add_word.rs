@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use teloxide::{payloads::SendMessageSetters, requests::Requester, types::Message};
 
-use crate::keyboard;
+use crate::{common::normalize, keyboard};
 
 use super::{add_translation, error::StateResult, State};
 
@@ -28,7 +28,7 @@ impl State for AddWord {
         msg: Message,
     ) -> StateResult<Box<dyn State>> {
         if let Some(text) = msg.text() {
-            let word = text.to_owned();
+            let word = normalize::clean(text);
             let has_word = ctx.db.has_word(ctx.chat_id.0, &word).await?;
             if has_word {
                 ctx.bot
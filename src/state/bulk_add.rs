@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use teloxide::{payloads::SendMessageSetters, requests::Requester, types::Message};
+
+use crate::{
+    common::{
+        bulk::{self, BulkLine},
+        normalize,
+    },
+    keyboard,
+    storage::NewWord,
+};
+
+use super::{error::StateResult, idle, State};
+
+/// Waits for a message with a word and its translations on every line
+#[derive(Clone, Debug, Default)]
+pub struct BulkAdd {}
+
+impl BulkAdd {
+    pub fn new() -> BulkAdd {
+        BulkAdd {}
+    }
+
+    pub fn prompt() -> &'static str {
+        "Write words with translations, one per line:\n\nperro - dog, hound\ngato = cat"
+    }
+
+    fn report(added: &[String], duplicates: &[String], malformed: &[String]) -> String {
+        let mut report = format!("Added {} words", added.len());
+        if !added.is_empty() {
+            report.push_str(&format!(": {}", added.join(", ")));
+        }
+        if !duplicates.is_empty() {
+            report.push_str(&format!(
+                "\n\nSkipped existing words: {}",
+                duplicates.join(", ")
+            ));
+        }
+        if !malformed.is_empty() {
+            report.push_str(&format!(
+                "\n\nCouldn't read lines:\n{}",
+                malformed.join("\n")
+            ));
+        }
+        report
+    }
+}
+
+#[async_trait]
+impl State for BulkAdd {
+    async fn on_enter(&self, _: &super::Context, _: Option<Box<dyn State>>) -> StateResult<()> {
+        log::info!("Entered BulkAdd state");
+
+        Ok(())
+    }
+
+    async fn handle_message(
+        &self,
+        ctx: &super::Context,
+        msg: Message,
+    ) -> StateResult<Box<dyn State>> {
+        let Some(text) = msg.text() else {
+            return Ok(self.clone_state());
+        };
+
+        let user = ctx.db.ensure_user(ctx.chat_id.0).await?;
+        let mut words: Vec<NewWord> = vec![];
+        let mut duplicates = vec![];
+        let mut malformed = vec![];
+        for line in bulk::parse(text) {
+            match line {
+                BulkLine::Word { word, translation } => {
+                    let key = normalize::key(&word);
+                    if words.iter().any(|w| normalize::key(&w.word) == key) {
+                        duplicates.push(word);
+                    } else {
+                        words.push(NewWord {
+                            word,
+                            translation,
+                            deck_id: user.active_deck_id.clone(),
+                            ..Default::default()
+                        });
+                    }
+                }
+                BulkLine::Malformed(line) => malformed.push(line),
+            }
+        }
+        if words.is_empty() {
+            ctx.bot
+                .send_message(
+                    msg.chat.id,
+                    format!(
+                        "No words found, write them like this\n\n{}",
+                        BulkAdd::prompt()
+                    ),
+                )
+                .reply_markup(keyboard::Button::Cancel.to_keyboard())
+                .await?;
+            return Ok(self.clone_state());
+        }
+
+        let added: Vec<String> = ctx
+            .db
+            .new_words(ctx.chat_id.0, &words)
+            .await?
+            .into_iter()
+            .map(|w| w.word)
+            .collect();
+        ctx.reschedule().await?;
+        let added_keys: Vec<String> = added.iter().map(|w| normalize::key(w)).collect();
+        duplicates.extend(
+            words
+                .into_iter()
+                .map(|w| w.word)
+                .filter(|w| !added_keys.contains(&normalize::key(w))),
+        );
+
+        ctx.bot
+            .send_message(
+                msg.chat.id,
+                BulkAdd::report(&added, &duplicates, &malformed),
+            )
+            .await?;
+        Ok(Box::new(idle::Idle::new()))
+    }
+
+    fn clone_state(&self) -> Box<dyn State> {
+        Box::new(self.clone())
+    }
+}
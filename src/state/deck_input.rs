@@ -0,0 +1,185 @@
+use async_trait::async_trait;
+use teloxide::{
+    payloads::SendMessageSetters,
+    requests::Requester,
+    types::{ChatId, Message},
+};
+
+use crate::{keyboard, storage::Deck};
+
+use super::{decks::Decks, error::StateResult, State};
+
+static NO_DECK: &str = "-";
+
+#[derive(Clone, Debug)]
+pub enum DeckAction {
+    Create,
+    /// Contains the deck when its new name is expected
+    Rename(Option<Deck>),
+    Delete,
+    Choose,
+    ToggleReminders,
+}
+
+impl DeckAction {
+    pub fn prompt(&self) -> String {
+        match self {
+            DeckAction::Create => {
+                "Write a name of the new deck. Languages could be added in the end: Spanish (es-en)"
+                    .to_owned()
+            }
+            DeckAction::Rename(None) => "Write a name of the deck to rename".to_owned(),
+            DeckAction::Rename(Some(deck)) => format!("Write a new name for {}", deck.name),
+            DeckAction::Delete => {
+                "Write a name of the deck to delete, its words will be kept without a deck"
+                    .to_owned()
+            }
+            DeckAction::Choose => format!(
+                "Write a name of the deck for new words or {} to add them without a deck",
+                NO_DECK
+            ),
+            DeckAction::ToggleReminders => {
+                "Write a name of the deck to turn its reminders on/off".to_owned()
+            }
+        }
+    }
+}
+
+/// Waits for a deck name to apply `DeckAction`
+#[derive(Clone, Debug)]
+pub struct DeckInput {
+    action: DeckAction,
+}
+
+impl DeckInput {
+    pub fn new(action: DeckAction) -> DeckInput {
+        DeckInput { action }
+    }
+
+    // "Spanish (es-en)" -> ("Spanish", Some("es"), Some("en"))
+    fn parse_deck(text: &str) -> (String, Option<String>, Option<String>) {
+        let text = text.trim();
+        if let Some((name, languages)) = text
+            .strip_suffix(')')
+            .and_then(|text| text.rsplit_once('('))
+        {
+            if let Some((from, to)) = languages.split_once('-') {
+                let (name, from, to) = (name.trim(), from.trim(), to.trim());
+                if !name.is_empty() && !from.is_empty() && !to.is_empty() {
+                    return (name.to_owned(), Some(from.to_owned()), Some(to.to_owned()));
+                }
+            }
+        }
+        (text.to_owned(), None, None)
+    }
+
+    async fn send(&self, ctx: &super::Context, text: impl Into<String>) -> StateResult<()> {
+        ctx.bot.send_message(ctx.chat_id, text).await?;
+        Ok(())
+    }
+
+    async fn find_deck(&self, ctx: &super::Context, name: &str) -> StateResult<Option<Deck>> {
+        let deck = ctx.db.find_deck(ctx.chat_id.0, name).await?;
+        if deck.is_none() {
+            ctx.bot
+                .send_message(
+                    ctx.chat_id,
+                    format!("Deck {} not found, write another name", name),
+                )
+                .reply_markup(keyboard::Button::Cancel.to_keyboard())
+                .await?;
+        }
+        Ok(deck)
+    }
+
+    async fn apply(&self, ctx: &super::Context, text: &str) -> StateResult<Box<dyn State>> {
+        let ChatId(chat_id) = ctx.chat_id;
+        match &self.action {
+            DeckAction::Create => {
+                let (name, from_lang, to_lang) = DeckInput::parse_deck(text);
+                let deck = ctx.db.new_deck(chat_id, &name, from_lang, to_lang).await?;
+                self.send(ctx, format!("Deck {} has been created", deck.name))
+                    .await?;
+            }
+            DeckAction::Rename(None) => {
+                let Some(deck) = self.find_deck(ctx, text).await? else {
+                    return Ok(self.clone_state());
+                };
+                let action = DeckAction::Rename(Some(deck));
+                ctx.bot
+                    .send_message(ctx.chat_id, action.prompt())
+                    .reply_markup(keyboard::Button::Cancel.to_keyboard())
+                    .await?;
+                return Ok(Box::new(DeckInput::new(action)));
+            }
+            DeckAction::Rename(Some(deck)) => {
+                ctx.db.rename_deck(chat_id, &deck.id, text).await?;
+                self.send(
+                    ctx,
+                    format!("Deck {} has been renamed to {}", deck.name, text),
+                )
+                .await?;
+            }
+            DeckAction::Delete => {
+                let Some(deck) = self.find_deck(ctx, text).await? else {
+                    return Ok(self.clone_state());
+                };
+                ctx.db.remove_deck(&deck.id).await?;
+                // words of a deck with disabled reminders are reminded again
+                ctx.reschedule().await?;
+                self.send(ctx, format!("Deck {} has been deleted", deck.name))
+                    .await?;
+            }
+            DeckAction::Choose if text == NO_DECK => {
+                ctx.db.set_active_deck(chat_id, None).await?;
+                self.send(ctx, "New words will be added without a deck")
+                    .await?;
+            }
+            DeckAction::Choose => {
+                let Some(deck) = self.find_deck(ctx, text).await? else {
+                    return Ok(self.clone_state());
+                };
+                ctx.db.set_active_deck(chat_id, Some(&deck.id)).await?;
+                self.send(ctx, format!("New words will be added to {}", deck.name))
+                    .await?;
+            }
+            DeckAction::ToggleReminders => {
+                let Some(deck) = self.find_deck(ctx, text).await? else {
+                    return Ok(self.clone_state());
+                };
+                let enabled = !deck.reminders_enabled;
+                ctx.db.set_deck_reminders(&deck.id, enabled).await?;
+                ctx.reschedule().await?;
+                let state = if enabled { "on" } else { "off" };
+                self.send(
+                    ctx,
+                    format!("Reminders of {} are turned {}", deck.name, state),
+                )
+                .await?;
+            }
+        }
+        Ok(Box::new(Decks::new(None)))
+    }
+}
+
+#[async_trait]
+impl State for DeckInput {
+    async fn handle_message(
+        &self,
+        ctx: &super::Context,
+        msg: Message,
+    ) -> StateResult<Box<dyn State>> {
+        if let Some(text) = msg.text() {
+            let text = text.trim();
+            if !text.is_empty() {
+                return self.apply(ctx, text).await;
+            }
+        }
+
+        Ok(self.clone_state())
+    }
+
+    fn clone_state(&self) -> Box<dyn State> {
+        Box::new(self.clone())
+    }
+}
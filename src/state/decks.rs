@@ -0,0 +1,141 @@
+use async_trait::async_trait;
+use teloxide::{
+    payloads::{EditMessageTextSetters, SendMessageSetters},
+    requests::Requester,
+    types::MessageId,
+};
+
+use crate::{keyboard, storage::WordFilter};
+
+use super::{
+    deck_input::{DeckAction, DeckInput},
+    error::{StateError, StateResult},
+    events::Event,
+    State,
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct Decks {
+    message_id: Option<MessageId>,
+}
+
+impl Decks {
+    pub fn new(message_id: Option<MessageId>) -> Decks {
+        Decks { message_id }
+    }
+
+    async fn decks_text(ctx: &super::Context) -> StateResult<String> {
+        let user = ctx.db.ensure_user(ctx.chat_id.0).await?;
+        let decks = ctx.db.get_decks(ctx.chat_id.0).await?;
+        if decks.is_empty() {
+            return Ok("You don't have decks yet".to_owned());
+        }
+
+        let mut text = String::from("Your decks:\n");
+        for deck in decks {
+            let filter = WordFilter {
+                deck_id: Some(deck.id.clone()),
+                ..Default::default()
+            };
+            let words_count = ctx.db.words_count(ctx.chat_id.0, &filter).await?;
+            text.push_str(&format!("\n• {}", deck.name));
+            if let Some(languages) = deck.languages() {
+                text.push_str(&format!(" ({})", languages));
+            }
+            if let Some(directions) = deck.directions {
+                text.push_str(&format!(" [{}]", directions.text()));
+            }
+            text.push_str(&format!(" - {} words", words_count));
+            if user.active_deck_id.as_ref() == Some(&deck.id) {
+                text.push_str(" ⭐");
+            }
+            if !deck.reminders_enabled {
+                text.push_str(" 🔕");
+            }
+        }
+        Ok(text)
+    }
+
+    fn keyboard() -> teloxide::types::InlineKeyboardMarkup {
+        keyboard::make(&[
+            vec![
+                Some(keyboard::Button::NewDeck),
+                Some(keyboard::Button::RenameDeck),
+            ],
+            vec![
+                Some(keyboard::Button::DeleteDeck),
+                Some(keyboard::Button::ChooseDeck),
+            ],
+            vec![Some(keyboard::Button::ToggleDeckReminders)],
+            vec![Some(keyboard::Button::Cancel)],
+        ])
+    }
+
+    async fn ask(
+        &self,
+        ctx: &super::Context,
+        message_id: MessageId,
+        action: DeckAction,
+    ) -> StateResult<Box<dyn State>> {
+        ctx.bot
+            .edit_message_text(ctx.chat_id, message_id, action.prompt())
+            .reply_markup(keyboard::Button::Cancel.to_keyboard())
+            .await?;
+        Ok(Box::new(DeckInput::new(action)))
+    }
+}
+
+#[async_trait]
+impl State for Decks {
+    async fn on_enter(&self, ctx: &super::Context, _: Option<Box<dyn State>>) -> StateResult<()> {
+        log::info!("Entered Decks state");
+
+        let text = Decks::decks_text(ctx).await?;
+        if let Some(message_id) = self.message_id {
+            ctx.bot
+                .edit_message_text(ctx.chat_id, message_id, text)
+                .reply_markup(Decks::keyboard())
+                .await?;
+        } else {
+            ctx.bot
+                .send_message(ctx.chat_id, text)
+                .reply_markup(Decks::keyboard())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn handle_event(
+        &self,
+        ctx: &super::Context,
+        event: Event,
+    ) -> StateResult<Box<dyn State>> {
+        if let Event::Button(cmd, query) = event {
+            let msg = query
+                .message
+                .ok_or(StateError::ExpectedMessageInsideCallbackQuery)?;
+            return match cmd {
+                keyboard::Button::NewDeck => self.ask(ctx, msg.id, DeckAction::Create).await,
+                keyboard::Button::RenameDeck => {
+                    self.ask(ctx, msg.id, DeckAction::Rename(None)).await
+                }
+                keyboard::Button::DeleteDeck => self.ask(ctx, msg.id, DeckAction::Delete).await,
+                keyboard::Button::ChooseDeck => self.ask(ctx, msg.id, DeckAction::Choose).await,
+                keyboard::Button::ToggleDeckReminders => {
+                    self.ask(ctx, msg.id, DeckAction::ToggleReminders).await
+                }
+                _ => Err(StateError::UnexpectedCommand(format!(
+                    "Unexpected command {} - {}",
+                    cmd.key(),
+                    cmd.text(),
+                ))),
+            };
+        }
+
+        Ok(self.clone_state())
+    }
+
+    fn clone_state(&self) -> Box<dyn State> {
+        Box::new(self.clone())
+    }
+}
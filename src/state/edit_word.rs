@@ -0,0 +1,246 @@
+use async_trait::async_trait;
+use teloxide::{
+    payloads::SendMessageSetters,
+    requests::Requester,
+    types::{InlineKeyboardMarkup, Message, ParseMode},
+    utils::markdown,
+};
+
+use crate::{
+    common::{normalize, translation::Translation},
+    keyboard,
+    storage::{error::StorageError, Word},
+};
+
+use super::{
+    error::{StateError, StateResult},
+    events::Event,
+    State,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub enum EditAction {
+    ChangeWord,
+    ReplaceTranslations,
+    AppendTranslations,
+}
+
+impl EditAction {
+    pub fn prompt(&self, word: &Word) -> String {
+        match self {
+            EditAction::ChangeWord => format!("Write a new text of the word {}", word.word),
+            EditAction::ReplaceTranslations => {
+                format!("Write new translations for {}", word.word)
+            }
+            EditAction::AppendTranslations => {
+                format!("Write translations to add to {}", word.word)
+            }
+        }
+    }
+}
+
+/// Waits for a word to edit, then for changes chosen in the menu
+#[derive(Clone, Debug)]
+pub struct EditWord {
+    word: Option<Word>,
+    action: Option<EditAction>,
+}
+
+impl EditWord {
+    pub fn new(word: Option<Word>) -> EditWord {
+        EditWord { word, action: None }
+    }
+
+    /// Edits the word written after the command or asks for it
+    pub async fn start(ctx: &super::Context, word: &str) -> StateResult<Box<dyn State>> {
+        if word.trim().is_empty() {
+            return Ok(Box::new(EditWord::new(None)));
+        }
+        match ctx.db.find_word(ctx.chat_id.0, word).await? {
+            Some(word) => Ok(Box::new(EditWord::new(Some(word)))),
+            None => {
+                ctx.bot
+                    .send_message(ctx.chat_id, format!("Word {} not found", word.trim()))
+                    .await?;
+                Ok(Box::new(EditWord::new(None)))
+            }
+        }
+    }
+
+    fn menu() -> InlineKeyboardMarkup {
+        keyboard::make(&[
+            vec![Some(keyboard::Button::ChangeWord)],
+            vec![
+                Some(keyboard::Button::ReplaceTranslations),
+                Some(keyboard::Button::AppendTranslations),
+            ],
+            vec![Some(keyboard::Button::ResetProgress)],
+            vec![Some(keyboard::Button::Cancel)],
+        ])
+    }
+
+    async fn send_menu(&self, ctx: &super::Context, word: &Word) -> StateResult<()> {
+        ctx.bot
+            .send_message(
+                ctx.chat_id,
+                format!(
+                    "{} \\- {}\n\nWhat would you like to change?",
+                    markdown::escape(&word.word),
+                    word.translations.to_formatted_string()
+                ),
+            )
+            .parse_mode(ParseMode::MarkdownV2)
+            .reply_markup(EditWord::menu())
+            .await?;
+        Ok(())
+    }
+
+    async fn send(&self, ctx: &super::Context, text: impl Into<String>) -> StateResult<()> {
+        ctx.bot
+            .send_message(ctx.chat_id, text)
+            .reply_markup(keyboard::Button::Cancel.to_keyboard())
+            .await?;
+        Ok(())
+    }
+
+    async fn choose_word(&self, ctx: &super::Context, text: &str) -> StateResult<Box<dyn State>> {
+        match ctx.db.find_word(ctx.chat_id.0, text).await? {
+            Some(word) => {
+                self.send_menu(ctx, &word).await?;
+                Ok(Box::new(EditWord::new(Some(word))))
+            }
+            None => {
+                self.send(ctx, "Word not found, write another one").await?;
+                Ok(self.clone_state())
+            }
+        }
+    }
+
+    async fn apply(
+        &self,
+        ctx: &super::Context,
+        word: &Word,
+        action: EditAction,
+        text: &str,
+    ) -> StateResult<Box<dyn State>> {
+        let chat_id = ctx.chat_id.0;
+        let new_text = match action {
+            EditAction::ChangeWord => {
+                let new_text = normalize::clean(text);
+                if new_text.is_empty() {
+                    self.send(ctx, "The word is empty, write another one")
+                        .await?;
+                    return Ok(self.clone_state());
+                }
+                match ctx.db.rename_word(chat_id, &word.id, &new_text).await {
+                    Err(StorageError::WordAlreadyExists) => {
+                        self.send(ctx, "Word already exists, write another one")
+                            .await?;
+                        return Ok(self.clone_state());
+                    }
+                    result => result?,
+                }
+                new_text
+            }
+            EditAction::ReplaceTranslations | EditAction::AppendTranslations => {
+                let parsed = Translation::parse(text);
+                if parsed.is_empty() {
+                    self.send(ctx, format!("Translation for {} is empty", word.word))
+                        .await?;
+                    return Ok(self.clone_state());
+                }
+                let translation = match action {
+                    EditAction::AppendTranslations => word.translations.merge(&parsed),
+                    _ => parsed,
+                };
+                ctx.db.set_translations(&word.id, &translation).await?;
+                word.word.clone()
+            }
+        };
+
+        let word = ctx
+            .db
+            .find_word(chat_id, &new_text)
+            .await?
+            .ok_or(StateError::WordNotFound(new_text))?;
+        self.send_menu(ctx, &word).await?;
+        Ok(Box::new(EditWord::new(Some(word))))
+    }
+
+    async fn reset_progress(&self, ctx: &super::Context, word: &Word) -> StateResult<()> {
+        ctx.db.reset_word_progress(&word.id).await?;
+        ctx.reschedule().await?;
+        ctx.bot
+            .send_message(
+                ctx.chat_id,
+                format!("Word {} will be learned from scratch", word.word),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl State for EditWord {
+    async fn on_enter(&self, ctx: &super::Context, _: Option<Box<dyn State>>) -> StateResult<()> {
+        log::info!("Entered EditWord state");
+
+        match &self.word {
+            Some(word) => self.send_menu(ctx, word).await,
+            None => self.send(ctx, "Write a word to edit").await,
+        }
+    }
+
+    async fn handle_message(
+        &self,
+        ctx: &super::Context,
+        msg: Message,
+    ) -> StateResult<Box<dyn State>> {
+        let Some(text) = msg.text() else {
+            return Ok(self.clone_state());
+        };
+        match (&self.word, self.action) {
+            (Some(word), Some(action)) => self.apply(ctx, word, action, text).await,
+            (Some(word), None) => {
+                self.send_menu(ctx, word).await?;
+                Ok(self.clone_state())
+            }
+            (None, _) => self.choose_word(ctx, text).await,
+        }
+    }
+
+    async fn handle_event(
+        &self,
+        ctx: &super::Context,
+        event: Event,
+    ) -> StateResult<Box<dyn State>> {
+        let (Event::Button(cmd, _), Some(word)) = (event, &self.word) else {
+            return Ok(self.clone_state());
+        };
+        let action = match cmd {
+            keyboard::Button::ChangeWord => EditAction::ChangeWord,
+            keyboard::Button::ReplaceTranslations => EditAction::ReplaceTranslations,
+            keyboard::Button::AppendTranslations => EditAction::AppendTranslations,
+            keyboard::Button::ResetProgress => {
+                self.reset_progress(ctx, word).await?;
+                return Ok(self.clone_state());
+            }
+            _ => {
+                return Err(StateError::UnexpectedCommand(format!(
+                    "Unexpected command {} - {}",
+                    cmd.key(),
+                    cmd.text(),
+                )))
+            }
+        };
+        self.send(ctx, action.prompt(word)).await?;
+        Ok(Box::new(EditWord {
+            action: Some(action),
+            ..self.clone()
+        }))
+    }
+
+    fn clone_state(&self) -> Box<dyn State> {
+        Box::new(self.clone())
+    }
+}
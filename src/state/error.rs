@@ -1,7 +1,7 @@
 use teloxide::types::User;
 use thiserror::Error;
 
-use crate::storage::error::StorageError;
+use crate::{common::export::ExportError, storage::error::StorageError};
 
 #[derive(Error, Debug)]
 pub enum StateError {
@@ -9,18 +9,26 @@ pub enum StateError {
     StorageError(#[from] StorageError),
     #[error("Request bot error")]
     RequestError(#[from] teloxide::RequestError),
+    #[error("Download file error")]
+    DownloadError(#[from] teloxide::DownloadError),
+    #[error("Export error")]
+    ExportError(#[from] ExportError),
+    #[error("Blocking task error")]
+    BlockingTaskError(#[from] tokio::task::JoinError),
     #[error("Expected message inside callback query")]
     ExpectedMessageInsideCallbackQuery,
+    #[error("Expected poll inside message")]
+    ExpectedPollInsideMessage,
     #[error("Word already exists")]
     WordAlreadyExists,
+    #[error("Word not found")]
+    WordNotFound(String),
     #[error("Unexpected command")]
     UnexpectedCommand(String),
     #[error("Unexpected query")]
     UnexpectedQueryData(Option<String>, User),
     #[error("Unexpected event")]
     UnexpectedEvent(String),
-    #[error("Incorrect word level: `{0}`")]
-    IncorrectWordLevel(i32),
 }
 
 pub type StateResult<T> = std::result::Result<T, StateError>;
@@ -1,12 +1,15 @@
-use teloxide::types::{CallbackQuery, Message};
+use teloxide::types::{CallbackQuery, Message, PollAnswer};
 
-use crate::{keyboard::Button, prisma, storage::LiteUser};
+use crate::{
+    keyboard::Button,
+    storage::{DueCard, LiteUser, Word},
+};
 
 #[derive(Debug, Clone)]
 pub enum Event {
     Message(Message),
     Button(Button, CallbackQuery),
-    Remind,
-    RemindWord(prisma::word::Data),
-    RemindWordToUser(prisma::word::Data, LiteUser),
+    PollAnswer(PollAnswer),
+    RemindWord(Word),
+    RemindWordToUser(DueCard, LiteUser),
 }
@@ -4,18 +4,20 @@ use async_trait::async_trait;
 use teloxide::{
     payloads::{EditMessageTextSetters, SendMessageSetters},
     requests::Requester,
-    types::CallbackQuery,
+    types::{CallbackQuery, Message},
 };
 
 use crate::{
     keyboard::{self, Button},
     state::remove_words,
+    storage::{WordFilter, WordStatus},
 };
 
 use super::{
-    add_word,
+    add_word, bulk_add, decks,
     error::{StateError, StateResult},
     events::Event,
+    import_file::ImportFile,
     remind::Remind,
     word_list, State,
 };
@@ -52,14 +54,43 @@ impl Idle {
             .ok_or(StateError::ExpectedMessageInsideCallbackQuery)?;
         match button {
             keyboard::Button::AddWord => {
+                let user = ctx.db.ensure_user(ctx.chat_id.0).await?;
+                let mut text = String::from("Write a word for translation");
+                if let Some(deck_id) = user.active_deck_id {
+                    let decks = ctx.db.get_decks(ctx.chat_id.0).await?;
+                    if let Some(deck) = decks.iter().find(|d| d.id == deck_id) {
+                        text.push_str(&format!(", it will be added to {}", deck.name));
+                    }
+                }
                 ctx.bot
-                    .edit_message_text(msg.chat.id, msg.id, "Write a word for translation")
+                    .edit_message_text(msg.chat.id, msg.id, text)
                     .reply_markup(keyboard::Button::Cancel.to_keyboard())
                     .await?;
                 return Ok(Box::new(add_word::AddWord::new()));
             }
             keyboard::Button::ListWords => {
-                return Ok(Box::new(word_list::WordList::new(Some(msg.id), 0)));
+                return Ok(Box::new(word_list::WordList::new(
+                    Some(msg.id),
+                    0,
+                    WordFilter::with_statuses(&[WordStatus::Active]),
+                )));
+            }
+            keyboard::Button::Archive => {
+                return Ok(Box::new(word_list::WordList::new(
+                    Some(msg.id),
+                    0,
+                    WordFilter::with_statuses(&[WordStatus::Mastered, WordStatus::Suspended]),
+                )));
+            }
+            keyboard::Button::Decks => {
+                return Ok(Box::new(decks::Decks::new(Some(msg.id))));
+            }
+            keyboard::Button::BulkAdd => {
+                ctx.bot
+                    .edit_message_text(msg.chat.id, msg.id, bulk_add::BulkAdd::prompt())
+                    .reply_markup(keyboard::Button::Cancel.to_keyboard())
+                    .await?;
+                return Ok(Box::new(bulk_add::BulkAdd::new()));
             }
             keyboard::Button::RemoveWord => {
                 ctx.bot
@@ -101,13 +132,37 @@ impl State for Idle {
         None
     }
 
+    async fn handle_message(
+        &self,
+        ctx: &super::Context,
+        msg: Message,
+    ) -> StateResult<Box<dyn State>> {
+        match msg.document() {
+            Some(document) if ImportFile::is_supported(document) => {
+                ImportFile::from_document(ctx, document).await
+            }
+            Some(_) => {
+                ctx.bot
+                    .send_message(
+                        msg.chat.id,
+                        "Only .csv, .tsv and .apkg (Anki) files can be imported",
+                    )
+                    .await?;
+                Ok(self.clone_state())
+            }
+            None => Ok(self.clone_state()),
+        }
+    }
+
     async fn handle_event(
         &self,
         ctx: &super::Context,
         event: Event,
     ) -> StateResult<Box<dyn State>> {
         match event {
-            Event::RemindWordToUser(word, user) => Ok(Box::new(Remind::new(word, user))),
+            Event::RemindWordToUser(card, user) => {
+                Ok(Box::new(Remind::prepare(ctx, card, user, None).await?))
+            }
             Event::Button(button, query) => self.handle_cmd(ctx, button, query).await,
             _ => Ok(self.clone_state()),
         }
@@ -0,0 +1,360 @@
+use std::{collections::HashMap, time};
+
+use async_trait::async_trait;
+use teloxide::{
+    net::Download,
+    payloads::{EditMessageTextSetters, SendMessageSetters},
+    requests::Requester,
+    types::{Document, InlineKeyboardButton, InlineKeyboardMarkup, MessageId},
+};
+
+use crate::{
+    common::{
+        config::{MAX_ANKI_FILE_SIZE, MAX_IMPORT_FILE_SIZE},
+        import::{self, Column, ImportRow, Table},
+    },
+    keyboard,
+    storage::{DuplicatePolicy, NewWord},
+};
+
+use super::{
+    error::{StateError, StateResult},
+    events::Event,
+    idle, State,
+};
+
+static PREVIEW_ROWS: usize = 3;
+static IMPORT_TIMEOUT: u64 = 5 * 60; // seconds
+
+/// Shows a preview of an uploaded CSV/TSV file or Anki deck and imports it
+/// when the column mapping and the duplicate policy are confirmed
+#[derive(Clone, Debug)]
+pub struct ImportFile {
+    file_name: String,
+    table: Table,
+    mapping: Vec<Column>,
+    policy: DuplicatePolicy,
+    /// Intervals of Anki cards are carried over
+    keep_progress: bool,
+    message_id: Option<MessageId>,
+}
+
+impl ImportFile {
+    pub fn is_supported(document: &Document) -> bool {
+        document.file_name.as_deref().is_some_and(|name| {
+            let name = name.to_lowercase();
+            name.ends_with(".csv") || name.ends_with(".tsv") || ImportFile::is_anki(&name)
+        })
+    }
+
+    fn is_anki(file_name: &str) -> bool {
+        file_name.to_lowercase().ends_with(".apkg")
+    }
+
+    /// Downloads and parses the document, stays idle if it can't be read
+    pub async fn from_document(
+        ctx: &super::Context,
+        document: &Document,
+    ) -> StateResult<Box<dyn State>> {
+        let file_name = document.file_name.clone().unwrap_or_default();
+        let max_size = if ImportFile::is_anki(&file_name) {
+            MAX_ANKI_FILE_SIZE
+        } else {
+            MAX_IMPORT_FILE_SIZE
+        };
+        if document.file.size > max_size {
+            ctx.bot
+                .send_message(
+                    ctx.chat_id,
+                    format!(
+                        "The file is too big, files up to {} KB can be imported",
+                        max_size / 1024
+                    ),
+                )
+                .await?;
+            return Ok(Box::new(idle::Idle::new()));
+        }
+
+        let file = ctx.bot.get_file(&document.file.id).await?;
+        let mut data: Vec<u8> = vec![];
+        ctx.bot.download_file(&file.path, &mut data).await?;
+        let table = if ImportFile::is_anki(&file_name) {
+            // the package is unpacked to a temporary SQLite file
+            tokio::task::spawn_blocking(move || Table::from_anki(&data)).await?
+        } else {
+            Table::parse(&data, &file_name)
+        };
+        match table {
+            Ok(table) => Ok(Box::new(ImportFile {
+                file_name,
+                mapping: table.default_mapping(),
+                keep_progress: !table.progress.is_empty(),
+                table,
+                policy: DuplicatePolicy::Skip,
+                message_id: None,
+            })),
+            Err(err) => {
+                ctx.bot.send_message(ctx.chat_id, err.to_string()).await?;
+                Ok(Box::new(idle::Idle::new()))
+            }
+        }
+    }
+
+    fn preview(&self) -> String {
+        let mut text = match self.table.delimiter {
+            Some(delimiter) => format!(
+                "File {}: {} rows, delimiter: {}, header: {}\n\nColumns:\n",
+                self.file_name,
+                self.table.rows.len(),
+                import::delimiter_name(delimiter),
+                if self.table.header.is_some() {
+                    "yes"
+                } else {
+                    "no"
+                }
+            ),
+            None => format!(
+                "Anki deck {}: {} notes\n\nFields:\n",
+                self.file_name,
+                self.table.rows.len()
+            ),
+        };
+        for (index, column) in self.mapping.iter().enumerate() {
+            let name = self
+                .table
+                .header
+                .as_ref()
+                .and_then(|header| header.get(index))
+                .map(|name| format!(" ({})", name))
+                .unwrap_or_default();
+            text.push_str(&format!("{}. {}{}\n", index + 1, column.text(), name));
+        }
+        text.push_str("\nFirst rows:\n");
+        for row in self.table.rows.iter().take(PREVIEW_ROWS) {
+            text.push_str(&format!("{}\n", row.join(" | ")));
+        }
+        text.push_str(&format!("\nExisting words: {}", self.policy.text()));
+        if !self.table.progress.is_empty() {
+            text.push_str(&format!(
+                "\nAnki progress: {}",
+                if self.keep_progress { "keep" } else { "reset" }
+            ));
+        }
+        text.push_str("\n\nTap a column to change its meaning");
+        text
+    }
+
+    // "<column index>" is the payload of column buttons
+    fn keyboard(&self) -> InlineKeyboardMarkup {
+        let mut rows: Vec<Vec<InlineKeyboardButton>> = self
+            .mapping
+            .chunks(3)
+            .enumerate()
+            .map(|(chunk, columns)| {
+                columns
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, column)| {
+                        let index = chunk * 3 + offset;
+                        keyboard::Button::ImportColumn.to_inline_button_with_payload(
+                            format!("{}. {}", index + 1, column.text()),
+                            &index.to_string(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        rows.push(vec![keyboard::Button::ImportPolicy.to_inline_button()]);
+        if !self.table.progress.is_empty() {
+            rows.push(vec![keyboard::Button::ImportProgress.to_inline_button()]);
+        }
+        rows.push(vec![
+            keyboard::Button::StartImport.to_inline_button(),
+            keyboard::Button::Cancel.to_inline_button(),
+        ]);
+        InlineKeyboardMarkup::new(rows)
+    }
+
+    async fn update_preview(&self, ctx: &super::Context) -> StateResult<()> {
+        if let Some(message_id) = self.message_id {
+            ctx.bot
+                .edit_message_text(ctx.chat_id, message_id, self.preview())
+                .reply_markup(self.keyboard())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn change_column(
+        &self,
+        ctx: &super::Context,
+        payload: Option<&str>,
+    ) -> StateResult<Box<dyn State>> {
+        let Some(index) = payload
+            .and_then(|payload| payload.parse::<usize>().ok())
+            .filter(|index| *index < self.mapping.len())
+        else {
+            return Ok(self.clone_state());
+        };
+        let mut mapping = self.mapping.clone();
+        mapping[index] = mapping[index].next();
+        let new_state = ImportFile {
+            mapping,
+            ..self.clone()
+        };
+        new_state.update_preview(ctx).await?;
+        Ok(Box::new(new_state))
+    }
+
+    async fn change_policy(&self, ctx: &super::Context) -> StateResult<Box<dyn State>> {
+        let policy = DuplicatePolicy::ALL
+            .into_iter()
+            .cycle()
+            .skip_while(|policy| *policy != self.policy)
+            .nth(1)
+            .unwrap_or(DuplicatePolicy::Skip);
+        let new_state = ImportFile {
+            policy,
+            ..self.clone()
+        };
+        new_state.update_preview(ctx).await?;
+        Ok(Box::new(new_state))
+    }
+
+    async fn toggle_progress(&self, ctx: &super::Context) -> StateResult<Box<dyn State>> {
+        let new_state = ImportFile {
+            keep_progress: !self.keep_progress,
+            ..self.clone()
+        };
+        new_state.update_preview(ctx).await?;
+        Ok(Box::new(new_state))
+    }
+
+    // decks are found by name, missing ones are created
+    async fn deck_ids(
+        &self,
+        ctx: &super::Context,
+        rows: &[ImportRow],
+    ) -> StateResult<HashMap<String, String>> {
+        let mut deck_ids = HashMap::new();
+        for name in rows.iter().filter_map(|row| row.deck.as_ref()) {
+            if deck_ids.contains_key(name) {
+                continue;
+            }
+            let deck = match ctx.db.find_deck(ctx.chat_id.0, name).await? {
+                Some(deck) => deck,
+                None => ctx.db.new_deck(ctx.chat_id.0, name, None, None).await?,
+            };
+            deck_ids.insert(name.clone(), deck.id);
+        }
+        Ok(deck_ids)
+    }
+
+    async fn import(&self, ctx: &super::Context) -> StateResult<Box<dyn State>> {
+        if !self.mapping.contains(&Column::Word) || !self.mapping.contains(&Column::Translation) {
+            ctx.bot
+                .send_message(
+                    ctx.chat_id,
+                    "Choose columns with words and translations first",
+                )
+                .await?;
+            return Ok(self.clone_state());
+        }
+
+        let (rows, malformed) = self.table.read(&self.mapping);
+        let user = ctx.db.ensure_user(ctx.chat_id.0).await?;
+        let deck_ids = self.deck_ids(ctx, &rows).await?;
+        let words: Vec<NewWord> = rows
+            .into_iter()
+            .map(|row| NewWord {
+                deck_id: match &row.deck {
+                    Some(name) => deck_ids.get(name).cloned(),
+                    None => user.active_deck_id.clone(),
+                },
+                word: row.word,
+                translation: row.translation,
+                tags: row.tags,
+                cards: if self.keep_progress {
+                    row.cards
+                } else {
+                    vec![]
+                },
+            })
+            .collect();
+        let summary = ctx
+            .db
+            .import_words(ctx.chat_id.0, &words, self.policy)
+            .await?;
+        ctx.reschedule().await?;
+
+        let mut report = format!(
+            "Import is done\n\nAdded: {}\nMerged: {}\nOverwritten: {}\nSkipped existing: {}",
+            summary.added, summary.merged, summary.overwritten, summary.skipped
+        );
+        if malformed > 0 {
+            report.push_str(&format!(
+                "\nRows without a word or translation: {}",
+                malformed
+            ));
+        }
+        if let Some(message_id) = self.message_id {
+            ctx.bot
+                .edit_message_text(ctx.chat_id, message_id, report)
+                .await?;
+        } else {
+            ctx.bot.send_message(ctx.chat_id, report).await?;
+        }
+        Ok(Box::new(idle::Idle::new()))
+    }
+}
+
+#[async_trait]
+impl State for ImportFile {
+    async fn on_enter(&self, ctx: &super::Context, _: Option<Box<dyn State>>) -> StateResult<()> {
+        log::info!("Entered ImportFile state");
+
+        ctx.bot
+            .send_message(ctx.chat_id, self.preview())
+            .reply_markup(self.keyboard())
+            .await?;
+        Ok(())
+    }
+
+    fn timeout(&self) -> Option<time::Duration> {
+        Some(time::Duration::from_secs(IMPORT_TIMEOUT))
+    }
+
+    async fn handle_event(
+        &self,
+        ctx: &super::Context,
+        event: Event,
+    ) -> StateResult<Box<dyn State>> {
+        if let Event::Button(cmd, query) = event {
+            // the preview is sent on enter, its id comes with the buttons
+            let message_id = query.message.as_ref().map(|msg| msg.id);
+            let state = ImportFile {
+                message_id: message_id.or(self.message_id),
+                ..self.clone()
+            };
+            return match cmd {
+                keyboard::Button::ImportColumn => {
+                    state.change_column(ctx, keyboard::payload(&query)).await
+                }
+                keyboard::Button::ImportPolicy => state.change_policy(ctx).await,
+                keyboard::Button::ImportProgress => state.toggle_progress(ctx).await,
+                keyboard::Button::StartImport => state.import(ctx).await,
+                _ => Err(StateError::UnexpectedCommand(format!(
+                    "Unexpected command {} - {}",
+                    cmd.key(),
+                    cmd.text(),
+                ))),
+            };
+        }
+
+        Ok(self.clone_state())
+    }
+
+    fn clone_state(&self) -> Box<dyn State> {
+        Box::new(self.clone())
+    }
+}
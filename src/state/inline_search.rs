@@ -0,0 +1,126 @@
+use teloxide::{
+    payloads::AnswerInlineQuerySetters,
+    requests::Requester,
+    types::{
+        InlineQuery, InlineQueryResult, InlineQueryResultArticle, InputMessageContent,
+        InputMessageContentText, ParseMode,
+    },
+    utils::markdown,
+};
+
+use rand::seq::SliceRandom;
+
+use crate::{
+    challenges,
+    common::search,
+    storage::{Direction, Word, WordFilter},
+};
+
+use super::{error::StateResult, quiz};
+
+// Telegram shows at most 50 results of an inline query
+static MAX_RESULTS: usize = 50;
+// Words matching the query which are ranked to pick the results from
+static SEARCH_POOL: i64 = 200;
+static CACHE_TIME: u32 = 10; // seconds
+
+/// Answers with a quiz challenge and the user's words containing the query.
+/// While the query is empty the newest words are shown and the challenge
+/// is about a random one of them.
+pub async fn answer(ctx: &super::Context, query: &InlineQuery) -> StateResult<()> {
+    let is_empty = query.query.trim().is_empty();
+    let words = if is_empty {
+        newest_words(ctx).await?
+    } else {
+        let filter = WordFilter {
+            search: Some(query.query.clone()),
+            ..Default::default()
+        };
+        ctx.db
+            .get_words(ctx.chat_id.0, &filter, 0, SEARCH_POOL)
+            .await?
+    };
+    let (found, challenge_word): (Vec<&Word>, Option<&Word>) = if is_empty {
+        (
+            words.iter().rev().collect(),
+            words.choose(&mut rand::thread_rng()),
+        )
+    } else {
+        let found = search::search(&words, &query.query);
+        let best = found.first().copied();
+        (found, best)
+    };
+
+    let mut results: Vec<InlineQueryResult> = vec![];
+    if let Some(word) = challenge_word {
+        results.extend(challenge(ctx, word).await?);
+    }
+    results.extend(
+        found
+            .into_iter()
+            .take(MAX_RESULTS - results.len())
+            .map(article),
+    );
+
+    let nothing_found = results.is_empty();
+    let mut request = ctx
+        .bot
+        .answer_inline_query(query.id.clone(), results)
+        .is_personal(true)
+        .cache_time(CACHE_TIME);
+    if nothing_found {
+        request = request
+            .switch_pm_text("No words found, add them in the bot")
+            .switch_pm_parameter("start");
+    }
+    request.await?;
+    Ok(())
+}
+
+async fn newest_words(ctx: &super::Context) -> StateResult<Vec<Word>> {
+    let filter = WordFilter::default();
+    let count = ctx.db.words_count(ctx.chat_id.0, &filter).await?;
+    let skip = (count - MAX_RESULTS as i64).max(0);
+    Ok(ctx
+        .db
+        .get_words(ctx.chat_id.0, &filter, skip, MAX_RESULTS as i64)
+        .await?)
+}
+
+// a quiz about the word, `None` if the user doesn't have enough words for it
+async fn challenge(ctx: &super::Context, word: &Word) -> StateResult<Option<InlineQueryResult>> {
+    let Some(quiz) = quiz::options(ctx, word, Direction::Forward).await? else {
+        return Ok(None);
+    };
+    let text = challenges::text(&word.word, &[]);
+    let keyboard = challenges::keyboard(&quiz.options);
+    let id = ctx.challenges.offer(ctx.chat_id.0, &word.word, quiz).await;
+    let result = InlineQueryResultArticle::new(
+        format!("challenge.{}", id),
+        format!("Quiz: {}", word.word),
+        InputMessageContent::Text(
+            InputMessageContentText::new(text).parse_mode(ParseMode::MarkdownV2),
+        ),
+    )
+    .description("Send a quiz about the word, everyone in the chat can answer")
+    .reply_markup(keyboard);
+    Ok(Some(result.into()))
+}
+
+// the word card which is sent to the chat
+fn article(word: &Word) -> InlineQueryResult {
+    let text = format!(
+        "*{}* \\- {}",
+        markdown::escape(&word.word),
+        word.translations.to_formatted_string()
+    );
+    InlineQueryResultArticle::new(
+        word.id.clone(),
+        word.word.clone(),
+        InputMessageContent::Text(
+            InputMessageContentText::new(text).parse_mode(ParseMode::MarkdownV2),
+        ),
+    )
+    .description(word.translations.to_plain_string())
+    .into()
+}
@@ -0,0 +1,215 @@
+use rand::seq::SliceRandom;
+
+use crate::{
+    common::normalize,
+    storage::{Direction, Word, WordFilter},
+};
+
+use super::error::StateResult;
+
+pub static QUIZ_OPTIONS: usize = 4;
+
+// Telegram limits length of poll options
+static OPTION_LENGTH: usize = 100;
+
+// Amount of words loaded to choose distractors from
+static DISTRACTORS_POOL: i64 = 200;
+
+#[derive(Debug, Clone)]
+pub struct QuizOptions {
+    pub options: Vec<String>,
+    /// Index of the right option
+    pub correct: usize,
+}
+
+/// Text of the word shown as an option for the direction
+pub fn option_text(word: &Word, direction: Direction) -> Option<String> {
+    match direction {
+        Direction::Forward => word.translations.first().map(|t| truncate(&t.text)),
+        Direction::Reverse => Some(truncate(&word.word)),
+    }
+}
+
+// Options are compared after truncation, otherwise a poll could get two equal options
+fn truncate(text: &str) -> String {
+    text.chars().take(OPTION_LENGTH).collect()
+}
+
+// Texts which are right answers for the direction, none of them can be a distractor
+fn right_answers(word: &Word, direction: Direction) -> Vec<String> {
+    match direction {
+        Direction::Forward => word.translations.texts().map(truncate).collect(),
+        Direction::Reverse => vec![truncate(&word.word)],
+    }
+}
+
+/// Options for a quiz about the word: its own text and distractors from other
+/// words of the user, words of the same deck and with a close level go first.
+/// Returns `None` if the user doesn't have enough words for `QUIZ_OPTIONS` options.
+pub async fn options(
+    ctx: &super::Context,
+    word: &Word,
+    direction: Direction,
+) -> StateResult<Option<QuizOptions>> {
+    let Some(correct) = option_text(word, direction) else {
+        return Ok(None);
+    };
+
+    let same_deck = WordFilter {
+        deck_id: word.deck_id.clone(),
+        ..Default::default()
+    };
+    let mut candidates = ctx
+        .db
+        .get_words(word.chat_id, &same_deck, 0, DISTRACTORS_POOL)
+        .await?;
+    if word.deck_id.is_some() && candidates.len() <= QUIZ_OPTIONS {
+        let all_words = ctx
+            .db
+            .get_words(word.chat_id, &WordFilter::default(), 0, DISTRACTORS_POOL)
+            .await?;
+        candidates.extend(all_words);
+    }
+
+    candidates.shuffle(&mut rand::thread_rng());
+    let level = word.level(direction);
+    candidates.sort_by_key(|candidate| {
+        (
+            candidate.deck_id != word.deck_id,
+            (candidate.level(direction) - level).abs(),
+        )
+    });
+
+    let mut keys: Vec<String> = right_answers(word, direction)
+        .iter()
+        .map(|text| normalize::key(text))
+        .collect();
+    let mut options = vec![correct];
+    for candidate in candidates.iter().filter(|c| c.id != word.id) {
+        if options.len() == QUIZ_OPTIONS {
+            break;
+        }
+        if let Some(text) = option_text(candidate, direction) {
+            let key = normalize::key(&text);
+            if !keys.contains(&key) {
+                keys.push(key);
+                options.push(text);
+            }
+        }
+    }
+    if options.len() < QUIZ_OPTIONS {
+        return Ok(None);
+    }
+
+    let correct_text = options[0].clone();
+    options.shuffle(&mut rand::thread_rng());
+    let correct = options.iter().position(|o| *o == correct_text).unwrap_or(0);
+    Ok(Some(QuizOptions { options, correct }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        storage::{memory::MemoryStorage, Storage},
+        testing::{context, CHAT_ID},
+    };
+
+    use super::*;
+
+    async fn add(db: &MemoryStorage, words: &[(&str, &str)]) -> Vec<Word> {
+        let mut added = vec![];
+        for (word, translation) in words {
+            let word = db
+                .new_word(CHAT_ID, word, &(*translation).into(), None)
+                .await
+                .unwrap();
+            added.push(word);
+        }
+        added
+    }
+
+    #[tokio::test]
+    async fn other_translations_are_not_distractors() {
+        let db = Arc::new(MemoryStorage::new());
+        let words = add(
+            &db,
+            &[
+                ("Hund", "dog, hound"),
+                ("Jagdhund", "Hound"),
+                ("Katze", "cat"),
+                ("Maus", "mouse"),
+                ("Pferd", "horse"),
+            ],
+        )
+        .await;
+        let ctx = context(db);
+
+        for _ in 0..10 {
+            let quiz = options(&ctx, &words[0], Direction::Forward)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(quiz.options.len(), QUIZ_OPTIONS);
+            assert_eq!(quiz.options[quiz.correct], "dog");
+            assert!(!quiz.options.iter().any(|o| normalize::key(o) == "hound"));
+        }
+    }
+
+    #[tokio::test]
+    async fn not_enough_words_for_quiz() {
+        let db = Arc::new(MemoryStorage::new());
+        let words = add(
+            &db,
+            &[
+                ("Hund", "dog"),
+                ("Katze", "cat"),
+                ("Kater", "Cat"),
+                ("Maus", "mouse"),
+            ],
+        )
+        .await;
+        let ctx = context(db);
+
+        assert!(options(&ctx, &words[0], Direction::Forward)
+            .await
+            .unwrap()
+            .is_none());
+        let quiz = options(&ctx, &words[0], Direction::Reverse)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(quiz.options[quiz.correct], "Hund");
+    }
+
+    #[tokio::test]
+    async fn options_are_unique_after_truncation() {
+        let db = Arc::new(MemoryStorage::new());
+        let long = "a".repeat(OPTION_LENGTH);
+        let words = add(
+            &db,
+            &[
+                ("Hund", "dog"),
+                ("eins", &format!("{}b", long)),
+                ("zwei", &format!("{}c", long)),
+                ("Katze", "cat"),
+                ("Maus", "mouse"),
+            ],
+        )
+        .await;
+        let ctx = context(db);
+
+        for _ in 0..10 {
+            let quiz = options(&ctx, &words[0], Direction::Forward)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(quiz.options.iter().filter(|o| **o == long).count(), 1);
+            assert!(quiz
+                .options
+                .iter()
+                .all(|o| o.chars().count() <= OPTION_LENGTH));
+        }
+    }
+}
@@ -1,165 +1,598 @@
 use core::time;
-use std::cmp;
 
 use async_trait::async_trait;
-use chrono::{DateTime, FixedOffset, Utc};
+use chrono::{DateTime, FixedOffset};
+use rand::seq::SliceRandom;
 use teloxide::{
-    payloads::SendMessageSetters,
+    payloads::{EditMessageTextSetters, SendMessageSetters, SendPollSetters},
     requests::Requester,
-    types::{ChatId, Message},
+    types::{ChatId, InlineKeyboardMarkup, Message, MessageId, PollAnswer, PollType},
+    utils::markdown,
 };
 
 use crate::{
-    common::{config::TIMINGS, translation::Translation},
+    common::{
+        config::{DAY, MASTERED_INTERVAL, POLL_REMIND_TIMEOUT, REMIND_TIMEOUT},
+        translation::{AnswerCheck, Translation, TranslationItem},
+    },
     keyboard,
-    prisma::{self},
+    scheduler::{self, Grade, Progress},
     state::idle,
-    storage::LiteUser,
+    storage::{
+        self, Card, Direction, DueCard, LiteUser, NewReview, ReviewMode, ReviewOutcome, Word,
+        WordStatus,
+    },
 };
 
 use super::{
     error::{StateError, StateResult},
     events::Event,
+    quiz::{self, QuizOptions},
     State,
 };
 
+// Telegram limits length of poll questions
+static POLL_QUESTION_LENGTH: usize = 300;
+
+/// What happened after the user's action on a remind
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// The answer is still expected
+    Continue(Box<Remind>),
+    /// The review is saved
+    Done(ReviewOutcome),
+    /// The remind ended without a review
+    Skipped,
+}
+
 #[derive(Debug, Clone)]
 pub struct Remind {
-    word: prisma::word::Data,
+    word: Word,
+    card: Card,
+    /// Formatted word or translation the user is asked about
+    question: String,
+    /// Plain text of the word or translation, polls don't support markdown
+    prompt: String,
+    /// Accepted answers, translations or the word itself in reverse direction
+    answers: Translation,
     user: LiteUser,
+    /// The user's review mode unless the quiz can't be made
+    mode: ReviewMode,
+    quiz: Option<QuizOptions>,
+    /// Id and message of the sent quiz poll
+    poll: Option<(String, MessageId)>,
+    asked_at: DateTime<FixedOffset>,
+    /// The translation is shown and a self-grade is expected
+    revealed: bool,
+    /// Number of the word and words count in a review session
+    position: Option<(usize, usize)>,
 }
 
 impl Remind {
-    pub fn new(word: prisma::word::Data, user: LiteUser) -> Remind {
-        Remind { word, user }
+    pub fn new(DueCard { word, card }: DueCard, user: LiteUser) -> Remind {
+        let translation = word.translations.choose(&mut rand::thread_rng()).cloned();
+        let (question, prompt, answers) = match (card.direction, translation) {
+            (Direction::Reverse, Some(translation)) => (
+                translation.to_formatted_string(),
+                translation.text,
+                Translation::new(vec![TranslationItem::new(word.word.clone(), None)]),
+            ),
+            _ => (
+                format!("`{}`", markdown::escape_code(&word.word)),
+                word.word.clone(),
+                word.translations.clone(),
+            ),
+        };
+        Remind {
+            word,
+            card,
+            question,
+            prompt,
+            answers,
+            mode: user.review_mode,
+            quiz: None,
+            poll: None,
+            user,
+            asked_at: storage::now(),
+            revealed: false,
+            position: None,
+        }
+    }
+
+    /// Loads quiz options if needed, falls back to typing without enough words.
+    /// A poll is sent right away, its id is needed to recognize the answer
+    pub async fn prepare(
+        ctx: &super::Context,
+        card: DueCard,
+        user: LiteUser,
+        position: Option<(usize, usize)>,
+    ) -> StateResult<Remind> {
+        let mut remind = Remind {
+            position,
+            ..Remind::new(card, user)
+        };
+        if matches!(remind.mode, ReviewMode::Quiz | ReviewMode::Poll) {
+            remind.quiz = quiz::options(ctx, &remind.word, remind.card.direction).await?;
+            if remind.quiz.is_none() {
+                remind.mode = ReviewMode::Typing;
+            }
+        }
+        if let (ReviewMode::Poll, Some(quiz)) = (remind.mode, &remind.quiz) {
+            remind.poll = Some(remind.send_poll(ctx, quiz).await?);
+        }
+        Ok(remind)
+    }
+
+    async fn send_poll(
+        &self,
+        ctx: &super::Context,
+        quiz: &QuizOptions,
+    ) -> StateResult<(String, MessageId)> {
+        let question = match self.card.direction {
+            Direction::Forward => format!("Choose translation for the word \"{}\"", self.prompt),
+            Direction::Reverse => {
+                format!("Choose the word for the translation \"{}\"", self.prompt)
+            }
+        };
+        let question = match self.position {
+            Some((number, count)) => format!("{}/{} {}", number, count, question),
+            None => question,
+        };
+        let question: String = question.chars().take(POLL_QUESTION_LENGTH).collect();
+        // options are already truncated by the quiz
+        let msg = ctx
+            .bot
+            .send_poll(ChatId(self.word.chat_id), question, quiz.options.clone())
+            .type_(PollType::Quiz)
+            .correct_option_id(quiz.correct as u8)
+            .is_anonymous(false)
+            .disable_notification(self.user.silent)
+            .await?;
+        let poll_id = msg
+            .poll()
+            .map(|poll| poll.id.clone())
+            .ok_or(StateError::ExpectedPollInsideMessage)?;
+        Ok((poll_id, msg.id))
+    }
+
+    // "<option index>.<word id>", the word id tells apart options of older quizzes
+    fn quiz_keyboard(&self, quiz: &QuizOptions) -> InlineKeyboardMarkup {
+        let buttons = quiz
+            .options
+            .iter()
+            .enumerate()
+            .map(|(index, option)| {
+                vec![keyboard::Button::QuizOption
+                    .to_inline_button_with_payload(option, &format!("{}.{}", index, self.word.id))]
+            })
+            .collect::<Vec<_>>();
+        InlineKeyboardMarkup::new(buttons)
+    }
+
+    async fn handle_quiz_answer(
+        &self,
+        ctx: &super::Context,
+        message_id: MessageId,
+        payload: Option<&str>,
+    ) -> StateResult<Step> {
+        let chosen = payload
+            .and_then(|payload| payload.split_once('.'))
+            .filter(|(_, word_id)| *word_id == self.word.id)
+            .and_then(|(index, _)| index.parse::<usize>().ok());
+        let (Some(quiz), Some(chosen)) = (&self.quiz, chosen) else {
+            log::warn!("Quiz option of another quiz: {:?}", payload);
+            return Ok(Step::Continue(Box::new(self.clone())));
+        };
+
+        let (text, outcome) = self.grade_quiz_choice(ctx, quiz, chosen).await?;
+        ctx.bot
+            .edit_message_text(
+                ChatId(self.word.chat_id),
+                message_id,
+                format!("{}\n\n{}", self.question, text),
+            )
+            .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+            .await?;
+        Ok(Step::Done(outcome))
+    }
+
+    async fn handle_poll_answer(
+        &self,
+        ctx: &super::Context,
+        answer: PollAnswer,
+    ) -> StateResult<Step> {
+        let chosen = answer.option_ids.first().map(|id| *id as usize);
+        let (Some(quiz), Some(chosen)) = (&self.quiz, chosen) else {
+            return Ok(Step::Continue(Box::new(self.clone())));
+        };
+
+        let (text, outcome) = self.grade_quiz_choice(ctx, quiz, chosen).await?;
+        ctx.bot
+            .send_message(ChatId(self.word.chat_id), text)
+            .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+            .await?;
+        Ok(Step::Done(outcome))
+    }
+
+    /// Saves the review of the chosen quiz option and returns the result text
+    async fn grade_quiz_choice(
+        &self,
+        ctx: &super::Context,
+        quiz: &QuizOptions,
+        chosen: usize,
+    ) -> StateResult<(String, ReviewOutcome)> {
+        let answer = quiz.options.get(chosen).cloned();
+        if chosen == quiz.correct {
+            let (_, status) = self
+                .update_next_reminds(ctx, Grade::Good, ReviewOutcome::Correct, answer)
+                .await?;
+            let text = format!(
+                "🎉 Correct\\! {}{}",
+                self.answer_text(),
+                self.mastered_text(status)
+            );
+            Ok((text, ReviewOutcome::Correct))
+        } else {
+            self.handle_incorrect_answer(ctx, ReviewOutcome::Wrong, answer)
+                .await?;
+            Ok((
+                format!("😔 Wrong\\! {}", self.answer_text()),
+                ReviewOutcome::Wrong,
+            ))
+        }
+    }
+
+    // "<word id>.<direction>", grades of older reminds are told apart by it
+    fn card_payload(&self) -> String {
+        format!("{}.{}", self.word.id, self.card.direction.key())
+    }
+
+    fn grade(button: keyboard::Button) -> Option<(Grade, ReviewOutcome)> {
+        match button {
+            keyboard::Button::Again => Some((Grade::Again, ReviewOutcome::Again)),
+            keyboard::Button::Hard => Some((Grade::Hard, ReviewOutcome::Hard)),
+            keyboard::Button::Good => Some((Grade::Good, ReviewOutcome::Good)),
+            keyboard::Button::Easy => Some((Grade::Easy, ReviewOutcome::Easy)),
+            _ => None,
+        }
+    }
+
+    fn answer_text(&self) -> String {
+        let subject = if self.card.direction == Direction::Reverse {
+            "The word"
+        } else {
+            "The translation"
+        };
+        format!("{} is {}", subject, self.answers.to_formatted_string())
+    }
+
+    fn mastered_text(&self, status: WordStatus) -> &'static str {
+        if status == WordStatus::Mastered && self.word.status != WordStatus::Mastered {
+            "\n\n🎊 You have mastered the word\\! It's moved to the archive"
+        } else {
+            ""
+        }
+    }
+
+    // the word is mastered when the cards of all its reviewed directions are
+    async fn is_mastered(&self, ctx: &super::Context, progress: &Progress) -> StateResult<bool> {
+        if progress.interval < MASTERED_INTERVAL {
+            return Ok(false);
+        }
+        let deck_directions = match &self.word.deck_id {
+            Some(deck_id) => ctx
+                .db
+                .get_decks(self.user.chat_id)
+                .await?
+                .into_iter()
+                .find(|deck| &deck.id == deck_id)
+                .and_then(|deck| deck.directions),
+            None => None,
+        };
+        let directions = deck_directions.unwrap_or(self.user.directions);
+        Ok(self
+            .word
+            .cards
+            .iter()
+            .filter(|card| card.direction != self.card.direction)
+            .filter(|card| directions.includes(card.direction))
+            .all(|card| card.progress.interval >= MASTERED_INTERVAL))
     }
 
     async fn update_next_reminds(
         &self,
         ctx: &super::Context,
-        next_remind_at: DateTime<FixedOffset>,
-        level: i32,
-    ) -> StateResult<()> {
+        grade: Grade,
+        outcome: ReviewOutcome,
+        answer: Option<String>,
+    ) -> StateResult<(Progress, WordStatus)> {
+        let scheduler = scheduler::get(&self.user.scheduler);
+        let elapsed = scheduler::elapsed_days(self.card.reminded_at);
+        let progress = scheduler.next(&self.card.progress, grade, elapsed);
+        log::debug!(
+            "Word {} ({}) rescheduled by {}: {:?}",
+            self.word.id,
+            self.card.direction.key(),
+            scheduler.name(),
+            progress
+        );
+
+        // a failed refresher review brings a mastered word back to learning
+        let status = if !grade.is_success() {
+            WordStatus::Active
+        } else if self.is_mastered(ctx, &progress).await? {
+            WordStatus::Mastered
+        } else {
+            self.word.status
+        };
         ctx.db
-            .update_word_remind(self.word.id.clone(), next_remind_at, level)
+            .update_word_remind(&self.word.id, self.card.direction, &progress, status)
+            .await?;
+        let latency = (storage::now() - self.asked_at).num_milliseconds();
+        ctx.db
+            .add_review(NewReview {
+                word_id: self.word.id.clone(),
+                chat_id: self.user.chat_id,
+                outcome,
+                direction: self.card.direction,
+                level_before: self.card.progress.level,
+                level_after: progress.level,
+                answer,
+                latency: i32::try_from(latency).ok(),
+            })
             .await?;
         ctx.db
             .update_next_remind_user(self.user.chat_id, self.user.remind_every as i64)
             .await?;
-        Ok(())
+        ctx.reschedule().await?;
+        Ok((progress, status))
     }
 
     async fn handle_correct_answer(
         &self,
         ctx: &super::Context,
         msg: &Message,
-        translation: &Translation,
-    ) -> StateResult<Box<dyn State>> {
-        let level = self.word.remember_level + 1;
-
-        if level >= TIMINGS.len() as i32 {
-            ctx.db.remove_word_by_id(self.word.id.clone()).await?;
-            ctx.bot
-                .send_message(msg.chat.id, "🎉 Success! You have remembered the word! 🎊")
-                .await?;
-            return Ok(Box::new(idle::Idle::new()));
-        }
+        answer: &str,
+    ) -> StateResult<Step> {
+        let (_, status) = self
+            .update_next_reminds(
+                ctx,
+                Grade::Good,
+                ReviewOutcome::Correct,
+                Some(answer.to_owned()),
+            )
+            .await?;
+        let answer = format!(
+            "🎉 Correct\\! {}{}",
+            self.answer_text(),
+            self.mastered_text(status)
+        );
+        ctx.bot
+            .send_message(msg.chat.id, answer)
+            .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+            .await?;
+        Ok(Step::Done(ReviewOutcome::Correct))
+    }
 
-        let next_remind_at = calc_next_remind(level)?;
-        self.update_next_reminds(ctx, next_remind_at, level).await?;
+    async fn handle_almost_correct_answer(
+        &self,
+        ctx: &super::Context,
+        msg: &Message,
+        expected: &str,
+        answer: &str,
+    ) -> StateResult<Step> {
+        // a typo is not a failure but the word is harder than it seemed
+        let (_, status) = self
+            .update_next_reminds(
+                ctx,
+                Grade::Hard,
+                ReviewOutcome::AlmostCorrect,
+                Some(answer.to_owned()),
+            )
+            .await?;
         let answer = format!(
-            "🎉 Correct\\! The translation is {}",
-            translation.to_formatted_string()
+            "👌 Almost\\! It's spelled `{}`\\. {}{}",
+            markdown::escape_code(expected),
+            self.answer_text(),
+            self.mastered_text(status)
         );
         ctx.bot
             .send_message(msg.chat.id, answer)
             .parse_mode(teloxide::types::ParseMode::MarkdownV2)
             .await?;
-        Ok(Box::new(idle::Idle::new()))
+        Ok(Step::Done(ReviewOutcome::AlmostCorrect))
     }
 
-    async fn handle_incorrect_answer(&self, ctx: &super::Context) -> StateResult<Box<dyn State>> {
-        let next_remind_at = calc_next_remind(0)?;
-        let level = cmp::max(self.word.remember_level - 1, 0);
-        self.update_next_reminds(ctx, next_remind_at, level).await?;
-        Ok(Box::new(idle::Idle::new()))
+    async fn handle_incorrect_answer(
+        &self,
+        ctx: &super::Context,
+        outcome: ReviewOutcome,
+        answer: Option<String>,
+    ) -> StateResult<()> {
+        self.update_next_reminds(ctx, Grade::Again, outcome, answer)
+            .await?;
+        Ok(())
     }
-}
 
-#[async_trait]
-impl State for Remind {
-    async fn on_enter(&self, ctx: &super::Context, _: Option<Box<dyn State>>) -> StateResult<()> {
+    async fn show_answer(&self, ctx: &super::Context, message_id: MessageId) -> StateResult<Step> {
         ctx.bot
-            .send_message(
+            .edit_message_text(
                 ChatId(self.word.chat_id),
-                format!("Write translation for the word `{}`", self.word.word),
+                message_id,
+                format!(
+                    "{} \\- {}\n\nHow well did you remember it?",
+                    self.question,
+                    self.answers.to_formatted_string()
+                ),
             )
             .parse_mode(teloxide::types::ParseMode::MarkdownV2)
-            .reply_markup(keyboard::Button::Forgot.to_keyboard())
+            .reply_markup(keyboard::grades(&self.card_payload()))
             .await?;
-        Ok(())
-    }
-
-    fn timeout(&self) -> Option<time::Duration> {
-        // Some(time::Duration::from_secs(60 * 2)) // 2 minutes
-        Some(time::Duration::from_secs(12))
+        Ok(Step::Continue(Box::new(Remind {
+            revealed: true,
+            ..self.clone()
+        })))
     }
 
-    async fn handle_message(
+    async fn handle_self_grade(
         &self,
         ctx: &super::Context,
-        msg: Message,
-    ) -> StateResult<Box<dyn State>> {
+        message_id: MessageId,
+        grade: Grade,
+        outcome: ReviewOutcome,
+    ) -> StateResult<Step> {
+        let (progress, status) = self.update_next_reminds(ctx, grade, outcome, None).await?;
+        let answer = format!(
+            "{} \\- {}\n\n{}\\! I will remind in `{:?}`{}",
+            self.question,
+            self.answers.to_formatted_string(),
+            outcome.text(),
+            time::Duration::from_secs((progress.interval * DAY as f64) as u64),
+            self.mastered_text(status)
+        );
+        ctx.bot
+            .edit_message_text(ChatId(self.word.chat_id), message_id, answer)
+            .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+            .await?;
+        Ok(Step::Done(outcome))
+    }
+
+    // "3/12" in front of questions of a review session
+    fn position_text(&self) -> String {
+        self.position
+            .map(|(number, count)| format!("*{}/{}* ", number, count))
+            .unwrap_or_default()
+    }
+
+    /// Sends the question unless it's a poll which is sent while preparing
+    pub async fn ask(&self, ctx: &super::Context) -> StateResult<()> {
+        if self.poll.is_some() {
+            return Ok(());
+        }
+        if let (ReviewMode::Quiz, Some(quiz)) = (self.mode, &self.quiz) {
+            let text = match self.card.direction {
+                Direction::Forward => format!("Choose translation for the word {}", self.question),
+                Direction::Reverse => {
+                    format!("Choose the word for the translation {}", self.question)
+                }
+            };
+            ctx.bot
+                .send_message(ChatId(self.word.chat_id), self.position_text() + &text)
+                .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+                .reply_markup(self.quiz_keyboard(quiz))
+                .disable_notification(self.user.silent)
+                .await?;
+            return Ok(());
+        }
+
+        let (text, button) = match (self.mode, self.card.direction) {
+            (ReviewMode::Typing | ReviewMode::Quiz | ReviewMode::Poll, Direction::Forward) => (
+                format!("Write translation for the word {}", self.question),
+                keyboard::Button::Forgot,
+            ),
+            (ReviewMode::Typing | ReviewMode::Quiz | ReviewMode::Poll, Direction::Reverse) => (
+                format!("Write the word for the translation {}", self.question),
+                keyboard::Button::Forgot,
+            ),
+            (ReviewMode::SelfGraded, Direction::Forward) => (
+                format!("Do you remember the word {}?", self.question),
+                keyboard::Button::ShowAnswer,
+            ),
+            (ReviewMode::SelfGraded, Direction::Reverse) => (
+                format!("Do you remember the word for {}?", self.question),
+                keyboard::Button::ShowAnswer,
+            ),
+        };
+        ctx.bot
+            .send_message(ChatId(self.word.chat_id), self.position_text() + &text)
+            .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+            .reply_markup(button.to_keyboard())
+            .disable_notification(self.user.silent)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn answer(&self, ctx: &super::Context, msg: Message) -> StateResult<Step> {
+        if self.revealed {
+            ctx.bot
+                .send_message(msg.chat.id, "Grade how well you remembered the word")
+                .reply_markup(keyboard::grades(&self.card_payload()))
+                .await?;
+            return Ok(Step::Continue(Box::new(self.clone())));
+        }
+
         if let Some(text) = msg.text() {
             let translation_request = text.to_owned();
-            let translation = Translation::new(&self.word.translate);
-            if translation.check(&translation_request) {
-                self.handle_correct_answer(ctx, &msg, &translation).await
-            } else {
-                self.handle_incorrect_answer(ctx).await?;
-                let answer = format!(
-                    "😔 Wrong\\! The translation is {}",
-                    translation.to_formatted_string()
-                );
-                ctx.bot
-                    .send_message(msg.chat.id, answer)
-                    .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+            match self
+                .answers
+                .check(&translation_request, self.user.strictness)
+            {
+                AnswerCheck::Correct => self.handle_correct_answer(ctx, &msg, text).await,
+                AnswerCheck::AlmostCorrect(expected) => {
+                    self.handle_almost_correct_answer(ctx, &msg, &expected, text)
+                        .await
+                }
+                AnswerCheck::Wrong => {
+                    self.handle_incorrect_answer(
+                        ctx,
+                        ReviewOutcome::Wrong,
+                        Some(translation_request),
+                    )
                     .await?;
-                Ok(Box::new(idle::Idle::new()))
+                    let answer = format!("😔 Wrong\\! {}", self.answer_text());
+                    ctx.bot
+                        .send_message(msg.chat.id, answer)
+                        .parse_mode(teloxide::types::ParseMode::MarkdownV2)
+                        .await?;
+                    Ok(Step::Done(ReviewOutcome::Wrong))
+                }
             }
         } else {
             log::error!("Unexpected message without text: {:?}", msg);
             ctx.bot
                 .send_message(msg.chat.id, "Unexpected message")
                 .await?;
-            Ok(Box::new(idle::Idle::new()))
+            Ok(Step::Skipped)
         }
     }
 
-    async fn handle_event(
+    pub async fn handle_remind_event(
         &self,
         ctx: &super::Context,
         event: Event,
-    ) -> StateResult<Box<dyn State>> {
+    ) -> StateResult<Step> {
         if let Event::Button(cmd, query) = event {
+            let payload = keyboard::payload(&query).map(str::to_owned);
             let msg = query
                 .message
                 .ok_or(StateError::ExpectedMessageInsideCallbackQuery)?;
+            if let Some((grade, outcome)) = Remind::grade(cmd) {
+                if !self.revealed || payload != Some(self.card_payload()) {
+                    log::warn!("Grade of another remind: {:?}", payload);
+                    return Ok(Step::Continue(Box::new(self.clone())));
+                }
+                return self.handle_self_grade(ctx, msg.id, grade, outcome).await;
+            }
             return match cmd {
+                keyboard::Button::QuizOption => {
+                    self.handle_quiz_answer(ctx, msg.id, payload.as_deref())
+                        .await
+                }
+                keyboard::Button::ShowAnswer => self.show_answer(ctx, msg.id).await,
                 keyboard::Button::Forgot => {
-                    let translation = Translation::new(&self.word.translate);
-                    self.handle_incorrect_answer(ctx).await?;
+                    self.handle_incorrect_answer(ctx, ReviewOutcome::Forgot, None)
+                        .await?;
                     let answer = format!(
-                        "😔 Oh\\! `{}` translation is {}",
-                        self.word.word,
-                        translation.to_formatted_string()
+                        "😔 Oh\\! {} \\- {}",
+                        self.question,
+                        self.answers.to_formatted_string()
                     );
                     ctx.bot.delete_message(msg.chat.id, msg.id).await?;
                     ctx.bot
                         .send_message(msg.chat.id, answer)
                         .parse_mode(teloxide::types::ParseMode::MarkdownV2)
                         .await?;
-                    Ok(Box::new(idle::Idle::new()))
+                    Ok(Step::Done(ReviewOutcome::Forgot))
                 }
                 _ => Err(StateError::UnexpectedCommand(format!(
                     "Unexpected command {} - {}",
@@ -168,23 +601,82 @@ impl State for Remind {
                 ))),
             };
         }
+        if let Event::PollAnswer(answer) = event {
+            if self.poll.as_ref().map(|(id, _)| id) == Some(&answer.poll_id) {
+                return self.handle_poll_answer(ctx, answer).await;
+            }
+            log::warn!("Answer to another poll: {}", answer.poll_id);
+        }
 
-        Ok(self.clone_state())
+        Ok(Step::Continue(Box::new(self.clone())))
     }
 
-    async fn handle_timeout(&self, ctx: &super::Context) -> StateResult<Box<dyn State>> {
-        let now = Utc::now();
-        let next_remind_at = calc_next_remind(0)?;
-        self.update_next_reminds(ctx, next_remind_at, 0).await?;
+    /// Stops waiting for the answer, the word is reminded again soon
+    pub async fn expire(&self, ctx: &super::Context) -> StateResult<Step> {
+        if let Some((_, message_id)) = &self.poll {
+            if let Err(err) = ctx
+                .bot
+                .stop_poll(ChatId(self.word.chat_id), *message_id)
+                .await
+            {
+                log::warn!("Failed to stop the poll: {}", err);
+            }
+        }
+        let (progress, _) = self
+            .update_next_reminds(ctx, Grade::Again, ReviewOutcome::Timeout, None)
+            .await?;
         let answer = format!(
             "Ok\\, I will remind in `{:?}`",
-            time::Duration::from_secs((next_remind_at.time() - now.time()).num_seconds() as u64)
+            time::Duration::from_secs((progress.interval * DAY as f64) as u64)
         );
         ctx.bot
             .send_message(ChatId(self.user.chat_id), answer)
             .parse_mode(teloxide::types::ParseMode::MarkdownV2)
             .await?;
-        Ok(Box::new(idle::Idle::new()))
+        Ok(Step::Done(ReviewOutcome::Timeout))
+    }
+}
+
+// a single remind ends in idle, a review session continues with the next word
+fn after(step: Step) -> Box<dyn State> {
+    match step {
+        Step::Continue(remind) => remind,
+        Step::Done(_) | Step::Skipped => Box::new(idle::Idle::new()),
+    }
+}
+
+#[async_trait]
+impl State for Remind {
+    async fn on_enter(&self, ctx: &super::Context, _: Option<Box<dyn State>>) -> StateResult<()> {
+        self.ask(ctx).await
+    }
+
+    fn timeout(&self) -> Option<time::Duration> {
+        let timeout = match self.mode {
+            ReviewMode::Poll => POLL_REMIND_TIMEOUT,
+            _ => REMIND_TIMEOUT,
+        };
+        Some(time::Duration::from_secs(timeout))
+    }
+
+    async fn handle_message(
+        &self,
+        ctx: &super::Context,
+        msg: Message,
+    ) -> StateResult<Box<dyn State>> {
+        self.answer(ctx, msg).await.map(after)
+    }
+
+    async fn handle_event(
+        &self,
+        ctx: &super::Context,
+        event: Event,
+    ) -> StateResult<Box<dyn State>> {
+        self.handle_remind_event(ctx, event).await.map(after)
+    }
+
+    async fn handle_timeout(&self, ctx: &super::Context) -> StateResult<Box<dyn State>> {
+        self.expire(ctx).await.map(after)
     }
 
     fn clone_state(&self) -> Box<dyn State> {
@@ -192,12 +684,162 @@ impl State for Remind {
     }
 }
 
-fn calc_next_remind(level: i32) -> StateResult<DateTime<FixedOffset>> {
-    let now = Utc::now();
-    let level_timing = TIMINGS
-        .get(&level)
-        .ok_or(StateError::IncorrectWordLevel(level))?;
-    let next_remind = now + chrono::Duration::seconds(level_timing.to_owned());
-    let next_remind = next_remind.with_timezone(&FixedOffset::east_opt(0).unwrap());
-    Ok(next_remind)
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        state::Context,
+        storage::{memory::MemoryStorage, Storage},
+        testing::{callback_query, context_with_bot, message, FakeTelegram, CHAT_ID},
+    };
+
+    use super::*;
+
+    struct Setup {
+        telegram: FakeTelegram,
+        db: Arc<MemoryStorage>,
+        ctx: Context,
+        remind: Remind,
+    }
+
+    // "Hund" is due in the forward direction, other words are distractors
+    async fn setup(mode: ReviewMode) -> Setup {
+        let telegram = FakeTelegram::start().await;
+        let db = Arc::new(MemoryStorage::new());
+        for (word, translation) in [
+            ("Hund", "dog"),
+            ("Katze", "cat"),
+            ("Maus", "mouse"),
+            ("Pferd", "horse"),
+        ] {
+            db.new_word(CHAT_ID, word, &translation.into(), None)
+                .await
+                .unwrap();
+        }
+        let hund = db.find_word(CHAT_ID, "Hund").await.unwrap().unwrap();
+        db.update_word_remind(
+            &hund.id,
+            Direction::Forward,
+            &Progress::default(),
+            hund.status,
+        )
+        .await
+        .unwrap();
+        db.set_review_mode(CHAT_ID, mode).await.unwrap();
+
+        let ctx = context_with_bot(db.clone(), telegram.bot());
+        let user = db.ensure_user(CHAT_ID).await.unwrap();
+        let card = db.find_due_cards(CHAT_ID).await.unwrap().remove(0);
+        let remind = Remind::prepare(&ctx, card, user, None).await.unwrap();
+        remind.on_enter(&ctx, None).await.unwrap();
+        telegram.texts();
+        Setup {
+            telegram,
+            db,
+            ctx,
+            remind,
+        }
+    }
+
+    fn button(button: keyboard::Button, payload: &str) -> Event {
+        let data = format!("{}:{}", button.key(), payload);
+        Event::Button(button, callback_query(&data))
+    }
+
+    async fn outcomes(setup: &Setup) -> Vec<ReviewOutcome> {
+        setup
+            .db
+            .get_word_reviews(&setup.remind.word.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|review| review.outcome)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn typed_answers_are_checked() {
+        let setup = setup(ReviewMode::Typing).await;
+        let state = setup
+            .remind
+            .handle_message(&setup.ctx, message("Dog"))
+            .await
+            .unwrap();
+        assert_eq!(state.name(), idle::Idle::new().name());
+        assert_eq!(outcomes(&setup).await, [ReviewOutcome::Correct]);
+        assert!(setup.telegram.texts()[0].starts_with("🎉 Correct"));
+
+        let state = setup
+            .remind
+            .handle_message(&setup.ctx, message("cat"))
+            .await
+            .unwrap();
+        assert_eq!(state.name(), idle::Idle::new().name());
+        assert_eq!(
+            outcomes(&setup).await,
+            [ReviewOutcome::Correct, ReviewOutcome::Wrong]
+        );
+    }
+
+    #[tokio::test]
+    async fn self_grades_need_the_revealed_card() {
+        let setup = setup(ReviewMode::SelfGraded).await;
+        let ctx = &setup.ctx;
+        let payload = setup.remind.card_payload();
+
+        let state = setup
+            .remind
+            .handle_event(ctx, button(keyboard::Button::Good, &payload))
+            .await
+            .unwrap();
+        assert_eq!(state.name(), setup.remind.name());
+        assert!(outcomes(&setup).await.is_empty());
+
+        let state = state
+            .handle_event(ctx, button(keyboard::Button::ShowAnswer, ""))
+            .await
+            .unwrap();
+        assert!(setup.telegram.texts()[0].ends_with("How well did you remember it?"));
+        let other = format!("{}.{}", setup.remind.word.id, Direction::Reverse.key());
+        let state = state
+            .handle_event(ctx, button(keyboard::Button::Good, &other))
+            .await
+            .unwrap();
+        assert_eq!(state.name(), setup.remind.name());
+        assert!(outcomes(&setup).await.is_empty());
+
+        let state = state
+            .handle_event(ctx, button(keyboard::Button::Good, &payload))
+            .await
+            .unwrap();
+        assert_eq!(state.name(), idle::Idle::new().name());
+        assert_eq!(outcomes(&setup).await, [ReviewOutcome::Good]);
+    }
+
+    #[tokio::test]
+    async fn quiz_options_of_the_word_are_graded() {
+        let setup = setup(ReviewMode::Quiz).await;
+        let ctx = &setup.ctx;
+        let quiz = setup.remind.quiz.clone().unwrap();
+        assert_eq!(quiz.options[quiz.correct], "dog");
+
+        let katze = setup.db.find_word(CHAT_ID, "Katze").await.unwrap().unwrap();
+        let other = format!("{}.{}", quiz.correct, katze.id);
+        let state = setup
+            .remind
+            .handle_event(ctx, button(keyboard::Button::QuizOption, &other))
+            .await
+            .unwrap();
+        assert_eq!(state.name(), setup.remind.name());
+        assert!(outcomes(&setup).await.is_empty());
+
+        let chosen = format!("{}.{}", quiz.correct, setup.remind.word.id);
+        let state = state
+            .handle_event(ctx, button(keyboard::Button::QuizOption, &chosen))
+            .await
+            .unwrap();
+        assert_eq!(state.name(), idle::Idle::new().name());
+        assert_eq!(outcomes(&setup).await, [ReviewOutcome::Correct]);
+    }
 }
@@ -0,0 +1,179 @@
+use std::{collections::VecDeque, time};
+
+use async_trait::async_trait;
+use teloxide::{requests::Requester, types::Message};
+
+use crate::{
+    scheduler::order,
+    storage::{DueCard, LiteUser, ReviewOutcome},
+};
+
+use super::{
+    error::StateResult,
+    events::Event,
+    idle,
+    remind::{Remind, Step},
+    State,
+};
+
+#[derive(Debug, Clone, Default)]
+struct Summary {
+    correct: usize,
+    wrong: usize,
+    forgotten: usize,
+}
+
+impl Summary {
+    fn add(&mut self, outcome: ReviewOutcome) {
+        match outcome {
+            ReviewOutcome::Correct
+            | ReviewOutcome::AlmostCorrect
+            | ReviewOutcome::Hard
+            | ReviewOutcome::Good
+            | ReviewOutcome::Easy => self.correct += 1,
+            ReviewOutcome::Wrong | ReviewOutcome::Again => self.wrong += 1,
+            ReviewOutcome::Forgot | ReviewOutcome::Timeout => self.forgotten += 1,
+        }
+    }
+}
+
+/// Reminds all due words one after another without waiting for the reminder
+#[derive(Debug, Clone)]
+pub struct Session {
+    user: LiteUser,
+    remind: Remind,
+    /// Cards after the current one
+    queue: VecDeque<DueCard>,
+    total: usize,
+    summary: Summary,
+    /// The remind is of the next word, not the same one waiting for an answer
+    next_word: bool,
+}
+
+impl Session {
+    /// Starts a session of the due words, at most `limit` of them.
+    /// Stays idle if there is nothing to review.
+    pub async fn start(ctx: &super::Context, limit: Option<usize>) -> StateResult<Box<dyn State>> {
+        let user = ctx.db.ensure_user(ctx.chat_id.0).await?;
+        let cards = ctx.db.find_due_cards(user.chat_id).await?;
+        let mut queue: VecDeque<DueCard> = order::sort_for_user(&*ctx.db, &user, cards)
+            .await?
+            .into_iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .collect();
+        let total = queue.len();
+        let Some(card) = queue.pop_front() else {
+            ctx.bot
+                .send_message(ctx.chat_id, "No words to review now 🎉")
+                .await?;
+            return Ok(Box::new(idle::Idle::new()));
+        };
+
+        ctx.bot
+            .send_message(ctx.chat_id, format!("Let's review {} words", total))
+            .await?;
+        let remind = Remind::prepare(ctx, card, user.clone(), Some((1, total))).await?;
+        Ok(Box::new(Session {
+            user,
+            remind,
+            queue,
+            total,
+            summary: Summary::default(),
+            next_word: true,
+        }))
+    }
+
+    async fn next(&self, ctx: &super::Context, step: Step) -> StateResult<Box<dyn State>> {
+        let mut summary = self.summary.clone();
+        match step {
+            Step::Continue(remind) => {
+                return Ok(Box::new(Session {
+                    remind: *remind,
+                    next_word: false,
+                    ..self.clone()
+                }))
+            }
+            Step::Done(outcome) => summary.add(outcome),
+            Step::Skipped => {}
+        }
+
+        let mut queue = self.queue.clone();
+        let Some(card) = queue.pop_front() else {
+            self.finish(ctx, &summary).await?;
+            return Ok(Box::new(idle::Idle::new()));
+        };
+        let position = (self.total - queue.len(), self.total);
+        let remind = Remind::prepare(ctx, card, self.user.clone(), Some(position)).await?;
+        remind.ask(ctx).await?;
+        Ok(Box::new(Session {
+            user: self.user.clone(),
+            remind,
+            queue,
+            total: self.total,
+            summary,
+            next_word: true,
+        }))
+    }
+
+    async fn finish(&self, ctx: &super::Context, summary: &Summary) -> StateResult<()> {
+        let mut text = format!(
+            "Session is over\n\n✅ Correct: {}\n😔 Wrong: {}\n🤷 Forgotten: {}",
+            summary.correct, summary.wrong, summary.forgotten
+        );
+        if !self.queue.is_empty() {
+            text.push_str(&format!(
+                "\n\n{} words are left for later",
+                self.queue.len()
+            ));
+        }
+        ctx.bot.send_message(ctx.chat_id, text).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl State for Session {
+    async fn on_enter(&self, ctx: &super::Context, _: Option<Box<dyn State>>) -> StateResult<()> {
+        self.remind.ask(ctx).await
+    }
+
+    fn timeout(&self) -> Option<time::Duration> {
+        self.remind.timeout()
+    }
+
+    fn resets_timeout(&self) -> bool {
+        self.next_word
+    }
+
+    async fn handle_message(
+        &self,
+        ctx: &super::Context,
+        msg: Message,
+    ) -> StateResult<Box<dyn State>> {
+        let step = self.remind.answer(ctx, msg).await?;
+        self.next(ctx, step).await
+    }
+
+    async fn handle_event(
+        &self,
+        ctx: &super::Context,
+        event: Event,
+    ) -> StateResult<Box<dyn State>> {
+        let step = self.remind.handle_remind_event(ctx, event).await?;
+        self.next(ctx, step).await
+    }
+
+    // the user has left, the rest of the words wait for the reminder
+    async fn handle_timeout(&self, ctx: &super::Context) -> StateResult<Box<dyn State>> {
+        let mut summary = self.summary.clone();
+        if let Step::Done(outcome) = self.remind.expire(ctx).await? {
+            summary.add(outcome);
+        }
+        self.finish(ctx, &summary).await?;
+        Ok(Box::new(idle::Idle::new()))
+    }
+
+    fn clone_state(&self) -> Box<dyn State> {
+        Box::new(self.clone())
+    }
+}
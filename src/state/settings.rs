@@ -0,0 +1,165 @@
+use async_trait::async_trait;
+use teloxide::{
+    payloads::{EditMessageReplyMarkupSetters, SendMessageSetters},
+    requests::Requester,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId},
+};
+
+use crate::{
+    common::translation::Strictness,
+    keyboard::Button,
+    scheduler,
+    storage::{Directions, LiteUser},
+};
+
+use super::{
+    error::{StateError, StateResult},
+    events::Event,
+    State,
+};
+
+// seconds
+static REMIND_INTERVALS: [i32; 7] = [900, 1800, 3600, 7200, 14400, 28800, 86400];
+static WORDS_PER_PAGE: [i32; 3] = [5, 10, 20];
+
+/// Reminder preferences of the user, every button switches its setting
+/// to the next option
+#[derive(Clone, Debug)]
+pub struct Settings {
+    message_id: MessageId,
+}
+
+impl Settings {
+    pub async fn start(ctx: &super::Context) -> StateResult<Box<dyn State>> {
+        let user = ctx.db.ensure_user(ctx.chat_id.0).await?;
+        let msg = ctx
+            .bot
+            .send_message(ctx.chat_id, "Settings, press a button to change it")
+            .reply_markup(Settings::keyboard(&user))
+            .await?;
+        Ok(Box::new(Settings { message_id: msg.id }))
+    }
+
+    fn keyboard(user: &LiteUser) -> InlineKeyboardMarkup {
+        let sound = if user.silent { "off" } else { "on" };
+        InlineKeyboardMarkup::new(vec![
+            vec![button(
+                Button::RemindInterval,
+                format!("⏰ Reminders: every {}", interval_text(user.remind_every)),
+            )],
+            vec![button(
+                Button::SettingsStrictness,
+                format!("✍️ Answers: {}", user.strictness.text()),
+            )],
+            vec![button(
+                Button::SettingsDirections,
+                format!("🔁 Directions: {}", user.directions.text()),
+            )],
+            vec![button(
+                Button::WordsPerPage,
+                format!("📄 Words per page: {}", user.words_per_page),
+            )],
+            vec![button(Button::Sound, format!("🔔 Sound: {}", sound))],
+            vec![button(
+                Button::SettingsScheduler,
+                format!("🧠 Scheduler: {}", scheduler::get(&user.scheduler).text()),
+            )],
+            vec![Button::Cancel.to_inline_button()],
+        ])
+    }
+
+    async fn change(&self, ctx: &super::Context, button: Button) -> StateResult<()> {
+        let user = ctx.db.ensure_user(ctx.chat_id.0).await?;
+        match button {
+            Button::RemindInterval => {
+                let remind_every = next(&REMIND_INTERVALS, user.remind_every);
+                ctx.db.set_remind_every(user.chat_id, remind_every).await?;
+                // the next reminder follows the new interval right away
+                ctx.db
+                    .update_next_remind_user(user.chat_id, remind_every.into())
+                    .await?;
+                ctx.reschedule().await?;
+            }
+            Button::SettingsStrictness => {
+                let strictness = next(&Strictness::ALL, user.strictness);
+                ctx.db.set_strictness(user.chat_id, strictness).await?;
+            }
+            Button::SettingsDirections => {
+                let directions = next(&Directions::ALL, user.directions);
+                ctx.db.set_directions(user.chat_id, directions).await?;
+                ctx.reschedule().await?;
+            }
+            Button::WordsPerPage => {
+                let words_per_page = next(&WORDS_PER_PAGE, user.words_per_page);
+                ctx.db
+                    .set_words_per_page(user.chat_id, words_per_page)
+                    .await?;
+            }
+            Button::Sound => {
+                ctx.db.set_silent(user.chat_id, !user.silent).await?;
+            }
+            Button::SettingsScheduler => {
+                // the progress of words is kept, the new scheduler continues from it
+                let names = scheduler::ALL.map(|scheduler| scheduler.name());
+                let name = next(&names, scheduler::get(&user.scheduler).name());
+                ctx.db.set_scheduler(user.chat_id, name).await?;
+            }
+            _ => {
+                return Err(StateError::UnexpectedCommand(format!(
+                    "Unexpected command {} - {}",
+                    button.key(),
+                    button.text(),
+                )))
+            }
+        }
+
+        let user = ctx.db.ensure_user(ctx.chat_id.0).await?;
+        ctx.bot
+            .edit_message_reply_markup(ctx.chat_id, self.message_id)
+            .reply_markup(Settings::keyboard(&user))
+            .await?;
+        Ok(())
+    }
+}
+
+// the button with the current value of its setting
+fn button(button: Button, text: String) -> InlineKeyboardButton {
+    InlineKeyboardButton::callback(text, button.key())
+}
+
+// the option after the current one, the first one if the current isn't among them
+fn next<T: Copy + PartialEq>(options: &[T], current: T) -> T {
+    options
+        .iter()
+        .position(|option| *option == current)
+        .and_then(|index| options.get(index + 1))
+        .copied()
+        .unwrap_or(options[0])
+}
+
+// 900 -> "15 min", 7200 -> "2 h"
+fn interval_text(seconds: i32) -> String {
+    if seconds % 3600 == 0 {
+        format!("{} h", seconds / 3600)
+    } else {
+        format!("{} min", seconds / 60)
+    }
+}
+
+#[async_trait]
+impl State for Settings {
+    async fn handle_event(
+        &self,
+        ctx: &super::Context,
+        event: Event,
+    ) -> StateResult<Box<dyn State>> {
+        if let Event::Button(button, _) = event {
+            self.change(ctx, button).await?;
+        }
+        Ok(self.clone_state())
+    }
+
+    fn clone_state(&self) -> Box<dyn State> {
+        Box::new(self.clone())
+    }
+}
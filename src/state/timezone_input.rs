@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use chrono_tz::Tz;
+use teloxide::{
+    payloads::SendMessageSetters,
+    requests::Requester,
+    types::{ButtonRequest, KeyboardButton, KeyboardMarkup, KeyboardRemove, Message},
+};
+
+use crate::{common::local_time, storage};
+
+use super::{error::StateResult, idle, State};
+
+/// Waits for a timezone name or a shared location
+#[derive(Clone, Debug)]
+pub struct TimezoneInput {}
+
+impl TimezoneInput {
+    pub fn new() -> TimezoneInput {
+        TimezoneInput {}
+    }
+
+    /// Sets the timezone and tells the local time in it
+    pub async fn apply(ctx: &super::Context, timezone: Tz) -> StateResult<()> {
+        ctx.db.set_timezone(ctx.chat_id.0, timezone).await?;
+        ctx.reschedule().await?;
+        let local = storage::now().with_timezone(&timezone);
+        ctx.bot
+            .send_message(
+                ctx.chat_id,
+                format!(
+                    "Timezone: {}, it's {} there now",
+                    timezone.name(),
+                    local.format("%H:%M")
+                ),
+            )
+            .reply_markup(KeyboardRemove::new())
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl State for TimezoneInput {
+    async fn on_enter(&self, ctx: &super::Context, _: Option<Box<dyn State>>) -> StateResult<()> {
+        let user = ctx.db.ensure_user(ctx.chat_id.0).await?;
+        let location = KeyboardButton::new("📍 Share location").request(ButtonRequest::Location);
+        ctx.bot
+            .send_message(
+                ctx.chat_id,
+                format!(
+                    "Your timezone is {}. Write a name like Europe/Berlin or share a location",
+                    user.timezone.name()
+                ),
+            )
+            .reply_markup(
+                KeyboardMarkup::new(vec![vec![location]])
+                    .resize_keyboard(true)
+                    .one_time_keyboard(true),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn handle_message(
+        &self,
+        ctx: &super::Context,
+        msg: Message,
+    ) -> StateResult<Box<dyn State>> {
+        // the location gives the offset only, daylight saving time isn't known
+        let timezone = match (msg.location(), msg.text()) {
+            (Some(location), _) => Some(local_time::timezone_from_location(location.longitude)),
+            (None, Some(text)) => local_time::parse_timezone(text),
+            (None, None) => None,
+        };
+        let Some(timezone) = timezone else {
+            ctx.bot
+                .send_message(
+                    ctx.chat_id,
+                    "Unknown timezone, write a name like Europe/Berlin or share a location",
+                )
+                .await?;
+            return Ok(self.clone_state());
+        };
+
+        TimezoneInput::apply(ctx, timezone).await?;
+        Ok(Box::new(idle::Idle::new()))
+    }
+
+    fn clone_state(&self) -> Box<dyn State> {
+        Box::new(self.clone())
+    }
+}
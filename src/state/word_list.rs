@@ -2,97 +2,179 @@ use async_trait::async_trait;
 use teloxide::{
     payloads::{EditMessageTextSetters, SendMessageSetters},
     requests::Requester,
-    types::{MessageId, ParseMode},
+    types::{Message, MessageId, ParseMode},
+    utils::markdown,
 };
 
-use crate::keyboard;
+use crate::{
+    keyboard,
+    storage::{Direction, WordFilter, WordStatus},
+};
 
 use super::{
+    edit_word::EditWord,
     error::{StateError, StateResult},
     events::Event,
     State,
 };
 
-pub static WORDS_PER_PAGE: i64 = 5;
-
 #[derive(Clone, Debug, Default)]
 pub struct WordList {
     message_id: Option<MessageId>,
     offset: i64,
+    filter: WordFilter,
 }
 
 impl WordList {
-    pub fn new(message_id: Option<MessageId>, offset: i64) -> WordList {
-        WordList { message_id, offset }
+    pub fn new(message_id: Option<MessageId>, offset: i64, filter: WordFilter) -> WordList {
+        WordList {
+            message_id,
+            offset,
+            filter,
+        }
     }
 }
 
 impl WordList {
-    async fn get_words(&self, ctx: &super::Context, total_words: i64) -> StateResult<String> {
-        let offset = num::clamp(
-            self.offset,
-            0,
-            std::cmp::max(0, total_words - WORDS_PER_PAGE),
-        );
+    // the user chooses it in the settings
+    async fn page_size(ctx: &super::Context) -> StateResult<i64> {
+        let user = ctx.db.ensure_user(ctx.chat_id.0).await?;
+        Ok(user.words_per_page.max(1).into())
+    }
+
+    async fn get_words(
+        &self,
+        ctx: &super::Context,
+        total_words: i64,
+        page_size: i64,
+    ) -> StateResult<String> {
+        let offset = num::clamp(self.offset, 0, std::cmp::max(0, total_words - page_size));
         let words = ctx
             .db
-            .get_words(ctx.chat_id.0, offset, WORDS_PER_PAGE)
+            .get_words(ctx.chat_id.0, &self.filter, offset, page_size)
             .await?;
         let mut text = String::new();
+        if let Some(deck_id) = &self.filter.deck_id {
+            let decks = ctx.db.get_decks(ctx.chat_id.0).await?;
+            if let Some(deck) = decks.iter().find(|d| &d.id == deck_id) {
+                text.push_str(&format!("Deck: *{}*\n", markdown::escape(&deck.name)));
+            }
+        }
+        if let Some(search) = &self.filter.search {
+            text.push_str(&format!("Search: `{}`\n\n", markdown::escape_code(search)));
+        }
         for word in words {
+            let status = match word.status {
+                WordStatus::Active => String::new(),
+                status => format!(" \\[{}\\]", status.text()),
+            };
             text.push_str(&format!(
-                "{} \\- `{}` \\(level: *{}*\\)\n",
-                word.word, word.translate, word.remember_level
+                "{} \\- {} \\(level: *{}* → / *{}* ←\\){}\n",
+                word.word,
+                word.translations.to_formatted_string(),
+                word.level(Direction::Forward),
+                word.level(Direction::Reverse),
+                status
             ));
         }
+        if total_words == 0 {
+            text.push_str("No words found");
+        }
         Ok(text)
     }
 
-    async fn update_list(&self, ctx: &super::Context) -> StateResult<()> {
-        let total_amount = ctx.db.words_count(ctx.chat_id.0).await?;
-        let words = self.get_words(ctx, total_amount).await?;
+    async fn update_list(&self, ctx: &super::Context) -> StateResult<MessageId> {
+        let total_amount = ctx.db.words_count(ctx.chat_id.0, &self.filter).await?;
+        let page_size = WordList::page_size(ctx).await?;
+        let words = self.get_words(ctx, total_amount, page_size).await?;
         let prev_button = if self.offset > 0 {
             Some(keyboard::Button::PrevPage)
         } else {
             None
         };
-        let next_button = if self.offset + WORDS_PER_PAGE < total_amount {
+        let next_button = if self.offset + page_size < total_amount {
             Some(keyboard::Button::NextPage)
         } else {
             None
         };
 
+        let has_decks = !ctx.db.get_decks(ctx.chat_id.0).await?.is_empty();
         let keyboard_of_list = keyboard::make(&vec![
             vec![prev_button, next_button],
+            vec![
+                has_decks.then_some(keyboard::Button::NextDeck),
+                Some(keyboard::Button::EditWord),
+            ],
             vec![Some(keyboard::Button::Cancel)],
         ]);
-        if let Some(msg_id) = self.message_id {
+        let message_id = if let Some(msg_id) = self.message_id {
             ctx.bot
                 .edit_message_text(ctx.chat_id, msg_id, words)
                 .parse_mode(ParseMode::MarkdownV2)
                 .reply_markup(keyboard_of_list)
-                .await?;
+                .await?
+                .id
         } else {
             ctx.bot
                 .send_message(ctx.chat_id, words)
                 .parse_mode(ParseMode::MarkdownV2)
                 .reply_markup(keyboard_of_list)
-                .await?;
+                .await?
+                .id
         };
-        Ok(())
+        Ok(message_id)
     }
 
     async fn next_page(&self, ctx: &super::Context) -> StateResult<Box<dyn State>> {
-        let new_state = WordList::new(self.message_id, self.offset + WORDS_PER_PAGE);
+        let new_state = WordList::new(
+            self.message_id,
+            self.offset + WordList::page_size(ctx).await?,
+            self.filter.clone(),
+        );
         new_state.update_list(ctx).await?;
         Ok(Box::new(new_state))
     }
 
     async fn prev_page(&self, ctx: &super::Context) -> StateResult<Box<dyn State>> {
-        let new_state = WordList::new(self.message_id, self.offset - WORDS_PER_PAGE);
+        let new_state = WordList::new(
+            self.message_id,
+            (self.offset - WordList::page_size(ctx).await?).max(0),
+            self.filter.clone(),
+        );
         new_state.update_list(ctx).await?;
         Ok(Box::new(new_state))
     }
+
+    // cycles through all words -> first deck -> ... -> last deck -> all words
+    async fn next_deck(&self, ctx: &super::Context) -> StateResult<Box<dyn State>> {
+        let decks = ctx.db.get_decks(ctx.chat_id.0).await?;
+        let deck_id = match &self.filter.deck_id {
+            None => decks.first(),
+            Some(deck_id) => decks.iter().skip_while(|d| &d.id != deck_id).nth(1),
+        }
+        .map(|d| d.id.clone());
+        let filter = WordFilter {
+            deck_id,
+            ..self.filter.clone()
+        };
+        let new_state = WordList::new(self.message_id, 0, filter);
+        new_state.update_list(ctx).await?;
+        Ok(Box::new(new_state))
+    }
+
+    // the result is sent as a new message below the search request
+    async fn search(&self, ctx: &super::Context, search: &str) -> StateResult<Box<dyn State>> {
+        let filter = WordFilter {
+            search: Some(search.trim().to_owned()).filter(|s| !s.is_empty()),
+            ..self.filter.clone()
+        };
+        let new_state = WordList::new(None, 0, filter);
+        let message_id = new_state.update_list(ctx).await?;
+        Ok(Box::new(WordList {
+            message_id: Some(message_id),
+            ..new_state
+        }))
+    }
 }
 
 #[async_trait]
@@ -100,7 +182,20 @@ impl State for WordList {
     async fn on_enter(&self, ctx: &super::Context, _: Option<Box<dyn State>>) -> StateResult<()> {
         log::info!("Entered WordList state");
 
-        self.update_list(ctx).await
+        self.update_list(ctx).await?;
+        Ok(())
+    }
+
+    async fn handle_message(
+        &self,
+        ctx: &super::Context,
+        msg: Message,
+    ) -> StateResult<Box<dyn State>> {
+        if let Some(text) = msg.text() {
+            return self.search(ctx, text).await;
+        }
+
+        Ok(self.clone_state())
     }
 
     async fn handle_event(
@@ -112,6 +207,8 @@ impl State for WordList {
             return match cmd {
                 keyboard::Button::NextPage => self.next_page(ctx).await,
                 keyboard::Button::PrevPage => self.prev_page(ctx).await,
+                keyboard::Button::NextDeck => self.next_deck(ctx).await,
+                keyboard::Button::EditWord => Ok(Box::new(EditWord::new(None))),
                 _ => Err(StateError::UnexpectedCommand(format!(
                     "Unexpected command {} - {}",
                     cmd.key(),
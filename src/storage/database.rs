@@ -0,0 +1,1373 @@
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use chrono_tz::Tz;
+
+use prisma_client_rust::{
+    operator::{and, or},
+    Direction,
+};
+
+use crate::{
+    common::{
+        local_time::{self, QuietHours},
+        normalize,
+        translation::{Strictness, Translation, TranslationItem},
+    },
+    prisma::{
+        self, card, challenge, challenge_answer, deck, review, translation,
+        user::{self},
+        word,
+    },
+    scheduler::{order::WordOrder, Progress},
+};
+
+use super::{
+    error::{StorageError, StorageResult},
+    merge_tags, now, word_next_remind_at, Card, Challenge, ChallengeAnswer, Deck,
+    Direction as CardDirection, Directions, DueCard, DuplicatePolicy, ImportSummary, LiteUser,
+    NewReview, NewWord, RemindTime, Review, ReviewMode, ReviewOutcome, Storage, UserWithCards,
+    Word, WordFilter, WordStatus,
+};
+
+impl From<user::Data> for LiteUser {
+    fn from(user: user::Data) -> Self {
+        LiteUser {
+            chat_id: user.chat_id,
+            next_remind_at: user.next_remind_at,
+            remind_every: user.remind_every,
+            scheduler: user.scheduler,
+            refresh_mastered: user.refresh_mastered,
+            active_deck_id: user.active_deck_id,
+            // unknown modes fall back to the default one like schedulers do
+            review_mode: ReviewMode::from_key(&user.review_mode).unwrap_or(ReviewMode::Typing),
+            strictness: Strictness::from_key(&user.strictness).unwrap_or(Strictness::Lenient),
+            directions: Directions::from_key(&user.directions).unwrap_or(Directions::Forward),
+            word_order: WordOrder::from_key(&user.word_order).unwrap_or(WordOrder::MostOverdue),
+            timezone: local_time::parse_timezone(&user.timezone).unwrap_or(Tz::UTC),
+            quiet_hours: user.quiet_hours.as_deref().and_then(QuietHours::parse),
+            words_per_page: user.words_per_page,
+            silent: user.silent,
+        }
+    }
+}
+
+impl From<deck::Data> for Deck {
+    fn from(deck: deck::Data) -> Self {
+        Deck {
+            id: deck.id,
+            chat_id: deck.chat_id,
+            created_at: deck.created_at,
+            name: deck.name,
+            from_lang: deck.from_lang,
+            to_lang: deck.to_lang,
+            reminders_enabled: deck.reminders_enabled,
+            directions: deck
+                .directions
+                .and_then(|directions| Directions::from_key(&directions).ok()),
+        }
+    }
+}
+
+impl From<translation::Data> for TranslationItem {
+    fn from(translation: translation::Data) -> Self {
+        TranslationItem::new(translation.text, translation.note)
+    }
+}
+
+impl TryFrom<card::Data> for Card {
+    type Error = StorageError;
+
+    fn try_from(card: card::Data) -> Result<Self, Self::Error> {
+        let direction = CardDirection::from_key(&card.direction)
+            .map_err(|_| StorageError::UnknownDirection(card.direction.clone()))?;
+        Ok(Card {
+            direction,
+            progress: Progress {
+                level: card.remember_level,
+                ease_factor: card.ease_factor,
+                interval: card.interval,
+                stability: card.stability,
+                difficulty: card.difficulty,
+            },
+            next_remind_at: card.next_remind_at,
+            reminded_at: card.reminded_at,
+        })
+    }
+}
+
+impl TryFrom<challenge::Data> for Challenge {
+    type Error = StorageError;
+
+    fn try_from(challenge: challenge::Data) -> Result<Self, Self::Error> {
+        let answers = challenge
+            .answers
+            .ok_or(StorageError::NotFetched("answers"))?
+            .into_iter()
+            .map(|answer| ChallengeAnswer {
+                user_id: answer.user_id,
+                name: answer.name,
+                correct: answer.correct,
+            })
+            .collect();
+        Ok(Challenge {
+            inline_message_id: challenge.inline_message_id,
+            chat_id: challenge.chat_id,
+            word: challenge.word,
+            options: challenge.options.lines().map(str::to_owned).collect(),
+            correct: challenge.correct as usize,
+            answers,
+        })
+    }
+}
+
+/// The word has to be fetched with its translations and cards,
+/// see `fetch_translations`
+impl TryFrom<word::Data> for Word {
+    type Error = StorageError;
+
+    fn try_from(mut word: word::Data) -> Result<Self, Self::Error> {
+        let translations = word
+            .translations
+            .take()
+            .ok_or(StorageError::NotFetched("translations"))?;
+        let cards = word
+            .cards
+            .take()
+            .ok_or(StorageError::NotFetched("cards"))?
+            .into_iter()
+            .map(Card::try_from)
+            .collect::<StorageResult<Vec<Card>>>()?;
+        let translations = Translation::new(
+            translations
+                .into_iter()
+                .map(TranslationItem::from)
+                .collect(),
+        );
+        with_relations(word, translations, cards)
+    }
+}
+
+// the word with relations which are already known, e.g. of a just created word
+fn with_relations(
+    word: word::Data,
+    translations: Translation,
+    cards: Vec<Card>,
+) -> StorageResult<Word> {
+    let status = WordStatus::from_key(&word.status)
+        .map_err(|_| StorageError::UnknownWordStatus(word.status.clone()))?;
+    Ok(Word {
+        id: word.id,
+        chat_id: word.chat_id,
+        created_at: word.created_at,
+        word: word.word,
+        translations,
+        cards,
+        status,
+        deck_id: word.deck_id,
+        tags: word.tags.split_whitespace().map(str::to_owned).collect(),
+    })
+}
+
+impl TryFrom<review::Data> for Review {
+    type Error = StorageError;
+
+    fn try_from(review: review::Data) -> Result<Self, Self::Error> {
+        let outcome = ReviewOutcome::from_key(&review.outcome)
+            .map_err(|_| StorageError::UnknownReviewOutcome(review.outcome.clone()))?;
+        let direction = CardDirection::from_key(&review.direction)
+            .map_err(|_| StorageError::UnknownDirection(review.direction.clone()))?;
+        Ok(Review {
+            id: review.id,
+            created_at: review.created_at,
+            word_id: review.word_id,
+            chat_id: review.chat_id,
+            outcome,
+            direction,
+            level_before: review.level_before,
+            level_after: review.level_after,
+            answer: review.answer,
+            latency: review.latency,
+        })
+    }
+}
+
+fn word_filters(chat_id: i64, filter: &WordFilter) -> Vec<word::WhereParam> {
+    let mut filters = vec![word::chat_id::equals(chat_id)];
+    if !filter.statuses.is_empty() {
+        let statuses = filter.statuses.iter().map(|s| s.key().to_owned()).collect();
+        filters.push(word::status::in_vec(statuses));
+    }
+    if let Some(deck_id) = &filter.deck_id {
+        filters.push(word::deck_id::equals(Some(deck_id.to_owned())));
+    }
+    if let Some(search) = &filter.search {
+        filters.push(or(vec![
+            word::normalized::contains(normalize::key(search)),
+            word::translations::some(vec![translation::normalized::contains(normalize::key(
+                search,
+            ))]),
+        ]));
+    }
+    filters
+}
+
+fn fetch_translations() -> word::translations::Fetch {
+    word::translations::fetch(vec![]).order_by(translation::position::order(Direction::Asc))
+}
+
+// creates the word with its cards and translations, expected to run in a transaction
+async fn create_word(
+    client: &prisma::PrismaClient,
+    chat_id: i64,
+    new_word: NewWord,
+) -> StorageResult<Word> {
+    let cards = new_word.cards();
+    let word = client
+        .word()
+        .create(
+            chat_id,
+            normalize::clean(&new_word.word),
+            normalize::key(&new_word.word),
+            prisma::user::UniqueWhereParam::ChatIdEquals(chat_id),
+            vec![
+                word::deck_id::set(new_word.deck_id),
+                word::tags::set(new_word.tags.join(" ")),
+            ],
+        )
+        .exec()
+        .await?;
+    for card in cards.iter() {
+        client
+            .card()
+            .create(
+                card.direction.key().to_owned(),
+                card.next_remind_at,
+                word::id::equals(word.id.clone()),
+                vec![
+                    card::reminded_at::set(card.reminded_at),
+                    card::remember_level::set(card.progress.level),
+                    card::ease_factor::set(card.progress.ease_factor),
+                    card::interval::set(card.progress.interval),
+                    card::stability::set(card.progress.stability),
+                    card::difficulty::set(card.progress.difficulty),
+                ],
+            )
+            .exec()
+            .await?;
+    }
+    create_translations(client, &word.id, &new_word.translation).await?;
+
+    with_relations(word, new_word.translation, cards)
+}
+
+async fn create_translations(
+    client: &prisma::PrismaClient,
+    word_id: &str,
+    translation: &Translation,
+) -> StorageResult<()> {
+    for (position, item) in translation.iter().enumerate() {
+        client
+            .translation()
+            .create(
+                item.text.clone(),
+                normalize::key(&item.text),
+                position as i32,
+                word::id::equals(word_id.to_owned()),
+                vec![translation::note::set(item.note.clone())],
+            )
+            .exec()
+            .await?;
+    }
+    Ok(())
+}
+
+// replaces translations of the word, expected to run in a transaction
+async fn replace_translations(
+    client: &prisma::PrismaClient,
+    word_id: &str,
+    translation: &Translation,
+) -> StorageResult<()> {
+    client
+        .translation()
+        .delete_many(vec![translation::word_id::equals(word_id.to_owned())])
+        .exec()
+        .await?;
+    create_translations(client, word_id, translation).await
+}
+
+static NORMALIZE_PAGE_SIZE: i64 = 1000;
+// Cards looked through at once for the next due one of a user
+static NEXT_DUE_PAGE_SIZE: i64 = 100;
+
+#[derive(Debug)]
+pub struct PrismaStorage(prisma::PrismaClient);
+
+impl Deref for PrismaStorage {
+    type Target = prisma::PrismaClient;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for PrismaStorage {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl PrismaStorage {
+    pub fn new(prisma_client: prisma::PrismaClient) -> Self {
+        Self(prisma_client)
+    }
+
+    /// Fills the normalized form of words and translations added before it
+    /// was stored, the database can't fold the case of non-ASCII letters itself.
+    /// Returns the amount of updated words and translations.
+    pub async fn normalize_words(&self) -> StorageResult<usize> {
+        Ok(self.normalize_word_texts().await? + self.normalize_translations().await?)
+    }
+
+    async fn normalize_word_texts(&self) -> StorageResult<usize> {
+        let mut updated = 0;
+        let mut last_id = None;
+        loop {
+            let mut filters = vec![word::normalized::equals(String::new())];
+            if let Some(last_id) = last_id {
+                filters.push(word::id::gt(last_id));
+            }
+            let words = self
+                .word()
+                .find_many(filters)
+                .order_by(word::id::order(Direction::Asc))
+                .take(NORMALIZE_PAGE_SIZE)
+                .exec()
+                .await?;
+            for word in words.iter() {
+                self.word()
+                    .update(
+                        word::id::equals(word.id.clone()),
+                        vec![word::normalized::set(normalize::key(&word.word))],
+                    )
+                    .exec()
+                    .await?;
+            }
+            updated += words.len();
+            match words.last() {
+                Some(word) if words.len() as i64 == NORMALIZE_PAGE_SIZE => {
+                    last_id = Some(word.id.clone())
+                }
+                _ => return Ok(updated),
+            }
+        }
+    }
+
+    async fn normalize_translations(&self) -> StorageResult<usize> {
+        let mut updated = 0;
+        let mut last_id = None;
+        loop {
+            let mut filters = vec![translation::normalized::equals(String::new())];
+            if let Some(last_id) = last_id {
+                filters.push(translation::id::gt(last_id));
+            }
+            let translations = self
+                .translation()
+                .find_many(filters)
+                .order_by(translation::id::order(Direction::Asc))
+                .take(NORMALIZE_PAGE_SIZE)
+                .exec()
+                .await?;
+            for translation in translations.iter() {
+                self.translation()
+                    .update(
+                        translation::id::equals(translation.id.clone()),
+                        vec![translation::normalized::set(normalize::key(
+                            &translation.text,
+                        ))],
+                    )
+                    .exec()
+                    .await?;
+            }
+            updated += translations.len();
+            match translations.last() {
+                Some(translation) if translations.len() as i64 == NORMALIZE_PAGE_SIZE => {
+                    last_id = Some(translation.id.clone())
+                }
+                _ => return Ok(updated),
+            }
+        }
+    }
+
+    // due cards of the users, see `Storage::find_to_remind` for the filters
+    async fn users_due_cards(
+        &self,
+        users: Vec<LiteUser>,
+        now: DateTime<FixedOffset>,
+    ) -> StorageResult<Vec<UserWithCards>> {
+        let chat_ids: Vec<i64> = users.iter().map(|u| u.chat_id).collect();
+        let word_filters = vec![
+            word::chat_id::in_vec(chat_ids.clone()),
+            word::cards::some(vec![card::next_remind_at::lte(now)]),
+            word::status::in_vec(vec![
+                WordStatus::Active.key().to_owned(),
+                WordStatus::Mastered.key().to_owned(),
+            ]),
+            or(vec![
+                word::deck_id::equals(None),
+                word::deck::is(vec![deck::reminders_enabled::equals(true)]),
+            ]),
+        ];
+        let mut words: HashMap<i64, Vec<Word>> = HashMap::new();
+        let found = self
+            .word()
+            .find_many(word_filters)
+            .with(fetch_translations())
+            .with(word::cards::fetch(vec![]))
+            .exec()
+            .await?;
+        for word in found {
+            let word = Word::try_from(word)?;
+            words.entry(word.chat_id).or_default().push(word);
+        }
+
+        let deck_directions = self.deck_directions(chat_ids).await?;
+
+        Ok(users
+            .into_iter()
+            .map(|user| {
+                // mastered words are reminded only as refresher reviews
+                let cards = words
+                    .remove(&user.chat_id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|w| user.refresh_mastered || w.status != WordStatus::Mastered)
+                    .flat_map(|w| {
+                        let directions = w
+                            .deck_id
+                            .as_ref()
+                            .and_then(|id| deck_directions.get(id).copied())
+                            .unwrap_or(user.directions);
+                        w.due_cards(directions, now)
+                    })
+                    .collect();
+                UserWithCards { user, cards }
+            })
+            .collect())
+    }
+
+    // directions of the users' decks which override the users' ones
+    async fn deck_directions(
+        &self,
+        chat_ids: Vec<i64>,
+    ) -> StorageResult<HashMap<String, Directions>> {
+        Ok(self
+            .deck()
+            .find_many(vec![deck::chat_id::in_vec(chat_ids)])
+            .exec()
+            .await?
+            .into_iter()
+            .map(Deck::from)
+            .filter_map(|deck| deck.directions.map(|directions| (deck.id, directions)))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl Storage for PrismaStorage {
+    /* #region User model */
+    async fn get_user(&self, chat_id: i64) -> StorageResult<Option<LiteUser>> {
+        let user = self
+            .user()
+            .find_first(vec![user::chat_id::equals(chat_id)])
+            .exec()
+            .await?;
+        Ok(user.map(LiteUser::from))
+    }
+
+    async fn new_user(&self, chat_id: i64) -> StorageResult<LiteUser> {
+        let user = self.user().create(chat_id, vec![]).exec().await?;
+        Ok(user.into())
+    }
+
+    async fn update_next_remind_user(
+        &self,
+        id: i64,
+        remind_every: i64,
+    ) -> StorageResult<DateTime<FixedOffset>> {
+        let next_remind_at = now() + chrono::Duration::seconds(remind_every);
+        log::debug!("Next remind user {}: {:?}", id, next_remind_at);
+
+        self.user()
+            .update(
+                user::chat_id::equals(id),
+                vec![user::next_remind_at::set(next_remind_at)],
+            )
+            .exec()
+            .await?;
+        Ok(next_remind_at)
+    }
+
+    async fn get_remind_times(
+        &self,
+        after: Option<RemindTime>,
+        take: i64,
+    ) -> StorageResult<Vec<RemindTime>> {
+        let filters = match after {
+            Some(after) => vec![or(vec![
+                user::next_remind_at::gt(after.next_remind_at),
+                and(vec![
+                    user::next_remind_at::equals(after.next_remind_at),
+                    user::chat_id::gt(after.chat_id),
+                ]),
+            ])],
+            None => vec![],
+        };
+        Ok(self
+            .user()
+            .find_many(filters)
+            .order_by(user::next_remind_at::order(Direction::Asc))
+            .order_by(user::chat_id::order(Direction::Asc))
+            .take(take)
+            .exec()
+            .await?
+            .into_iter()
+            .map(|user| RemindTime {
+                next_remind_at: user.next_remind_at,
+                chat_id: user.chat_id,
+            })
+            .collect())
+    }
+
+    async fn set_refresh_mastered(&self, chat_id: i64, enabled: bool) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::refresh_mastered::set(enabled)],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_scheduler(&self, chat_id: i64, scheduler: &str) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::scheduler::set(scheduler.to_owned())],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_remind_every(&self, chat_id: i64, remind_every: i32) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::remind_every::set(remind_every)],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_words_per_page(&self, chat_id: i64, words_per_page: i32) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::words_per_page::set(words_per_page)],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_silent(&self, chat_id: i64, silent: bool) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::silent::set(silent)],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_review_mode(&self, chat_id: i64, mode: ReviewMode) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::review_mode::set(mode.key().to_owned())],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_strictness(&self, chat_id: i64, strictness: Strictness) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::strictness::set(strictness.key().to_owned())],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_directions(&self, chat_id: i64, directions: Directions) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::directions::set(directions.key().to_owned())],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_word_order(&self, chat_id: i64, order: WordOrder) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::word_order::set(order.key().to_owned())],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_timezone(&self, chat_id: i64, timezone: Tz) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::timezone::set(timezone.name().to_owned())],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_quiet_hours(
+        &self,
+        chat_id: i64,
+        quiet_hours: Option<QuietHours>,
+    ) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::quiet_hours::set(
+                    quiet_hours.map(|hours| hours.to_string()),
+                )],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_active_deck(&self, chat_id: i64, deck_id: Option<&str>) -> StorageResult<()> {
+        self.user()
+            .update(
+                user::chat_id::equals(chat_id),
+                vec![user::active_deck_id::set(deck_id.map(str::to_owned))],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+    /* #endregion */
+
+    /* #region Deck model */
+    async fn new_deck(
+        &self,
+        chat_id: i64,
+        name: &str,
+        from_lang: Option<String>,
+        to_lang: Option<String>,
+    ) -> StorageResult<Deck> {
+        if self.find_deck(chat_id, name).await?.is_some() {
+            return Err(StorageError::DeckAlreadyExists);
+        }
+
+        let user = self.ensure_user(chat_id).await?;
+        let deck = self
+            .deck()
+            .create(
+                name.to_owned(),
+                user::chat_id::equals(user.chat_id),
+                vec![deck::from_lang::set(from_lang), deck::to_lang::set(to_lang)],
+            )
+            .exec()
+            .await?;
+        Ok(deck.into())
+    }
+
+    async fn get_decks(&self, chat_id: i64) -> StorageResult<Vec<Deck>> {
+        let decks = self
+            .deck()
+            .find_many(vec![deck::chat_id::equals(chat_id)])
+            .order_by(deck::created_at::order(Direction::Asc))
+            .exec()
+            .await?;
+        Ok(decks.into_iter().map(Deck::from).collect())
+    }
+
+    async fn find_deck(&self, chat_id: i64, name: &str) -> StorageResult<Option<Deck>> {
+        let deck = self
+            .deck()
+            .find_first(vec![
+                deck::chat_id::equals(chat_id),
+                deck::name::equals(name.to_owned()),
+            ])
+            .exec()
+            .await?;
+        Ok(deck.map(Deck::from))
+    }
+
+    async fn rename_deck(&self, chat_id: i64, deck_id: &str, name: &str) -> StorageResult<()> {
+        let exists = self
+            .deck()
+            .find_first(vec![
+                deck::chat_id::equals(chat_id),
+                deck::name::equals(name.to_owned()),
+                deck::id::not(deck_id.to_owned()),
+            ])
+            .exec()
+            .await?
+            .is_some();
+        if exists {
+            return Err(StorageError::DeckAlreadyExists);
+        }
+
+        self.deck()
+            .update(
+                deck::id::equals(deck_id.to_owned()),
+                vec![deck::name::set(name.to_owned())],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_deck(&self, deck_id: &str) -> StorageResult<()> {
+        self.user()
+            .update_many(
+                vec![user::active_deck_id::equals(Some(deck_id.to_owned()))],
+                vec![user::active_deck_id::set(None)],
+            )
+            .exec()
+            .await?;
+        self.deck()
+            .delete(deck::id::equals(deck_id.to_owned()))
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_deck_reminders(&self, deck_id: &str, enabled: bool) -> StorageResult<()> {
+        self.deck()
+            .update(
+                deck::id::equals(deck_id.to_owned()),
+                vec![deck::reminders_enabled::set(enabled)],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_deck_directions(
+        &self,
+        deck_id: &str,
+        directions: Option<Directions>,
+    ) -> StorageResult<()> {
+        self.deck()
+            .update(
+                deck::id::equals(deck_id.to_owned()),
+                vec![deck::directions::set(
+                    directions.map(|directions| directions.key().to_owned()),
+                )],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+    /* #endregion */
+
+    /* #region Word model */
+    async fn new_word(
+        &self,
+        chat_id: i64,
+        word: &str,
+        translation: &Translation,
+        deck_id: Option<&str>,
+    ) -> StorageResult<Word> {
+        if self.has_word(chat_id, word).await? {
+            return Err(StorageError::WordAlreadyExists);
+        }
+
+        self.ensure_user(chat_id).await?;
+
+        let new_word = NewWord {
+            word: word.to_owned(),
+            translation: translation.clone(),
+            deck_id: deck_id.map(str::to_owned),
+            ..Default::default()
+        };
+        self._transaction()
+            .run::<StorageError, _, _, _>(|client| async move {
+                create_word(&client, chat_id, new_word).await
+            })
+            .await
+    }
+
+    async fn new_words(&self, chat_id: i64, words: &[NewWord]) -> StorageResult<Vec<Word>> {
+        self.ensure_user(chat_id).await?;
+
+        let words = words.to_vec();
+        self._transaction()
+            .run::<StorageError, _, _, _>(|client| async move {
+                let keys: Vec<String> = words.iter().map(|w| normalize::key(&w.word)).collect();
+                let mut existing: Vec<String> = client
+                    .word()
+                    .find_many(vec![
+                        word::chat_id::equals(chat_id),
+                        word::normalized::in_vec(keys),
+                    ])
+                    .exec()
+                    .await?
+                    .into_iter()
+                    .map(|w| w.normalized)
+                    .collect();
+
+                let mut added = vec![];
+                for new_word in words {
+                    let key = normalize::key(&new_word.word);
+                    if existing.contains(&key) {
+                        continue;
+                    }
+                    existing.push(key);
+                    added.push(create_word(&client, chat_id, new_word).await?);
+                }
+                Ok(added)
+            })
+            .await
+    }
+
+    async fn import_words(
+        &self,
+        chat_id: i64,
+        words: &[NewWord],
+        policy: DuplicatePolicy,
+    ) -> StorageResult<ImportSummary> {
+        self.ensure_user(chat_id).await?;
+
+        let words = words.to_vec();
+        self._transaction()
+            .run::<StorageError, _, _, _>(|client| async move {
+                let keys: Vec<String> = words.iter().map(|w| normalize::key(&w.word)).collect();
+                let mut existing: HashMap<String, Word> = HashMap::new();
+                let found = client
+                    .word()
+                    .find_many(vec![
+                        word::chat_id::equals(chat_id),
+                        word::normalized::in_vec(keys),
+                    ])
+                    .with(fetch_translations())
+                    .with(word::cards::fetch(vec![]))
+                    .exec()
+                    .await?;
+                for word in found {
+                    existing.insert(word.normalized.clone(), Word::try_from(word)?);
+                }
+
+                let mut summary = ImportSummary::default();
+                for new_word in words {
+                    let key = normalize::key(&new_word.word);
+                    let Some(word) = existing.get_mut(&key) else {
+                        let word = create_word(&client, chat_id, new_word).await?;
+                        existing.insert(key, word);
+                        summary.added += 1;
+                        continue;
+                    };
+                    let (translation, tags, deck_id) = match policy {
+                        DuplicatePolicy::Skip => {
+                            summary.skipped += 1;
+                            continue;
+                        }
+                        DuplicatePolicy::Merge => {
+                            summary.merged += 1;
+                            (
+                                word.translations.merge(&new_word.translation),
+                                merge_tags(&word.tags, &new_word.tags),
+                                word.deck_id.clone(),
+                            )
+                        }
+                        DuplicatePolicy::Overwrite => {
+                            summary.overwritten += 1;
+                            (new_word.translation, new_word.tags, new_word.deck_id)
+                        }
+                    };
+                    replace_translations(&client, &word.id, &translation).await?;
+                    client
+                        .word()
+                        .update(
+                            word::id::equals(word.id.clone()),
+                            vec![
+                                word::tags::set(tags.join(" ")),
+                                word::deck_id::set(deck_id.clone()),
+                            ],
+                        )
+                        .exec()
+                        .await?;
+                    word.translations = translation;
+                    word.tags = tags;
+                    word.deck_id = deck_id;
+                }
+                Ok(summary)
+            })
+            .await
+    }
+
+    async fn has_word(&self, chat_id: i64, word: &str) -> StorageResult<bool> {
+        let has_word = self
+            .word()
+            .find_first(vec![
+                word::normalized::equals(normalize::key(word)),
+                word::chat_id::equals(chat_id),
+            ])
+            .exec()
+            .await?
+            .is_some();
+        Ok(has_word)
+    }
+
+    async fn find_word(&self, chat_id: i64, word: &str) -> StorageResult<Option<Word>> {
+        self.word()
+            .find_first(vec![
+                word::normalized::equals(normalize::key(word)),
+                word::chat_id::equals(chat_id),
+            ])
+            .with(fetch_translations())
+            .with(word::cards::fetch(vec![]))
+            .exec()
+            .await?
+            .map(Word::try_from)
+            .transpose()
+    }
+
+    async fn rename_word(&self, chat_id: i64, word_id: &str, word: &str) -> StorageResult<()> {
+        let normalized = normalize::key(word);
+        let exists = self
+            .word()
+            .find_first(vec![
+                word::normalized::equals(normalized.clone()),
+                word::chat_id::equals(chat_id),
+                word::id::not(word_id.to_owned()),
+            ])
+            .exec()
+            .await?
+            .is_some();
+        if exists {
+            return Err(StorageError::WordAlreadyExists);
+        }
+
+        self.word()
+            .update(
+                word::id::equals(word_id.to_owned()),
+                vec![
+                    word::word::set(normalize::clean(word)),
+                    word::normalized::set(normalized),
+                ],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_translations(
+        &self,
+        word_id: &str,
+        translation: &Translation,
+    ) -> StorageResult<()> {
+        let word_id = word_id.to_owned();
+        let translation = translation.clone();
+        self._transaction()
+            .run::<StorageError, _, _, _>(|client| async move {
+                replace_translations(&client, &word_id, &translation).await
+            })
+            .await
+    }
+
+    async fn reset_word_progress(&self, word_id: &str) -> StorageResult<()> {
+        let word_id = word_id.to_owned();
+        self._transaction()
+            .run::<StorageError, _, _, _>(|client| async move {
+                for card in CardDirection::ALL.into_iter().map(Card::new) {
+                    let progress = card.progress;
+                    client
+                        .card()
+                        .update_many(
+                            vec![
+                                card::word_id::equals(word_id.clone()),
+                                card::direction::equals(card.direction.key().to_owned()),
+                            ],
+                            vec![
+                                card::next_remind_at::set(card.next_remind_at),
+                                card::reminded_at::set(card.reminded_at),
+                                card::remember_level::set(progress.level),
+                                card::ease_factor::set(progress.ease_factor),
+                                card::interval::set(progress.interval),
+                                card::stability::set(progress.stability),
+                                card::difficulty::set(progress.difficulty),
+                            ],
+                        )
+                        .exec()
+                        .await?;
+                }
+                client
+                    .word()
+                    .update(
+                        word::id::equals(word_id),
+                        vec![word::status::set(WordStatus::Active.key().to_owned())],
+                    )
+                    .exec()
+                    .await?;
+                Ok(())
+            })
+            .await
+    }
+
+    async fn get_words(
+        &self,
+        chat_id: i64,
+        filter: &WordFilter,
+        skip: i64,
+        take: i64,
+    ) -> StorageResult<Vec<Word>> {
+        self.word()
+            .find_many(word_filters(chat_id, filter))
+            .with(fetch_translations())
+            .with(word::cards::fetch(vec![]))
+            .order_by(word::created_at::order(Direction::Asc))
+            .skip(skip)
+            .take(take)
+            .exec()
+            .await?
+            .into_iter()
+            .map(Word::try_from)
+            .collect()
+    }
+
+    async fn words_count(&self, chat_id: i64, filter: &WordFilter) -> StorageResult<i64> {
+        let count = self
+            .word()
+            .count(word_filters(chat_id, filter))
+            .exec()
+            .await?;
+        Ok(count)
+    }
+
+    async fn remove_word(&self, chat_id: i64, word: &str) -> StorageResult<()> {
+        self.word()
+            .delete_many(vec![
+                word::normalized::equals(normalize::key(word)),
+                word::chat_id::equals(chat_id),
+            ])
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn update_word_remind(
+        &self,
+        word_id: &str,
+        direction: CardDirection,
+        progress: &Progress,
+        status: WordStatus,
+    ) -> StorageResult<()> {
+        self.card()
+            .update_many(
+                vec![
+                    card::word_id::equals(word_id.to_owned()),
+                    card::direction::equals(direction.key().to_owned()),
+                ],
+                vec![
+                    card::next_remind_at::set(word_next_remind_at(progress, status)),
+                    card::reminded_at::set(now()),
+                    card::remember_level::set(progress.level),
+                    card::ease_factor::set(progress.ease_factor),
+                    card::interval::set(progress.interval),
+                    card::stability::set(progress.stability),
+                    card::difficulty::set(progress.difficulty),
+                ],
+            )
+            .exec()
+            .await?;
+        self.word()
+            .update(
+                word::id::equals(word_id.to_owned()),
+                vec![word::status::set(status.key().to_owned())],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn set_word_status(
+        &self,
+        chat_id: i64,
+        word: &str,
+        status: WordStatus,
+    ) -> StorageResult<bool> {
+        let word_filters = || {
+            vec![
+                word::normalized::equals(normalize::key(word)),
+                word::chat_id::equals(chat_id),
+            ]
+        };
+        if status == WordStatus::Active {
+            // words returned to learning are due right away instead of in months
+            let mut filters = word_filters();
+            filters.push(word::status::not(WordStatus::Active.key().to_owned()));
+            self.card()
+                .update_many(
+                    vec![card::word::is(filters)],
+                    vec![card::next_remind_at::set(now())],
+                )
+                .exec()
+                .await?;
+        }
+        let updated = self
+            .word()
+            .update_many(
+                word_filters(),
+                vec![word::status::set(status.key().to_owned())],
+            )
+            .exec()
+            .await?;
+        Ok(updated > 0)
+    }
+
+    async fn find_to_remind(&self, chat_ids: &[i64]) -> StorageResult<Vec<UserWithCards>> {
+        let now = now();
+        let (due, not_due): (Vec<LiteUser>, Vec<LiteUser>) = self
+            .user()
+            .find_many(vec![user::chat_id::in_vec(chat_ids.to_vec())])
+            .order_by(user::next_remind_at::order(Direction::Asc))
+            .exec()
+            .await?
+            .into_iter()
+            .map(LiteUser::from)
+            .partition(|user| user.next_remind_at <= now);
+
+        log::debug!("Found {} users to remind", due.len());
+        let mut users = self.users_due_cards(due, now).await?;
+        users.extend(not_due.into_iter().map(|user| UserWithCards {
+            user,
+            cards: vec![],
+        }));
+        Ok(users)
+    }
+
+    async fn find_due_cards(&self, chat_id: i64) -> StorageResult<Vec<DueCard>> {
+        let Some(user) = self.get_user(chat_id).await? else {
+            return Ok(vec![]);
+        };
+        let mut cards = self
+            .users_due_cards(vec![user], now())
+            .await?
+            .pop()
+            .map(|user| user.cards)
+            .unwrap_or_default();
+        cards.sort_by_key(|c| c.card.next_remind_at);
+        Ok(cards)
+    }
+
+    async fn next_due_at(&self, user: &LiteUser) -> StorageResult<Option<DateTime<FixedOffset>>> {
+        let mut statuses = vec![WordStatus::Active.key().to_owned()];
+        // mastered words are reminded only as refresher reviews
+        if user.refresh_mastered {
+            statuses.push(WordStatus::Mastered.key().to_owned());
+        }
+        let deck_directions = self.deck_directions(vec![user.chat_id]).await?;
+        let mut skip = 0;
+        // cards of inactive directions are skipped page by page
+        loop {
+            let cards = self
+                .card()
+                .find_many(vec![card::word::is(vec![
+                    word::chat_id::equals(user.chat_id),
+                    word::status::in_vec(statuses.clone()),
+                    or(vec![
+                        word::deck_id::equals(None),
+                        word::deck::is(vec![deck::reminders_enabled::equals(true)]),
+                    ]),
+                ])])
+                .with(card::word::fetch())
+                .order_by(card::next_remind_at::order(Direction::Asc))
+                .skip(skip)
+                .take(NEXT_DUE_PAGE_SIZE)
+                .exec()
+                .await?;
+            for card in cards.iter() {
+                let word = card.word.as_ref().ok_or(StorageError::NotFetched("word"))?;
+                let directions = word
+                    .deck_id
+                    .as_ref()
+                    .and_then(|id| deck_directions.get(id).copied())
+                    .unwrap_or(user.directions);
+                let direction = CardDirection::from_key(&card.direction)
+                    .map_err(|_| StorageError::UnknownDirection(card.direction.clone()))?;
+                if directions.includes(direction) {
+                    return Ok(Some(card.next_remind_at));
+                }
+            }
+            if (cards.len() as i64) < NEXT_DUE_PAGE_SIZE {
+                return Ok(None);
+            }
+            skip += NEXT_DUE_PAGE_SIZE;
+        }
+    }
+    /* #endregion */
+
+    /* #region Review model */
+    async fn add_review(&self, review: NewReview) -> StorageResult<Review> {
+        let review = self
+            .review()
+            .create(
+                review.chat_id,
+                review.outcome.key().to_owned(),
+                review.level_before,
+                review.level_after,
+                word::id::equals(review.word_id),
+                vec![
+                    review::direction::set(review.direction.key().to_owned()),
+                    review::answer::set(review.answer),
+                    review::latency::set(review.latency),
+                ],
+            )
+            .exec()
+            .await?;
+        review.try_into()
+    }
+
+    async fn get_word_reviews(&self, word_id: &str) -> StorageResult<Vec<Review>> {
+        self.review()
+            .find_many(vec![review::word_id::equals(word_id.to_owned())])
+            .order_by(review::created_at::order(Direction::Asc))
+            .exec()
+            .await?
+            .into_iter()
+            .map(Review::try_from)
+            .collect()
+    }
+
+    async fn get_user_reviews(
+        &self,
+        chat_id: i64,
+        skip: i64,
+        take: i64,
+    ) -> StorageResult<Vec<Review>> {
+        self.review()
+            .find_many(vec![review::chat_id::equals(chat_id)])
+            .order_by(review::created_at::order(Direction::Desc))
+            .skip(skip)
+            .take(take)
+            .exec()
+            .await?
+            .into_iter()
+            .map(Review::try_from)
+            .collect()
+    }
+    /* #endregion */
+
+    /* #region Challenge model */
+    async fn new_challenge(&self, challenge: &Challenge) -> StorageResult<()> {
+        let exists = self
+            .challenge()
+            .find_unique(challenge::inline_message_id::equals(
+                challenge.inline_message_id.clone(),
+            ))
+            .exec()
+            .await?
+            .is_some();
+        if exists {
+            return Err(StorageError::ChallengeAlreadyExists);
+        }
+
+        self.challenge()
+            .create(
+                challenge.inline_message_id.clone(),
+                challenge.chat_id,
+                challenge.word.clone(),
+                challenge.options.join("\n"),
+                challenge.correct as i32,
+                vec![],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+
+    async fn get_challenge(&self, inline_message_id: &str) -> StorageResult<Option<Challenge>> {
+        self.challenge()
+            .find_unique(challenge::inline_message_id::equals(
+                inline_message_id.to_owned(),
+            ))
+            .with(
+                challenge::answers::fetch(vec![])
+                    .order_by(challenge_answer::created_at::order(Direction::Asc)),
+            )
+            .exec()
+            .await?
+            .map(Challenge::try_from)
+            .transpose()
+    }
+
+    async fn add_challenge_answer(
+        &self,
+        inline_message_id: &str,
+        answer: &ChallengeAnswer,
+    ) -> StorageResult<()> {
+        let exists = self
+            .challenge()
+            .find_unique(challenge::inline_message_id::equals(
+                inline_message_id.to_owned(),
+            ))
+            .exec()
+            .await?
+            .is_some();
+        if !exists {
+            return Err(StorageError::ChallengeNotFound);
+        }
+        let answered = self
+            .challenge_answer()
+            .find_first(vec![
+                challenge_answer::inline_message_id::equals(inline_message_id.to_owned()),
+                challenge_answer::user_id::equals(answer.user_id),
+            ])
+            .exec()
+            .await?;
+        if answered.is_some() {
+            return Err(StorageError::ChallengeAlreadyAnswered);
+        }
+
+        self.challenge_answer()
+            .create(
+                answer.user_id,
+                answer.name.clone(),
+                answer.correct,
+                challenge::inline_message_id::equals(inline_message_id.to_owned()),
+                vec![],
+            )
+            .exec()
+            .await?;
+        Ok(())
+    }
+    /* #endregion */
+}
@@ -7,6 +7,22 @@ pub enum StorageError {
     DatabaseQueryError(#[from] QueryError),
     #[error("Word already exists")]
     WordAlreadyExists,
+    #[error("Deck already exists")]
+    DeckAlreadyExists,
+    #[error("Challenge already exists")]
+    ChallengeAlreadyExists,
+    #[error("Challenge not found")]
+    ChallengeNotFound,
+    #[error("Challenge already answered")]
+    ChallengeAlreadyAnswered,
+    #[error("Unknown review outcome: `{0}`")]
+    UnknownReviewOutcome(String),
+    #[error("Unknown word status: `{0}`")]
+    UnknownWordStatus(String),
+    #[error("Unknown review direction: `{0}`")]
+    UnknownDirection(String),
+    #[error("Relation `{0}` was not fetched")]
+    NotFetched(&'static str),
 }
 
 pub type StorageResult<T> = std::result::Result<T, StorageError>;
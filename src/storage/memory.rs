@@ -0,0 +1,1171 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use chrono_tz::Tz;
+
+use crate::{
+    common::{
+        config::DEFAULT_SCHEDULER,
+        local_time::QuietHours,
+        normalize,
+        translation::{Strictness, Translation},
+        AsyncMutex,
+    },
+    scheduler::{order::WordOrder, Progress},
+};
+
+use super::{
+    error::{StorageError, StorageResult},
+    merge_tags, now, word_next_remind_at, Card, Challenge, ChallengeAnswer, Deck, Direction,
+    Directions, DueCard, DuplicatePolicy, ImportSummary, LiteUser, NewReview, NewWord, RemindTime,
+    Review, ReviewMode, Storage, UserWithCards, Word, WordFilter, WordStatus,
+};
+
+#[derive(Debug, Default)]
+struct Data {
+    users: HashMap<i64, LiteUser>,
+    words: HashMap<String, Word>,
+    decks: HashMap<String, Deck>,
+    reviews: Vec<Review>,
+    challenges: HashMap<String, Challenge>,
+    last_id: u64,
+}
+
+impl Data {
+    fn next_id(&mut self) -> String {
+        self.last_id += 1;
+        format!("mem{}", self.last_id)
+    }
+
+    // words of the user ordered the same way as the database returns them
+    fn user_words(&self, chat_id: i64, filter: &WordFilter) -> Vec<&Word> {
+        let mut words: Vec<&Word> = self
+            .words
+            .values()
+            .filter(|w| w.chat_id == chat_id && filter.matches(w))
+            .collect();
+        words.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        words
+    }
+
+    fn due_cards(&self, user: &LiteUser, now: DateTime<FixedOffset>) -> Vec<DueCard> {
+        self.reminded_cards(user)
+            .into_iter()
+            .filter(|due| due.card.next_remind_at <= now)
+            .collect()
+    }
+
+    // cards of the user whenever they are due, see `Storage::find_to_remind` for the filters
+    fn reminded_cards(&self, user: &LiteUser) -> Vec<DueCard> {
+        self.user_words(user.chat_id, &WordFilter::default())
+            .into_iter()
+            .filter(|w| match w.status {
+                WordStatus::Active => true,
+                WordStatus::Mastered => user.refresh_mastered,
+                WordStatus::Suspended => false,
+            })
+            .filter_map(|w| {
+                let deck = w.deck_id.as_ref().and_then(|id| self.decks.get(id));
+                if deck.is_some_and(|deck| !deck.reminders_enabled) {
+                    return None;
+                }
+                let directions = deck
+                    .and_then(|deck| deck.directions)
+                    .unwrap_or(user.directions);
+                Some(w.cards_in(directions))
+            })
+            .flatten()
+            .collect()
+    }
+
+    fn insert_word(&mut self, chat_id: i64, new_word: &NewWord) -> Word {
+        let word = Word {
+            id: self.next_id(),
+            chat_id,
+            created_at: now(),
+            word: normalize::clean(&new_word.word),
+            translations: new_word.translation.clone(),
+            cards: new_word.cards(),
+            status: WordStatus::Active,
+            deck_id: new_word.deck_id.clone(),
+            tags: new_word.tags.clone(),
+        };
+        self.words.insert(word.id.clone(), word.clone());
+        word
+    }
+
+    fn find_deck(&self, chat_id: i64, name: &str) -> Option<&Deck> {
+        self.decks
+            .values()
+            .find(|d| d.chat_id == chat_id && d.name == name)
+    }
+
+    fn ensure_user(&mut self, chat_id: i64) -> LiteUser {
+        self.users
+            .entry(chat_id)
+            .or_insert_with(|| new_user(chat_id))
+            .clone()
+    }
+}
+
+fn is_same_word(word: &Word, chat_id: i64, key: &str) -> bool {
+    word.chat_id == chat_id && normalize::key(&word.word) == key
+}
+
+fn new_user(chat_id: i64) -> LiteUser {
+    LiteUser {
+        chat_id,
+        next_remind_at: now(),
+        remind_every: 1800,
+        scheduler: DEFAULT_SCHEDULER.to_owned(),
+        refresh_mastered: true,
+        active_deck_id: None,
+        review_mode: ReviewMode::Typing,
+        strictness: Strictness::Lenient,
+        directions: Directions::Forward,
+        word_order: WordOrder::MostOverdue,
+        timezone: Tz::UTC,
+        quiet_hours: None,
+        words_per_page: 5,
+        silent: false,
+    }
+}
+
+/// Storage which keeps everything in memory, data is lost on restart.
+/// Useful for tests and running the bot without a database.
+#[derive(Debug, Default)]
+pub struct MemoryStorage(AsyncMutex<Data>);
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    /* #region User model */
+    async fn get_user(&self, chat_id: i64) -> StorageResult<Option<LiteUser>> {
+        let data = self.0.lock().await;
+        Ok(data.users.get(&chat_id).cloned())
+    }
+
+    async fn new_user(&self, chat_id: i64) -> StorageResult<LiteUser> {
+        let mut data = self.0.lock().await;
+        let user = new_user(chat_id);
+        data.users.insert(chat_id, user.clone());
+        Ok(user)
+    }
+
+    async fn update_next_remind_user(
+        &self,
+        id: i64,
+        remind_every: i64,
+    ) -> StorageResult<DateTime<FixedOffset>> {
+        let next_remind_at = now() + chrono::Duration::seconds(remind_every);
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&id) {
+            user.next_remind_at = next_remind_at;
+        }
+        Ok(next_remind_at)
+    }
+
+    async fn get_remind_times(
+        &self,
+        after: Option<RemindTime>,
+        take: i64,
+    ) -> StorageResult<Vec<RemindTime>> {
+        let data = self.0.lock().await;
+        let mut times: Vec<RemindTime> = data
+            .users
+            .values()
+            .map(|u| RemindTime {
+                next_remind_at: u.next_remind_at,
+                chat_id: u.chat_id,
+            })
+            .filter(|time| after.is_none_or(|after| *time > after))
+            .collect();
+        times.sort();
+        times.truncate(take as usize);
+        Ok(times)
+    }
+
+    async fn set_refresh_mastered(&self, chat_id: i64, enabled: bool) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.refresh_mastered = enabled;
+        }
+        Ok(())
+    }
+
+    async fn set_scheduler(&self, chat_id: i64, scheduler: &str) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.scheduler = scheduler.to_owned();
+        }
+        Ok(())
+    }
+
+    async fn set_remind_every(&self, chat_id: i64, remind_every: i32) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.remind_every = remind_every;
+        }
+        Ok(())
+    }
+
+    async fn set_words_per_page(&self, chat_id: i64, words_per_page: i32) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.words_per_page = words_per_page;
+        }
+        Ok(())
+    }
+
+    async fn set_silent(&self, chat_id: i64, silent: bool) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.silent = silent;
+        }
+        Ok(())
+    }
+
+    async fn set_review_mode(&self, chat_id: i64, mode: ReviewMode) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.review_mode = mode;
+        }
+        Ok(())
+    }
+
+    async fn set_strictness(&self, chat_id: i64, strictness: Strictness) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.strictness = strictness;
+        }
+        Ok(())
+    }
+
+    async fn set_directions(&self, chat_id: i64, directions: Directions) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.directions = directions;
+        }
+        Ok(())
+    }
+
+    async fn set_word_order(&self, chat_id: i64, order: WordOrder) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.word_order = order;
+        }
+        Ok(())
+    }
+
+    async fn set_timezone(&self, chat_id: i64, timezone: Tz) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.timezone = timezone;
+        }
+        Ok(())
+    }
+
+    async fn set_quiet_hours(
+        &self,
+        chat_id: i64,
+        quiet_hours: Option<QuietHours>,
+    ) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.quiet_hours = quiet_hours;
+        }
+        Ok(())
+    }
+
+    async fn set_active_deck(&self, chat_id: i64, deck_id: Option<&str>) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(user) = data.users.get_mut(&chat_id) {
+            user.active_deck_id = deck_id.map(str::to_owned);
+        }
+        Ok(())
+    }
+    /* #endregion */
+
+    /* #region Deck model */
+    async fn new_deck(
+        &self,
+        chat_id: i64,
+        name: &str,
+        from_lang: Option<String>,
+        to_lang: Option<String>,
+    ) -> StorageResult<Deck> {
+        let mut data = self.0.lock().await;
+        if data.find_deck(chat_id, name).is_some() {
+            return Err(StorageError::DeckAlreadyExists);
+        }
+
+        data.ensure_user(chat_id);
+        let deck = Deck {
+            id: data.next_id(),
+            chat_id,
+            created_at: now(),
+            name: name.to_owned(),
+            from_lang,
+            to_lang,
+            reminders_enabled: true,
+            directions: None,
+        };
+        data.decks.insert(deck.id.clone(), deck.clone());
+        Ok(deck)
+    }
+
+    async fn get_decks(&self, chat_id: i64) -> StorageResult<Vec<Deck>> {
+        let data = self.0.lock().await;
+        let mut decks: Vec<Deck> = data
+            .decks
+            .values()
+            .filter(|d| d.chat_id == chat_id)
+            .cloned()
+            .collect();
+        decks.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        Ok(decks)
+    }
+
+    async fn find_deck(&self, chat_id: i64, name: &str) -> StorageResult<Option<Deck>> {
+        let data = self.0.lock().await;
+        Ok(data.find_deck(chat_id, name).cloned())
+    }
+
+    async fn rename_deck(&self, chat_id: i64, deck_id: &str, name: &str) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if data
+            .find_deck(chat_id, name)
+            .is_some_and(|deck| deck.id != deck_id)
+        {
+            return Err(StorageError::DeckAlreadyExists);
+        }
+
+        if let Some(deck) = data.decks.get_mut(deck_id) {
+            deck.name = name.to_owned();
+        }
+        Ok(())
+    }
+
+    async fn remove_deck(&self, deck_id: &str) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        data.decks.remove(deck_id);
+        for word in data.words.values_mut() {
+            if word.deck_id.as_deref() == Some(deck_id) {
+                word.deck_id = None;
+            }
+        }
+        for user in data.users.values_mut() {
+            if user.active_deck_id.as_deref() == Some(deck_id) {
+                user.active_deck_id = None;
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_deck_reminders(&self, deck_id: &str, enabled: bool) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(deck) = data.decks.get_mut(deck_id) {
+            deck.reminders_enabled = enabled;
+        }
+        Ok(())
+    }
+
+    async fn set_deck_directions(
+        &self,
+        deck_id: &str,
+        directions: Option<Directions>,
+    ) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(deck) = data.decks.get_mut(deck_id) {
+            deck.directions = directions;
+        }
+        Ok(())
+    }
+    /* #endregion */
+
+    /* #region Word model */
+    async fn new_word(
+        &self,
+        chat_id: i64,
+        word: &str,
+        translation: &Translation,
+        deck_id: Option<&str>,
+    ) -> StorageResult<Word> {
+        let mut data = self.0.lock().await;
+        let key = normalize::key(word);
+        let exists = data.words.values().any(|w| is_same_word(w, chat_id, &key));
+        if exists {
+            return Err(StorageError::WordAlreadyExists);
+        }
+
+        data.ensure_user(chat_id);
+        let new_word = NewWord {
+            word: word.to_owned(),
+            translation: translation.clone(),
+            deck_id: deck_id.map(str::to_owned),
+            ..Default::default()
+        };
+        Ok(data.insert_word(chat_id, &new_word))
+    }
+
+    async fn new_words(&self, chat_id: i64, words: &[NewWord]) -> StorageResult<Vec<Word>> {
+        let mut data = self.0.lock().await;
+        data.ensure_user(chat_id);
+        let mut added = vec![];
+        for new_word in words {
+            let key = normalize::key(&new_word.word);
+            if data.words.values().any(|w| is_same_word(w, chat_id, &key)) {
+                continue;
+            }
+            added.push(data.insert_word(chat_id, new_word));
+        }
+        Ok(added)
+    }
+
+    async fn import_words(
+        &self,
+        chat_id: i64,
+        words: &[NewWord],
+        policy: DuplicatePolicy,
+    ) -> StorageResult<ImportSummary> {
+        let mut data = self.0.lock().await;
+        data.ensure_user(chat_id);
+        let mut summary = ImportSummary::default();
+        for new_word in words {
+            let key = normalize::key(&new_word.word);
+            let existing = data
+                .words
+                .values_mut()
+                .find(|w| is_same_word(w, chat_id, &key));
+            match (existing, policy) {
+                (None, _) => {
+                    data.insert_word(chat_id, new_word);
+                    summary.added += 1;
+                }
+                (Some(_), DuplicatePolicy::Skip) => summary.skipped += 1,
+                (Some(word), DuplicatePolicy::Merge) => {
+                    word.translations = word.translations.merge(&new_word.translation);
+                    word.tags = merge_tags(&word.tags, &new_word.tags);
+                    summary.merged += 1;
+                }
+                (Some(word), DuplicatePolicy::Overwrite) => {
+                    word.translations = new_word.translation.clone();
+                    word.tags = new_word.tags.clone();
+                    word.deck_id = new_word.deck_id.clone();
+                    summary.overwritten += 1;
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    async fn has_word(&self, chat_id: i64, word: &str) -> StorageResult<bool> {
+        let data = self.0.lock().await;
+        let key = normalize::key(word);
+        Ok(data.words.values().any(|w| is_same_word(w, chat_id, &key)))
+    }
+
+    async fn find_word(&self, chat_id: i64, word: &str) -> StorageResult<Option<Word>> {
+        let data = self.0.lock().await;
+        let key = normalize::key(word);
+        Ok(data
+            .words
+            .values()
+            .find(|w| is_same_word(w, chat_id, &key))
+            .cloned())
+    }
+
+    async fn rename_word(&self, chat_id: i64, word_id: &str, word: &str) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        let key = normalize::key(word);
+        let exists = data
+            .words
+            .values()
+            .any(|w| w.id != word_id && is_same_word(w, chat_id, &key));
+        if exists {
+            return Err(StorageError::WordAlreadyExists);
+        }
+        if let Some(w) = data.words.get_mut(word_id) {
+            w.word = normalize::clean(word);
+        }
+        Ok(())
+    }
+
+    async fn set_translations(
+        &self,
+        word_id: &str,
+        translation: &Translation,
+    ) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(word) = data.words.get_mut(word_id) {
+            word.translations = translation.clone();
+        }
+        Ok(())
+    }
+
+    async fn reset_word_progress(&self, word_id: &str) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(word) = data.words.get_mut(word_id) {
+            word.cards = Direction::ALL.into_iter().map(Card::new).collect();
+            word.status = WordStatus::Active;
+        }
+        Ok(())
+    }
+
+    async fn get_words(
+        &self,
+        chat_id: i64,
+        filter: &WordFilter,
+        skip: i64,
+        take: i64,
+    ) -> StorageResult<Vec<Word>> {
+        let data = self.0.lock().await;
+        Ok(data
+            .user_words(chat_id, filter)
+            .into_iter()
+            .skip(skip as usize)
+            .take(take as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn words_count(&self, chat_id: i64, filter: &WordFilter) -> StorageResult<i64> {
+        let data = self.0.lock().await;
+        Ok(data.user_words(chat_id, filter).len() as i64)
+    }
+
+    async fn remove_word(&self, chat_id: i64, word: &str) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        let key = normalize::key(word);
+        data.words.retain(|_, w| !is_same_word(w, chat_id, &key));
+        let Data { words, reviews, .. } = &mut *data;
+        reviews.retain(|r| words.contains_key(&r.word_id));
+        Ok(())
+    }
+
+    async fn update_word_remind(
+        &self,
+        word_id: &str,
+        direction: Direction,
+        progress: &Progress,
+        status: WordStatus,
+    ) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if let Some(word) = data.words.get_mut(word_id) {
+            if let Some(card) = word.cards.iter_mut().find(|c| c.direction == direction) {
+                card.progress = *progress;
+                card.next_remind_at = word_next_remind_at(progress, status);
+                card.reminded_at = now();
+            }
+            word.status = status;
+        }
+        Ok(())
+    }
+
+    async fn set_word_status(
+        &self,
+        chat_id: i64,
+        word: &str,
+        status: WordStatus,
+    ) -> StorageResult<bool> {
+        let mut data = self.0.lock().await;
+        let key = normalize::key(word);
+        let mut updated = false;
+        for w in data.words.values_mut() {
+            if is_same_word(w, chat_id, &key) {
+                // words returned to learning are due right away instead of in months
+                if status == WordStatus::Active && w.status != WordStatus::Active {
+                    for card in w.cards.iter_mut() {
+                        card.next_remind_at = now();
+                    }
+                }
+                w.status = status;
+                updated = true;
+            }
+        }
+        Ok(updated)
+    }
+
+    async fn find_to_remind(&self, chat_ids: &[i64]) -> StorageResult<Vec<UserWithCards>> {
+        let now = now();
+        let data = self.0.lock().await;
+        let mut users: Vec<&LiteUser> = chat_ids
+            .iter()
+            .filter_map(|chat_id| data.users.get(chat_id))
+            .collect();
+        users.sort_by_key(|user| user.next_remind_at);
+        let users: Vec<UserWithCards> = users
+            .into_iter()
+            .map(|user| UserWithCards {
+                user: user.clone(),
+                cards: if user.next_remind_at <= now {
+                    data.due_cards(user, now)
+                } else {
+                    vec![]
+                },
+            })
+            .collect();
+
+        log::debug!("Found {} users to remind", users.len());
+
+        Ok(users)
+    }
+
+    async fn find_due_cards(&self, chat_id: i64) -> StorageResult<Vec<DueCard>> {
+        let data = self.0.lock().await;
+        let Some(user) = data.users.get(&chat_id) else {
+            return Ok(vec![]);
+        };
+        let mut cards = data.due_cards(user, now());
+        cards.sort_by_key(|c| c.card.next_remind_at);
+        Ok(cards)
+    }
+
+    async fn next_due_at(&self, user: &LiteUser) -> StorageResult<Option<DateTime<FixedOffset>>> {
+        let data = self.0.lock().await;
+        Ok(data
+            .reminded_cards(user)
+            .iter()
+            .map(|due| due.card.next_remind_at)
+            .min())
+    }
+    /* #endregion */
+
+    /* #region Review model */
+    async fn add_review(&self, review: NewReview) -> StorageResult<Review> {
+        let mut data = self.0.lock().await;
+        let review = Review {
+            id: data.next_id(),
+            created_at: now(),
+            word_id: review.word_id,
+            chat_id: review.chat_id,
+            outcome: review.outcome,
+            direction: review.direction,
+            level_before: review.level_before,
+            level_after: review.level_after,
+            answer: review.answer,
+            latency: review.latency,
+        };
+        data.reviews.push(review.clone());
+        Ok(review)
+    }
+
+    async fn get_word_reviews(&self, word_id: &str) -> StorageResult<Vec<Review>> {
+        let data = self.0.lock().await;
+        Ok(data
+            .reviews
+            .iter()
+            .filter(|r| r.word_id == word_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_user_reviews(
+        &self,
+        chat_id: i64,
+        skip: i64,
+        take: i64,
+    ) -> StorageResult<Vec<Review>> {
+        let data = self.0.lock().await;
+        Ok(data
+            .reviews
+            .iter()
+            .rev()
+            .filter(|r| r.chat_id == chat_id)
+            .skip(skip as usize)
+            .take(take as usize)
+            .cloned()
+            .collect())
+    }
+    /* #endregion */
+
+    /* #region Challenge model */
+    async fn new_challenge(&self, challenge: &Challenge) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        if data.challenges.contains_key(&challenge.inline_message_id) {
+            return Err(StorageError::ChallengeAlreadyExists);
+        }
+        data.challenges
+            .insert(challenge.inline_message_id.clone(), challenge.clone());
+        Ok(())
+    }
+
+    async fn get_challenge(&self, inline_message_id: &str) -> StorageResult<Option<Challenge>> {
+        let data = self.0.lock().await;
+        Ok(data.challenges.get(inline_message_id).cloned())
+    }
+
+    async fn add_challenge_answer(
+        &self,
+        inline_message_id: &str,
+        answer: &ChallengeAnswer,
+    ) -> StorageResult<()> {
+        let mut data = self.0.lock().await;
+        let challenge = data
+            .challenges
+            .get_mut(inline_message_id)
+            .ok_or(StorageError::ChallengeNotFound)?;
+        if challenge
+            .answers
+            .iter()
+            .any(|a| a.user_id == answer.user_id)
+        {
+            return Err(StorageError::ChallengeAlreadyAnswered);
+        }
+        challenge.answers.push(answer.clone());
+        Ok(())
+    }
+    /* #endregion */
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::ReviewOutcome;
+
+    use super::*;
+
+    static CHAT_ID: i64 = 1;
+
+    async fn add(db: &MemoryStorage, word: &str, translation: &str) -> Word {
+        db.new_word(CHAT_ID, word, &translation.into(), None)
+            .await
+            .unwrap()
+    }
+
+    // the card of the direction is due now
+    async fn make_due(db: &MemoryStorage, word: &Word, direction: Direction) {
+        db.update_word_remind(&word.id, direction, &Progress::default(), word.status)
+            .await
+            .unwrap();
+    }
+
+    async fn due_words(db: &MemoryStorage) -> Vec<(String, Direction)> {
+        db.find_due_cards(CHAT_ID)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.word.word, c.card.direction))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn users_are_created_once() {
+        let db = MemoryStorage::new();
+        assert!(db.get_user(CHAT_ID).await.unwrap().is_none());
+        db.ensure_user(CHAT_ID).await.unwrap();
+        db.set_remind_every(CHAT_ID, 3600).await.unwrap();
+        let user = db.ensure_user(CHAT_ID).await.unwrap();
+        assert_eq!(user.remind_every, 3600);
+        assert_eq!(user.scheduler, DEFAULT_SCHEDULER);
+    }
+
+    #[tokio::test]
+    async fn words_are_compared_normalized() {
+        let db = MemoryStorage::new();
+        let word = add(&db, " Der  Hund ", "dog").await;
+        assert_eq!(word.word, "Der Hund");
+        assert_eq!(word.cards.len(), Direction::ALL.len());
+
+        assert!(db.has_word(CHAT_ID, "der hund").await.unwrap());
+        assert!(!db.has_word(CHAT_ID + 1, "der hund").await.unwrap());
+        assert!(matches!(
+            db.new_word(CHAT_ID, "DER HUND", &"hound".into(), None)
+                .await,
+            Err(StorageError::WordAlreadyExists)
+        ));
+        // another user can have the same word
+        db.new_word(CHAT_ID + 1, "der Hund", &"dog".into(), None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn words_are_updated_and_removed() {
+        let db = MemoryStorage::new();
+        let hund = add(&db, "Hund", "dog").await;
+        add(&db, "Katze", "cat").await;
+
+        assert!(matches!(
+            db.rename_word(CHAT_ID, &hund.id, "katze").await,
+            Err(StorageError::WordAlreadyExists)
+        ));
+        // a word can be renamed to its own text in another case
+        db.rename_word(CHAT_ID, &hund.id, "HUND").await.unwrap();
+        db.set_translations(&hund.id, &"dog, hound".into())
+            .await
+            .unwrap();
+        let found = db.find_word(CHAT_ID, "hund").await.unwrap().unwrap();
+        assert_eq!(found.word, "HUND");
+        assert_eq!(found.translations.to_plain_string(), "dog, hound");
+
+        db.add_review(NewReview {
+            word_id: hund.id.clone(),
+            chat_id: CHAT_ID,
+            outcome: ReviewOutcome::Correct,
+            direction: Direction::Forward,
+            level_before: 0,
+            level_after: 1,
+            answer: Some("dog".to_owned()),
+            latency: None,
+        })
+        .await
+        .unwrap();
+        db.remove_word(CHAT_ID, "hund").await.unwrap();
+        assert!(db.find_word(CHAT_ID, "hund").await.unwrap().is_none());
+        assert!(db.get_word_reviews(&hund.id).await.unwrap().is_empty());
+        assert_eq!(
+            db.words_count(CHAT_ID, &WordFilter::default())
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn words_are_filtered_and_paged() {
+        let db = MemoryStorage::new();
+        let deck = db.new_deck(CHAT_ID, "Animals", None, None).await.unwrap();
+        for (word, translation) in [("Hund", "dog"), ("Katze", "cat"), ("Haus", "house")] {
+            add(&db, word, translation).await;
+        }
+        db.new_word(CHAT_ID, "Maus", &"mouse".into(), Some(&deck.id))
+            .await
+            .unwrap();
+        db.set_word_status(CHAT_ID, "katze", WordStatus::Suspended)
+            .await
+            .unwrap();
+
+        let page = db
+            .get_words(CHAT_ID, &WordFilter::default(), 1, 2)
+            .await
+            .unwrap();
+        assert_eq!(
+            page.iter().map(|w| w.word.as_str()).collect::<Vec<_>>(),
+            ["Katze", "Haus"]
+        );
+
+        let active = WordFilter::with_statuses(&[WordStatus::Active]);
+        assert_eq!(db.words_count(CHAT_ID, &active).await.unwrap(), 3);
+        let search = WordFilter {
+            search: Some("OUS".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(db.words_count(CHAT_ID, &search).await.unwrap(), 2);
+        let in_deck = WordFilter {
+            deck_id: Some(deck.id.clone()),
+            ..Default::default()
+        };
+        assert_eq!(db.words_count(CHAT_ID, &in_deck).await.unwrap(), 1);
+    }
+
+    // PrismaStorage stores `normalize::key` of translations and searches it
+    // by the key of the query, which is what `WordFilter::matches` does
+    #[tokio::test]
+    async fn translations_are_searched_by_normalized_key() {
+        let db = MemoryStorage::new();
+        add(&db, "Sommer", "été").await;
+        add(&db, "Straße", "street").await;
+        add(&db, "Hund", "dog").await;
+
+        for (search, count) in [
+            ("ÉTÉ", 1),
+            ("e\u{301}te\u{301}", 1),
+            ("STRASSE", 1),
+            ("STREET", 1),
+            ("ete", 0),
+        ] {
+            let filter = WordFilter {
+                search: Some(search.to_owned()),
+                ..Default::default()
+            };
+            assert_eq!(
+                db.words_count(CHAT_ID, &filter).await.unwrap(),
+                count,
+                "{}",
+                search
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn due_cards_follow_status_and_directions() {
+        let db = MemoryStorage::new();
+        let hund = add(&db, "Hund", "dog").await;
+        let katze = add(&db, "Katze", "cat").await;
+        assert!(due_words(&db).await.is_empty());
+
+        make_due(&db, &hund, Direction::Forward).await;
+        make_due(&db, &hund, Direction::Reverse).await;
+        make_due(&db, &katze, Direction::Forward).await;
+        assert_eq!(
+            due_words(&db).await,
+            [
+                ("Hund".to_owned(), Direction::Forward),
+                ("Katze".to_owned(), Direction::Forward)
+            ]
+        );
+
+        db.set_directions(CHAT_ID, Directions::Both).await.unwrap();
+        assert_eq!(due_words(&db).await.len(), 3);
+        db.set_directions(CHAT_ID, Directions::Reverse)
+            .await
+            .unwrap();
+        assert_eq!(
+            due_words(&db).await,
+            [("Hund".to_owned(), Direction::Reverse)]
+        );
+
+        db.set_word_status(CHAT_ID, "hund", WordStatus::Suspended)
+            .await
+            .unwrap();
+        assert!(due_words(&db).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn next_due_card_follows_filters() {
+        let db = MemoryStorage::new();
+        let user = db.ensure_user(CHAT_ID).await.unwrap();
+        assert!(db.next_due_at(&user).await.unwrap().is_none());
+
+        let hund = add(&db, "Hund", "dog").await;
+        let forward_at = hund
+            .cards
+            .iter()
+            .find(|card| card.direction == Direction::Forward)
+            .unwrap()
+            .next_remind_at;
+        make_due(&db, &hund, Direction::Reverse).await;
+        assert_eq!(db.next_due_at(&user).await.unwrap(), Some(forward_at));
+
+        db.set_directions(CHAT_ID, Directions::Both).await.unwrap();
+        let user = db.ensure_user(CHAT_ID).await.unwrap();
+        assert!(db.next_due_at(&user).await.unwrap().unwrap() <= now());
+
+        db.set_word_status(CHAT_ID, "hund", WordStatus::Suspended)
+            .await
+            .unwrap();
+        assert!(db.next_due_at(&user).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn mastered_words_are_refreshed_rarely() {
+        let db = MemoryStorage::new();
+        let hund = add(&db, "Hund", "dog").await;
+        db.update_word_remind(
+            &hund.id,
+            Direction::Forward,
+            &Progress::default(),
+            WordStatus::Mastered,
+        )
+        .await
+        .unwrap();
+        assert!(due_words(&db).await.is_empty());
+
+        // activated words are due right away
+        assert!(db
+            .set_word_status(CHAT_ID, "hund", WordStatus::Active)
+            .await
+            .unwrap());
+        assert_eq!(due_words(&db).await.len(), 1);
+        assert!(!db
+            .set_word_status(CHAT_ID, "katze", WordStatus::Active)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn due_cards_follow_decks() {
+        let db = MemoryStorage::new();
+        let deck = db.new_deck(CHAT_ID, "Animals", None, None).await.unwrap();
+        let hund = db
+            .new_word(CHAT_ID, "Hund", &"dog".into(), Some(&deck.id))
+            .await
+            .unwrap();
+        make_due(&db, &hund, Direction::Forward).await;
+        make_due(&db, &hund, Direction::Reverse).await;
+
+        db.set_deck_directions(&deck.id, Some(Directions::Reverse))
+            .await
+            .unwrap();
+        assert_eq!(
+            due_words(&db).await,
+            [("Hund".to_owned(), Direction::Reverse)]
+        );
+        db.set_deck_reminders(&deck.id, false).await.unwrap();
+        assert!(due_words(&db).await.is_empty());
+
+        // words of a removed deck follow the user's settings
+        db.remove_deck(&deck.id).await.unwrap();
+        assert_eq!(
+            due_words(&db).await,
+            [("Hund".to_owned(), Direction::Forward)]
+        );
+    }
+
+    #[tokio::test]
+    async fn users_are_reminded_when_due() {
+        let db = MemoryStorage::new();
+        let hund = add(&db, "Hund", "dog").await;
+        make_due(&db, &hund, Direction::Forward).await;
+        db.ensure_user(CHAT_ID + 1).await.unwrap();
+        db.update_next_remind_user(CHAT_ID + 1, 3600).await.unwrap();
+
+        let users = db
+            .find_to_remind(&[CHAT_ID + 1, CHAT_ID, CHAT_ID + 2])
+            .await
+            .unwrap();
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].user.chat_id, CHAT_ID);
+        assert_eq!(users[0].cards.len(), 1);
+        assert!(users[1].cards.is_empty());
+
+        let times = db.get_remind_times(None, 1).await.unwrap();
+        assert_eq!(times.len(), 1);
+        assert_eq!(times[0].chat_id, CHAT_ID);
+        let times = db.get_remind_times(Some(times[0]), 10).await.unwrap();
+        assert_eq!(times.len(), 1);
+        assert_eq!(times[0].chat_id, CHAT_ID + 1);
+    }
+
+    #[tokio::test]
+    async fn decks_have_unique_names() {
+        let db = MemoryStorage::new();
+        let animals = db.new_deck(CHAT_ID, "Animals", None, None).await.unwrap();
+        db.new_deck(CHAT_ID, "Food", None, None).await.unwrap();
+        assert!(matches!(
+            db.new_deck(CHAT_ID, "Animals", None, None).await,
+            Err(StorageError::DeckAlreadyExists)
+        ));
+        assert!(matches!(
+            db.rename_deck(CHAT_ID, &animals.id, "Food").await,
+            Err(StorageError::DeckAlreadyExists)
+        ));
+        db.rename_deck(CHAT_ID, &animals.id, "Animals")
+            .await
+            .unwrap();
+        db.rename_deck(CHAT_ID, &animals.id, "Pets").await.unwrap();
+        let names: Vec<String> = db
+            .get_decks(CHAT_ID)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|d| d.name)
+            .collect();
+        assert_eq!(names, ["Pets", "Food"]);
+    }
+
+    #[tokio::test]
+    async fn import_follows_duplicate_policy() {
+        let db = MemoryStorage::new();
+        add(&db, "Hund", "dog").await;
+        let words = [
+            NewWord {
+                word: "hund".to_owned(),
+                translation: "hound".into(),
+                tags: vec!["animals".to_owned()],
+                ..Default::default()
+            },
+            NewWord {
+                word: "Katze".to_owned(),
+                translation: "cat".into(),
+                ..Default::default()
+            },
+        ];
+
+        let summary = db
+            .import_words(CHAT_ID, &words, DuplicatePolicy::Skip)
+            .await
+            .unwrap();
+        assert_eq!((summary.added, summary.skipped), (1, 1));
+        let summary = db
+            .import_words(CHAT_ID, &words, DuplicatePolicy::Merge)
+            .await
+            .unwrap();
+        assert_eq!((summary.added, summary.merged), (0, 2));
+        let hund = db.find_word(CHAT_ID, "hund").await.unwrap().unwrap();
+        assert_eq!(hund.translations.to_plain_string(), "dog, hound");
+        assert_eq!(hund.tags, ["animals"]);
+
+        let summary = db
+            .import_words(CHAT_ID, &words[..1], DuplicatePolicy::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(summary.overwritten, 1);
+        let hund = db.find_word(CHAT_ID, "hund").await.unwrap().unwrap();
+        assert_eq!(hund.translations.to_plain_string(), "hound");
+    }
+
+    #[tokio::test]
+    async fn user_reviews_from_newest() {
+        let db = MemoryStorage::new();
+        let hund = add(&db, "Hund", "dog").await;
+        for level_after in 1..=3 {
+            db.add_review(NewReview {
+                word_id: hund.id.clone(),
+                chat_id: CHAT_ID,
+                outcome: ReviewOutcome::Good,
+                direction: Direction::Forward,
+                level_before: level_after - 1,
+                level_after,
+                answer: None,
+                latency: Some(1000),
+            })
+            .await
+            .unwrap();
+        }
+        let levels = |reviews: Vec<Review>| -> Vec<i32> {
+            reviews.into_iter().map(|r| r.level_after).collect()
+        };
+        assert_eq!(
+            levels(db.get_user_reviews(CHAT_ID, 1, 5).await.unwrap()),
+            [2, 1]
+        );
+        assert_eq!(
+            levels(db.get_word_reviews(&hund.id).await.unwrap()),
+            [1, 2, 3]
+        );
+    }
+
+    #[tokio::test]
+    async fn challenges_are_answered_once() {
+        let db = MemoryStorage::new();
+        let challenge = Challenge {
+            inline_message_id: "message".to_owned(),
+            chat_id: CHAT_ID,
+            word: "Hund".to_owned(),
+            options: vec!["cat".to_owned(), "dog".to_owned()],
+            correct: 1,
+            answers: vec![],
+        };
+        db.new_challenge(&challenge).await.unwrap();
+        assert!(matches!(
+            db.new_challenge(&challenge).await,
+            Err(StorageError::ChallengeAlreadyExists)
+        ));
+        assert!(db.get_challenge("other").await.unwrap().is_none());
+
+        let answer = ChallengeAnswer {
+            user_id: 2,
+            name: "@friend".to_owned(),
+            correct: true,
+        };
+        db.add_challenge_answer("message", &answer).await.unwrap();
+        assert!(matches!(
+            db.add_challenge_answer("message", &answer).await,
+            Err(StorageError::ChallengeAlreadyAnswered)
+        ));
+        assert!(matches!(
+            db.add_challenge_answer("other", &answer).await,
+            Err(StorageError::ChallengeNotFound)
+        ));
+        let saved = db.get_challenge("message").await.unwrap().unwrap();
+        assert_eq!(saved.options, challenge.options);
+        assert_eq!(saved.answers.len(), 1);
+    }
+}
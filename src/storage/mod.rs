@@ -1,83 +1,422 @@
+pub mod database;
 pub mod error;
+pub mod memory;
 
-use std::ops::{Deref, DerefMut};
+use std::fmt::Debug;
 
+use async_trait::async_trait;
 use chrono::{DateTime, FixedOffset, Utc};
+use chrono_tz::Tz;
 
 use crate::{
-    common::config::TIMINGS,
-    prisma::{
-        self,
-        user::{self},
-        word,
+    common::{
+        config::{REFRESH_INTERVAL, RELEARN_INTERVAL},
+        local_time::{self, QuietHours},
+        normalize,
+        translation::{Strictness, Translation},
     },
+    key_value_enum,
+    scheduler::{order::WordOrder, Progress},
 };
 
-use self::error::{StorageError, StorageResult};
+use self::error::StorageResult;
 
+/// Users reminded at once, the rest of the due ones wait for the next round
 pub static MAX_USERS_TO_REMIND: i64 = 200;
-
-user::include!((filters: Vec<word::WhereParam>) => users_with_words {
-    words(filters)
-});
+static WORDS_PAGE_SIZE: i64 = 100;
 
 #[derive(Debug, Clone)]
 pub struct LiteUser {
     pub chat_id: i64,
     pub next_remind_at: DateTime<FixedOffset>,
     pub remind_every: i32,
+    pub scheduler: String,
+    pub refresh_mastered: bool,
+    pub active_deck_id: Option<String>,
+    pub review_mode: ReviewMode,
+    pub strictness: Strictness,
+    pub directions: Directions,
+    pub word_order: WordOrder,
+    pub timezone: Tz,
+    /// In the user's timezone
+    pub quiet_hours: Option<QuietHours>,
+    pub words_per_page: i32,
+    /// Reminders come without a notification sound
+    pub silent: bool,
+}
+
+impl LiteUser {
+    /// The first moment since `at` the user can be reminded at
+    pub fn allowed_at(&self, at: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        local_time::next_allowed(at, self.timezone, self.quiet_hours)
+    }
+
+    /// When the user is reminded about a card due at `due_at`, the interval
+    /// since the last remind has to pass too
+    pub fn remind_at(&self, due_at: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        self.allowed_at(due_at.max(self.next_remind_at))
+    }
+}
+
+key_value_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ReviewMode {
+        // the user types the translation
+        Typing { text: "typing", key: "typing" },
+        // the user reveals the translation and grades themselves
+        SelfGraded { text: "self-graded", key: "self_graded" },
+        // the user chooses the translation among a few options
+        Quiz { text: "quiz", key: "quiz" },
+        // the user answers a native Telegram quiz poll
+        Poll { text: "poll", key: "poll" },
+    }
+}
+
+impl ReviewMode {
+    pub const ALL: [ReviewMode; 4] = [
+        ReviewMode::Typing,
+        ReviewMode::SelfGraded,
+        ReviewMode::Quiz,
+        ReviewMode::Poll,
+    ];
 }
 
-impl Into<LiteUser> for users_with_words::Data {
-    fn into(self) -> LiteUser {
-        LiteUser {
-            chat_id: self.chat_id,
-            next_remind_at: self.next_remind_at,
-            remind_every: self.remind_every,
+key_value_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum WordStatus {
+        Active { text: "active", key: "active" },
+        Mastered { text: "mastered", key: "mastered" },
+        Suspended { text: "suspended", key: "suspended" },
+    }
+}
+
+key_value_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Direction {
+        Forward { text: "word → translation", key: "forward" },
+        Reverse { text: "translation → word", key: "reverse" },
+    }
+}
+
+impl Direction {
+    pub const ALL: [Direction; 2] = [Direction::Forward, Direction::Reverse];
+}
+
+// Which directions are reviewed
+key_value_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Directions {
+        Forward { text: "word → translation", key: "forward" },
+        Reverse { text: "translation → word", key: "reverse" },
+        Both { text: "word ↔ translation", key: "both" },
+    }
+}
+
+impl Directions {
+    pub const ALL: [Directions; 3] = [Directions::Forward, Directions::Reverse, Directions::Both];
+
+    pub fn includes(&self, direction: Direction) -> bool {
+        match self {
+            Directions::Forward => direction == Direction::Forward,
+            Directions::Reverse => direction == Direction::Reverse,
+            Directions::Both => true,
+        }
+    }
+}
+
+/// Scheduling state of a word reviewed in one direction
+#[derive(Debug, Clone)]
+pub struct Card {
+    pub direction: Direction,
+    pub progress: Progress,
+    pub next_remind_at: DateTime<FixedOffset>,
+    pub reminded_at: DateTime<FixedOffset>,
+}
+
+impl Card {
+    /// Card of a just added word
+    pub fn new(direction: Direction) -> Self {
+        let first_remind = Progress {
+            interval: RELEARN_INTERVAL,
+            ..Progress::default()
+        }
+        .next_remind_at();
+        Self {
+            direction,
+            progress: Progress::default(),
+            next_remind_at: first_remind,
+            reminded_at: now(),
         }
     }
 }
 
-#[derive(Debug)]
-pub struct Storage(prisma::PrismaClient);
+#[derive(Debug, Clone)]
+pub struct Word {
+    pub id: String,
+    pub chat_id: i64,
+    pub created_at: DateTime<FixedOffset>,
+    pub word: String,
+    pub translations: Translation,
+    pub cards: Vec<Card>,
+    pub status: WordStatus,
+    pub deck_id: Option<String>,
+    /// Free-form labels, e.g. from imported files
+    pub tags: Vec<String>,
+}
+
+impl Word {
+    pub fn card(&self, direction: Direction) -> Option<&Card> {
+        self.cards.iter().find(|card| card.direction == direction)
+    }
 
-impl Deref for Storage {
-    type Target = prisma::PrismaClient;
+    pub fn level(&self, direction: Direction) -> i32 {
+        self.card(direction).map_or(0, |card| card.progress.level)
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    /// Cards which are due at `now` in the given directions
+    pub fn due_cards(&self, directions: Directions, now: DateTime<FixedOffset>) -> Vec<DueCard> {
+        self.cards_in(directions)
+            .filter(|due| due.card.next_remind_at <= now)
+            .collect()
+    }
+
+    /// Cards in the given directions whenever they are due
+    pub fn cards_in(&self, directions: Directions) -> impl Iterator<Item = DueCard> + '_ {
+        self.cards
+            .iter()
+            .filter(move |card| directions.includes(card.direction))
+            .map(|card| DueCard {
+                word: self.clone(),
+                card: card.clone(),
+            })
     }
 }
 
-impl DerefMut for Storage {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+#[derive(Debug, Clone, Default)]
+pub struct NewWord {
+    pub word: String,
+    pub translation: Translation,
+    pub deck_id: Option<String>,
+    pub tags: Vec<String>,
+    /// Cards with progress, e.g. from Anki, new cards are created if empty
+    pub cards: Vec<Card>,
+}
+
+impl NewWord {
+    pub fn cards(&self) -> Vec<Card> {
+        if self.cards.is_empty() {
+            Direction::ALL.into_iter().map(Card::new).collect()
+        } else {
+            self.cards.clone()
+        }
+    }
+}
+
+key_value_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum DuplicatePolicy {
+        // the word the user has is kept as it is
+        Skip { text: "skip", key: "skip" },
+        // new translations and tags are added to the word
+        Merge { text: "merge translations", key: "merge" },
+        // translations, tags and deck are replaced, progress is kept
+        Overwrite { text: "overwrite", key: "overwrite" },
+    }
+}
+
+impl DuplicatePolicy {
+    pub const ALL: [DuplicatePolicy; 3] = [
+        DuplicatePolicy::Skip,
+        DuplicatePolicy::Merge,
+        DuplicatePolicy::Overwrite,
+    ];
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub merged: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Deck {
+    pub id: String,
+    pub chat_id: i64,
+    pub created_at: DateTime<FixedOffset>,
+    pub name: String,
+    pub from_lang: Option<String>,
+    pub to_lang: Option<String>,
+    pub reminders_enabled: bool,
+    /// The user's directions are used if `None`
+    pub directions: Option<Directions>,
+}
+
+impl Deck {
+    pub fn languages(&self) -> Option<String> {
+        match (&self.from_lang, &self.to_lang) {
+            (Some(from), Some(to)) => Some(format!("{} → {}", from, to)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WordFilter {
+    /// Words with any status if empty
+    pub statuses: Vec<WordStatus>,
+    /// Part of the word or its translation
+    pub search: Option<String>,
+    /// Words of any deck if `None`
+    pub deck_id: Option<String>,
+}
+
+impl WordFilter {
+    pub fn with_statuses(statuses: &[WordStatus]) -> Self {
+        Self {
+            statuses: statuses.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    pub fn matches(&self, word: &Word) -> bool {
+        let status_matches = self.statuses.is_empty() || self.statuses.contains(&word.status);
+        let deck_matches = self.deck_id.is_none() || self.deck_id == word.deck_id;
+        let search_matches = self.search.as_ref().is_none_or(|search| {
+            let search = normalize::key(search);
+            normalize::key(&word.word).contains(&search)
+                || word
+                    .translations
+                    .texts()
+                    .any(|text| normalize::key(text).contains(&search))
+        });
+        status_matches && deck_matches && search_matches
     }
 }
 
-impl Storage {
-    pub fn new(prisma_client: prisma::PrismaClient) -> Self {
-        Self(prisma_client)
+/// A word to review in one direction
+#[derive(Debug, Clone)]
+pub struct DueCard {
+    pub word: Word,
+    pub card: Card,
+}
+
+/// When the user should be reminded next, users are paged through
+/// by it from the earliest time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RemindTime {
+    pub next_remind_at: DateTime<FixedOffset>,
+    pub chat_id: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserWithCards {
+    pub user: LiteUser,
+    pub cards: Vec<DueCard>,
+}
+
+key_value_enum! {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum ReviewOutcome {
+        Correct { text: "Correct", key: "correct" },
+        Wrong { text: "Wrong", key: "wrong" },
+        Forgot { text: "Forgot", key: "forgot" },
+        Timeout { text: "Timeout", key: "timeout" },
+        AlmostCorrect { text: "Almost correct", key: "almost_correct" },
+        Again { text: "Again", key: "again" },
+        Hard { text: "Hard", key: "hard" },
+        Good { text: "Good", key: "good" },
+        Easy { text: "Easy", key: "easy" },
     }
 }
 
-/* #region User model */
-impl Storage {
-    pub async fn get_user(&self, chat_id: i64) -> StorageResult<Option<user::Data>> {
-        let user = self
-            .user()
-            .find_first(vec![user::chat_id::equals(chat_id)])
-            .exec()
-            .await?;
-        Ok(user)
+#[derive(Debug, Clone)]
+pub struct NewReview {
+    pub word_id: String,
+    pub chat_id: i64,
+    pub outcome: ReviewOutcome,
+    pub direction: Direction,
+    pub level_before: i32,
+    pub level_after: i32,
+    /// Text typed by the user
+    pub answer: Option<String>,
+    /// Time between the question and the answer in milliseconds
+    pub latency: Option<i32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Review {
+    pub id: String,
+    pub created_at: DateTime<FixedOffset>,
+    pub word_id: String,
+    pub chat_id: i64,
+    pub outcome: ReviewOutcome,
+    pub direction: Direction,
+    pub level_before: i32,
+    pub level_after: i32,
+    pub answer: Option<String>,
+    pub latency: Option<i32>,
+}
+
+/// A quiz about a word posted to any chat with an inline query,
+/// everyone in the chat can answer it once
+#[derive(Debug, Clone)]
+pub struct Challenge {
+    pub inline_message_id: String,
+    /// The user who has posted it
+    pub chat_id: i64,
+    pub word: String,
+    pub options: Vec<String>,
+    /// Index of the right option
+    pub correct: usize,
+    /// From the first one
+    pub answers: Vec<ChallengeAnswer>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChallengeAnswer {
+    pub user_id: i64,
+    /// @username or the full name
+    pub name: String,
+    pub correct: bool,
+}
+
+/// Tags followed by the new ones which are not among them yet
+pub fn merge_tags(tags: &[String], new: &[String]) -> Vec<String> {
+    let mut merged = tags.to_vec();
+    for tag in new {
+        if !merged.contains(tag) {
+            merged.push(tag.clone());
+        }
     }
+    merged
+}
 
-    pub async fn new_user(&self, chat_id: i64) -> StorageResult<user::Data> {
-        let user = self.user().create(chat_id, vec![]).exec().await?;
-        Ok(user)
+pub fn now() -> DateTime<FixedOffset> {
+    Utc::now().with_timezone(&FixedOffset::east_opt(0).unwrap())
+}
+
+/// Mastered words are only shown as rare refresher reviews
+pub fn word_next_remind_at(progress: &Progress, status: WordStatus) -> DateTime<FixedOffset> {
+    match status {
+        WordStatus::Mastered => Progress {
+            interval: REFRESH_INTERVAL,
+            ..*progress
+        }
+        .next_remind_at(),
+        _ => progress.next_remind_at(),
     }
+}
+
+#[async_trait]
+pub trait Storage: Send + Sync + Debug {
+    /* #region User model */
+    async fn get_user(&self, chat_id: i64) -> StorageResult<Option<LiteUser>>;
+
+    async fn new_user(&self, chat_id: i64) -> StorageResult<LiteUser>;
 
-    pub async fn ensure_user(&self, chat_id: i64) -> StorageResult<user::Data> {
+    async fn ensure_user(&self, chat_id: i64) -> StorageResult<LiteUser> {
         let user = self.get_user(chat_id).await?;
         if let Some(user) = user {
             Ok(user)
@@ -86,147 +425,210 @@ impl Storage {
         }
     }
 
-    pub async fn update_next_remind_user(&self, id: i64, remind_every: i64) -> StorageResult<()> {
-        let now = Utc::now();
-        let next_remind_at = now + chrono::Duration::seconds(remind_every as i64);
-        let next_remind_at = next_remind_at.with_timezone(&FixedOffset::east_opt(0).unwrap());
-        log::debug!("Next remind user {}: {:?}", id, next_remind_at);
+    /// Returns the new time the user should be reminded at
+    async fn update_next_remind_user(
+        &self,
+        id: i64,
+        remind_every: i64,
+    ) -> StorageResult<DateTime<FixedOffset>>;
 
-        self.user()
-            .update(
-                user::chat_id::equals(id),
-                vec![user::next_remind_at::set(next_remind_at)],
-            )
-            .exec()
-            .await?;
-        Ok(())
-    }
-}
-/* #endregion */
+    /// Times of the users going after the cursor, ordered by the time
+    /// and then by the chat id
+    async fn get_remind_times(
+        &self,
+        after: Option<RemindTime>,
+        take: i64,
+    ) -> StorageResult<Vec<RemindTime>>;
+
+    async fn set_refresh_mastered(&self, chat_id: i64, enabled: bool) -> StorageResult<()>;
+
+    /// `scheduler` is a name of `scheduler::Scheduler`
+    async fn set_scheduler(&self, chat_id: i64, scheduler: &str) -> StorageResult<()>;
+
+    /// Only the interval is changed, see `update_next_remind_user`
+    async fn set_remind_every(&self, chat_id: i64, remind_every: i32) -> StorageResult<()>;
+
+    async fn set_words_per_page(&self, chat_id: i64, words_per_page: i32) -> StorageResult<()>;
+
+    async fn set_silent(&self, chat_id: i64, silent: bool) -> StorageResult<()>;
+
+    async fn set_review_mode(&self, chat_id: i64, mode: ReviewMode) -> StorageResult<()>;
+
+    async fn set_strictness(&self, chat_id: i64, strictness: Strictness) -> StorageResult<()>;
 
-/* #region Word model */
-impl Storage {
-    pub async fn new_word(
+    async fn set_directions(&self, chat_id: i64, directions: Directions) -> StorageResult<()>;
+
+    async fn set_word_order(&self, chat_id: i64, order: WordOrder) -> StorageResult<()>;
+
+    async fn set_timezone(&self, chat_id: i64, timezone: Tz) -> StorageResult<()>;
+
+    /// `None` allows reminders at any time
+    async fn set_quiet_hours(
+        &self,
+        chat_id: i64,
+        quiet_hours: Option<QuietHours>,
+    ) -> StorageResult<()>;
+
+    /// New words are added to the active deck
+    async fn set_active_deck(&self, chat_id: i64, deck_id: Option<&str>) -> StorageResult<()>;
+    /* #endregion */
+
+    /* #region Deck model */
+    /// Fails with `StorageError::DeckAlreadyExists` if the user already has a deck with the name
+    async fn new_deck(
+        &self,
+        chat_id: i64,
+        name: &str,
+        from_lang: Option<String>,
+        to_lang: Option<String>,
+    ) -> StorageResult<Deck>;
+
+    async fn get_decks(&self, chat_id: i64) -> StorageResult<Vec<Deck>>;
+
+    async fn find_deck(&self, chat_id: i64, name: &str) -> StorageResult<Option<Deck>>;
+
+    /// Fails with `StorageError::DeckAlreadyExists` if another deck of the user has the name
+    async fn rename_deck(&self, chat_id: i64, deck_id: &str, name: &str) -> StorageResult<()>;
+
+    /// Words of the deck are kept without a deck
+    async fn remove_deck(&self, deck_id: &str) -> StorageResult<()>;
+
+    async fn set_deck_reminders(&self, deck_id: &str, enabled: bool) -> StorageResult<()>;
+
+    /// `None` makes the deck follow the user's directions
+    async fn set_deck_directions(
+        &self,
+        deck_id: &str,
+        directions: Option<Directions>,
+    ) -> StorageResult<()>;
+    /* #endregion */
+
+    /* #region Word model */
+    /// Fails with `StorageError::WordAlreadyExists` if the user already has the word
+    async fn new_word(
         &self,
         chat_id: i64,
         word: &str,
-        translation: &str,
-    ) -> StorageResult<word::Data> {
-        if self.has_word(chat_id, word).await? {
-            return Err(StorageError::WordAlreadyExists);
+        translation: &Translation,
+        deck_id: Option<&str>,
+    ) -> StorageResult<Word>;
+
+    /// Adds the words in one transaction skipping the ones the user already has.
+    /// Returns the added words.
+    async fn new_words(&self, chat_id: i64, words: &[NewWord]) -> StorageResult<Vec<Word>>;
+
+    /// Adds the words in one transaction, the words the user already has
+    /// (or repeated ones) are handled according to the policy
+    async fn import_words(
+        &self,
+        chat_id: i64,
+        words: &[NewWord],
+        policy: DuplicatePolicy,
+    ) -> StorageResult<ImportSummary>;
+
+    async fn has_word(&self, chat_id: i64, word: &str) -> StorageResult<bool>;
+
+    /// The word compared the same way as duplicates are
+    async fn find_word(&self, chat_id: i64, word: &str) -> StorageResult<Option<Word>>;
+
+    /// Fails with `StorageError::WordAlreadyExists` if the user has another word
+    /// with the same text
+    async fn rename_word(&self, chat_id: i64, word_id: &str, word: &str) -> StorageResult<()>;
+
+    /// Replaces all translations of the word
+    async fn set_translations(&self, word_id: &str, translation: &Translation)
+        -> StorageResult<()>;
+
+    /// Starts learning the word from scratch in all directions
+    async fn reset_word_progress(&self, word_id: &str) -> StorageResult<()>;
+
+    async fn get_words(
+        &self,
+        chat_id: i64,
+        filter: &WordFilter,
+        skip: i64,
+        take: i64,
+    ) -> StorageResult<Vec<Word>>;
+
+    /// All words matching the filter, read page by page
+    async fn get_all_words(&self, chat_id: i64, filter: &WordFilter) -> StorageResult<Vec<Word>> {
+        let mut words = vec![];
+        loop {
+            let page = self
+                .get_words(chat_id, filter, words.len() as i64, WORDS_PAGE_SIZE)
+                .await?;
+            let is_last = (page.len() as i64) < WORDS_PAGE_SIZE;
+            words.extend(page);
+            if is_last {
+                return Ok(words);
+            }
         }
+    }
+
+    async fn words_count(&self, chat_id: i64, filter: &WordFilter) -> StorageResult<i64>;
+
+    async fn remove_word(&self, chat_id: i64, word: &str) -> StorageResult<()>;
+
+    /// Reschedules the card of the direction and updates the word's status
+    async fn update_word_remind(
+        &self,
+        word_id: &str,
+        direction: Direction,
+        progress: &Progress,
+        status: WordStatus,
+    ) -> StorageResult<()>;
+
+    /// Returns `false` if the user doesn't have the word.
+    /// Cards of a word which becomes active again are due now.
+    async fn set_word_status(
+        &self,
+        chat_id: i64,
+        word: &str,
+        status: WordStatus,
+    ) -> StorageResult<bool>;
+
+    /// The users with the ids together with their cards that are due now,
+    /// the longest waiting users first. The cards are empty for users
+    /// who shouldn't be reminded yet.
+    /// Mastered words are included only for users with enabled refresher
+    /// reviews, words of decks with disabled reminders are skipped,
+    /// cards of inactive directions too.
+    async fn find_to_remind(&self, chat_ids: &[i64]) -> StorageResult<Vec<UserWithCards>>;
+
+    /// Cards of the user which are due now, the most overdue first.
+    /// Filtered the same way as in `find_to_remind`.
+    async fn find_due_cards(&self, chat_id: i64) -> StorageResult<Vec<DueCard>>;
+
+    /// When the earliest of the user's cards is due, filtered the same way
+    /// as in `find_to_remind`. `None` if there is nothing to remind.
+    async fn next_due_at(&self, user: &LiteUser) -> StorageResult<Option<DateTime<FixedOffset>>>;
+    /* #endregion */
+
+    /* #region Review model */
+    async fn add_review(&self, review: NewReview) -> StorageResult<Review>;
 
-        let user = self.ensure_user(chat_id).await?;
-
-        let now = Utc::now();
-        let first_remind = now + chrono::Duration::seconds(TIMINGS.get(&0i32).unwrap().to_owned());
-        let first_remind = first_remind.with_timezone(&FixedOffset::east_opt(0).unwrap());
-        let word = self
-            .word()
-            .create(
-                chat_id,
-                word.to_owned(),
-                translation.to_owned(),
-                first_remind,
-                prisma::user::UniqueWhereParam::ChatIdEquals(user.chat_id),
-                vec![],
-            )
-            .exec()
-            .await?;
-        Ok(word)
-    }
-
-    pub async fn has_word(&self, chat_id: i64, word: &str) -> StorageResult<bool> {
-        let has_word = self
-            .word()
-            .find_first(vec![
-                word::word::equals(word.to_owned()),
-                word::chat_id::equals(chat_id),
-            ])
-            .exec()
-            .await?
-            .is_some();
-        Ok(has_word)
-    }
-
-    pub async fn get_words(
+    /// All reviews of the word from the oldest to the newest
+    async fn get_word_reviews(&self, word_id: &str) -> StorageResult<Vec<Review>>;
+
+    /// Reviews of the user from the newest to the oldest
+    async fn get_user_reviews(
         &self,
         chat_id: i64,
         skip: i64,
         take: i64,
-    ) -> StorageResult<Vec<word::Data>> {
-        let words = self
-            .word()
-            .find_many(vec![word::chat_id::equals(chat_id)])
-            .skip(skip)
-            .take(take)
-            .exec()
-            .await?;
-        Ok(words)
-    }
-
-    pub async fn words_count(&self, chat_id: i64) -> StorageResult<i64> {
-        let count = self
-            .word()
-            .count(vec![word::chat_id::equals(chat_id)])
-            .exec()
-            .await?;
-        Ok(count)
-    }
-
-    pub async fn remove_word(&self, chat_id: i64, word: &str) -> StorageResult<()> {
-        self.word()
-            .delete_many(vec![
-                word::word::equals(word.to_owned()),
-                word::chat_id::equals(chat_id),
-            ])
-            .exec()
-            .await?;
-        Ok(())
-    }
-
-    pub async fn remove_word_by_id(&self, word_id: impl Into<String>) -> StorageResult<()> {
-        self.word()
-            .delete(word::id::equals(word_id.into()))
-            .exec()
-            .await?;
-        Ok(())
-    }
-
-    pub async fn update_word_remind(
+    ) -> StorageResult<Vec<Review>>;
+    /* #endregion */
+
+    /* #region Challenge model */
+    async fn new_challenge(&self, challenge: &Challenge) -> StorageResult<()>;
+
+    async fn get_challenge(&self, inline_message_id: &str) -> StorageResult<Option<Challenge>>;
+
+    /// Fails if the user has already answered the challenge
+    async fn add_challenge_answer(
         &self,
-        word_id: impl Into<String>,
-        next_remind_at: DateTime<FixedOffset>,
-        new_level: i32,
-    ) -> StorageResult<()> {
-        self.word()
-            .update(
-                word::id::equals(word_id.into()),
-                vec![
-                    word::next_remind_at::set(next_remind_at),
-                    word::remember_level::set(new_level),
-                ],
-            )
-            .exec()
-            .await?;
-        Ok(())
-    }
-
-    pub async fn find_to_remind(&self) -> StorageResult<Vec<users_with_words::Data>> {
-        let now = Utc::now();
-        let fixed_now = now.with_timezone(&FixedOffset::east_opt(0).unwrap());
-        let word_filters = vec![word::next_remind_at::lte(fixed_now)];
-        let users = self
-            .user()
-            .find_many(vec![user::next_remind_at::lte(fixed_now)])
-            .take(MAX_USERS_TO_REMIND)
-            .include(users_with_words::include(word_filters))
-            .exec()
-            .await?;
-
-        log::debug!("Found {} users to remind", users.len());
-
-        Ok(users)
-    }
-}
-/* #endregion */
+        inline_message_id: &str,
+        answer: &ChallengeAnswer,
+    ) -> StorageResult<()>;
+    /* #endregion */
+}
@@ -0,0 +1,203 @@
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use teloxide::types::{CallbackQuery, ChatId, Message};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    challenges::Challenges,
+    reminder::queue::RemindQueue,
+    state::Context,
+    storage::{memory::MemoryStorage, Storage},
+};
+
+pub static CHAT_ID: i64 = 1;
+
+/// Context of the test user with a bot which isn't connected anywhere
+pub fn context(db: Arc<MemoryStorage>) -> Context {
+    context_with_bot(db, teloxide::Bot::new("test"))
+}
+
+pub fn context_with_bot(db: Arc<MemoryStorage>, bot: teloxide::Bot) -> Context {
+    let db: Arc<dyn Storage> = db;
+    Context::new(
+        bot,
+        ChatId(CHAT_ID),
+        db.clone(),
+        Challenges::new(db),
+        RemindQueue::default(),
+    )
+}
+
+/// A request the bot has sent to the Bot API
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub body: Value,
+}
+
+/// Bot API on a local port which records the requests and answers every
+/// method with a plausible result, messages echo the sent text
+#[derive(Debug, Clone)]
+pub struct FakeTelegram {
+    bot: teloxide::Bot,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl FakeTelegram {
+    pub async fn start() -> FakeTelegram {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(vec![]));
+        tokio::spawn({
+            let requests = requests.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(serve(stream, requests.clone()));
+                }
+            }
+        });
+
+        let client = teloxide::net::default_reqwest_settings()
+            .no_proxy()
+            .build()
+            .unwrap();
+        let bot = teloxide::Bot::with_client("test", client);
+        let url = bot.api_url().join(&format!("http://{}/", address)).unwrap();
+        FakeTelegram {
+            bot: bot.set_api_url(url),
+            requests,
+        }
+    }
+
+    pub fn bot(&self) -> teloxide::Bot {
+        self.bot.clone()
+    }
+
+    /// Texts of the sent and edited messages, forgets all requests
+    pub fn texts(&self) -> Vec<String> {
+        std::mem::take(&mut *self.requests.lock().unwrap())
+            .into_iter()
+            .filter(|request| ["sendMessage", "editMessageText"].contains(&request.method.as_str()))
+            .filter_map(|request| request.body["text"].as_str().map(str::to_owned))
+            .collect()
+    }
+}
+
+// one request per connection, the client is told to close it
+async fn serve(stream: TcpStream, requests: Arc<Mutex<Vec<Request>>>) {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await.unwrap();
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).await.unwrap();
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap();
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await.unwrap();
+
+    // "POST /bot<token>/<Method> HTTP/1.1"
+    let method = request_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|path| path.rsplit('/').next())
+        .unwrap_or_default();
+    // teloxide writes the names capitalized, the API ignores the case
+    let mut chars = method.chars();
+    let method: String = chars
+        .next()
+        .map(|first| first.to_ascii_lowercase())
+        .into_iter()
+        .chain(chars)
+        .collect();
+    let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let result = response(&method, &body, requests.lock().unwrap().len());
+    requests.lock().unwrap().push(Request { method, body });
+
+    let content = json!({ "ok": true, "result": result }).to_string();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        content.len(),
+        content
+    );
+    let mut stream = reader.into_inner();
+    stream.write_all(response.as_bytes()).await.unwrap();
+    stream.shutdown().await.unwrap();
+}
+
+fn response(method: &str, body: &Value, id: usize) -> Value {
+    match method {
+        "getMe" => json!({
+            "id": 1000,
+            "is_bot": true,
+            "first_name": "Reminder",
+            "username": "reminder_bot",
+            "can_join_groups": false,
+            "can_read_all_group_messages": false,
+            "supports_inline_queries": true,
+        }),
+        "sendPoll" => {
+            let mut message = message_json(body["question"].as_str().unwrap_or_default(), id);
+            message["poll"] = json!({
+                "id": format!("poll{}", id),
+                "question": body["question"],
+                "options": [],
+                "total_voter_count": 0,
+                "is_closed": false,
+                "is_anonymous": false,
+                "type": "quiz",
+                "allows_multiple_answers": false,
+            });
+            message.as_object_mut().unwrap().remove("text");
+            message
+        }
+        "sendMessage" | "editMessageText" => {
+            message_json(body["text"].as_str().unwrap_or_default(), id)
+        }
+        _ => json!(true),
+    }
+}
+
+fn user_json() -> Value {
+    json!({ "id": CHAT_ID, "is_bot": false, "first_name": "Test" })
+}
+
+fn message_json(text: &str, id: usize) -> Value {
+    json!({
+        "message_id": id,
+        "date": 0,
+        "chat": { "id": CHAT_ID, "type": "private", "first_name": "Test" },
+        "from": user_json(),
+        "text": text,
+    })
+}
+
+/// A text message from the test user
+pub fn message(text: &str) -> Message {
+    serde_json::from_value(message_json(text, 0)).unwrap()
+}
+
+/// A press of an inline button of a message sent to the test user
+pub fn callback_query(data: &str) -> CallbackQuery {
+    serde_json::from_value(json!({
+        "id": "query",
+        "from": user_json(),
+        "chat_instance": "chat",
+        "data": data,
+        "message": message_json("", 0),
+    }))
+    .unwrap()
+}